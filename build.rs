@@ -1,10 +1,740 @@
+//! Build script
+//!
+//! Besides linking libgcc on AVR, this turns the files in `assets/` into
+//! Rust tables so melodies and bitmaps can be edited as text:
+//!
+//! - `assets/melodies/*.rtttl` and `*.abc` become `&[Note]` tables in `melodies.rs`
+//! - `assets/bitmaps/*.txt` (8 lines of 8 `#`/`.` characters) become `[u8; 8]`
+//!   row tables in `bitmaps.rs`
+//! - `assets/animations/*.txt` (bitmaps, each after a `frame <ms>` line)
+//!   become `[Frame; N]` tables in program memory in `animations.rs`
+//!
+//! The files are written to `OUT_DIR` and pulled in by `src/melody.rs`,
+//! `src/bitmaps.rs` and `src/animations.rs`. Invalid assets fail the build
+//! with `file:line: message`.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+// Reuse the firmware's RTTTL parser so both accept exactly the same songs
+#[cfg(not(test))]
+#[allow(dead_code)]
+#[path = "src/tone.rs"]
+mod tone;
+
+#[cfg(not(test))]
+#[allow(dead_code)]
+#[path = "src/rtttl.rs"]
+mod rtttl;
+
+// The library's unit tests include this file to test the asset parsers
+#[cfg(test)]
+use crate::{rtttl, tone};
+
+use tone::Note;
+
 fn main() {
     // For AVR targets, we need to link against avr-libc for certain symbols
     // The 'exit' symbol is required by the AVR startup code
     let target = std::env::var("TARGET").unwrap_or_default();
-    
+
     if target.starts_with("avr") {
         // Tell cargo to pass -lgcc to the linker to get the exit symbol
         println!("cargo:rustc-link-lib=gcc");
     }
+
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    let assets = manifest_dir.join("assets");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/tone.rs");
+    println!("cargo:rerun-if-changed=src/rtttl.rs");
+    println!("cargo:rerun-if-changed=assets");
+
+    let melodies = generate_melodies(&assets.join("melodies"));
+    let bitmaps = generate_bitmaps(&assets.join("bitmaps"));
+    let animations = generate_animations(&assets.join("animations"));
+    fs::write(out_dir.join("melodies.rs"), melodies).unwrap();
+    fs::write(out_dir.join("bitmaps.rs"), bitmaps).unwrap();
+    fs::write(out_dir.join("animations.rs"), animations).unwrap();
+}
+
+/// An invalid asset: 1-based line number and what is wrong with it
+#[derive(Debug)]
+struct AssetError {
+    line: usize,
+    message: String,
+}
+
+impl AssetError {
+    fn new(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Stop the build, pointing at the offending asset line.
+fn fail(path: &Path, error: AssetError) -> ! {
+    eprintln!(
+        "error: {}:{}: {}",
+        path.display(),
+        error.line,
+        error.message
+    );
+    process::exit(1);
+}
+
+/// Asset files in `dir` with the given extensions, sorted by name.
+fn asset_files(dir: &Path, extensions: &[&str]) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            extensions.contains(&extension)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// `happy-birthday.rtttl` -> (`happy-birthday`, `HAPPY_BIRTHDAY`)
+///
+/// `taken` holds the identifiers already used in the same generated file,
+/// so `mario.rtttl` and `mario.abc` fail here instead of as a duplicate
+/// `const` in `OUT_DIR`.
+fn asset_names(
+    path: &Path,
+    taken: &mut HashMap<String, PathBuf>,
+) -> Result<(String, String), AssetError> {
+    let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+    let ident: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) || ident == "ALL" {
+        return Err(AssetError::new(
+            1,
+            "file name must start with a letter and not be `all`",
+        ));
+    }
+    if let Some(other) = taken.insert(ident.clone(), path.to_path_buf()) {
+        let message = format!(
+            "`{}` and `{}` both become `{ident}`",
+            other.file_name().unwrap().to_string_lossy(),
+            path.file_name().unwrap().to_string_lossy(),
+        );
+        return Err(AssetError::new(1, message));
+    }
+    Ok((name, ident))
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| fail(path, AssetError::new(1, e.to_string())))
+}
+
+fn generate_melodies(dir: &Path) -> String {
+    let mut code = String::from("// Generated by build.rs from assets/melodies - do not edit\n\n");
+    let mut all = Vec::new();
+    let mut taken = HashMap::new();
+
+    for path in asset_files(dir, &["rtttl", "abc"]) {
+        let text = read(&path);
+        let parsed = match path.extension().unwrap().to_str() {
+            Some("abc") => parse_abc(&text),
+            _ => parse_rtttl(&text),
+        };
+        let notes = parsed.unwrap_or_else(|error| fail(&path, error));
+        if notes.is_empty() {
+            fail(&path, AssetError::new(1, "melody has no notes"));
+        }
+
+        let (name, ident) =
+            asset_names(&path, &mut taken).unwrap_or_else(|error| fail(&path, error));
+        writeln!(
+            code,
+            "/// `assets/melodies/{}`",
+            path.file_name().unwrap().to_string_lossy()
+        )
+        .unwrap();
+        writeln!(code, "pub const {ident}: &[Note] = &[").unwrap();
+        for note in notes {
+            writeln!(
+                code,
+                "    Note::new({}, {}),",
+                note.freq_hz, note.duration_ms
+            )
+            .unwrap();
+        }
+        code.push_str("];\n\n");
+        all.push((name, ident));
+    }
+
+    code.push_str("/// Every melody in `assets/melodies`, by file name\n");
+    code.push_str("pub const ALL: &[(&str, &[Note])] = &[\n");
+    for (name, ident) in all {
+        writeln!(code, "    (\"{name}\", {ident}),").unwrap();
+    }
+    code.push_str("];\n");
+    code
+}
+
+fn generate_bitmaps(dir: &Path) -> String {
+    let mut code = String::from("// Generated by build.rs from assets/bitmaps - do not edit\n\n");
+    let mut all = Vec::new();
+    let mut taken = HashMap::new();
+
+    for path in asset_files(dir, &["txt"]) {
+        let rows = parse_bitmap(&read(&path)).unwrap_or_else(|error| fail(&path, error));
+        let (name, ident) =
+            asset_names(&path, &mut taken).unwrap_or_else(|error| fail(&path, error));
+        writeln!(
+            code,
+            "/// `assets/bitmaps/{}`",
+            path.file_name().unwrap().to_string_lossy()
+        )
+        .unwrap();
+        writeln!(code, "pub const {ident}: [u8; 8] = [").unwrap();
+        for row in rows {
+            writeln!(code, "    0b{row:08b},").unwrap();
+        }
+        code.push_str("];\n\n");
+        all.push((name, ident));
+    }
+
+    code.push_str("/// Every bitmap in `assets/bitmaps`, by file name\n");
+    code.push_str("pub const ALL: &[(&str, [u8; 8])] = &[\n");
+    for (name, ident) in all {
+        writeln!(code, "    (\"{name}\", {ident}),").unwrap();
+    }
+    code.push_str("];\n");
+    code
+}
+
+fn generate_animations(dir: &Path) -> String {
+    let mut code =
+        String::from("// Generated by build.rs from assets/animations - do not edit\n\n");
+    let mut all = Vec::new();
+    let mut taken = HashMap::new();
+
+    for path in asset_files(dir, &["txt"]) {
+        let frames = parse_animation(&read(&path)).unwrap_or_else(|error| fail(&path, error));
+        let (name, ident) =
+            asset_names(&path, &mut taken).unwrap_or_else(|error| fail(&path, error));
+        writeln!(
+            code,
+            "/// `assets/animations/{}`",
+            path.file_name().unwrap().to_string_lossy()
+        )
+        .unwrap();
+        code.push_str("#[cfg_attr(target_arch = \"avr\", link_section = \".progmem.data\")]\n");
+        writeln!(code, "pub static {ident}: [Frame; {}] = [", frames.len()).unwrap();
+        for (rows, duration_ms) in frames {
+            let rows: Vec<String> = rows.iter().map(|row| format!("0b{row:08b}")).collect();
+            writeln!(
+                code,
+                "    Frame::new([{}], {duration_ms}),",
+                rows.join(", ")
+            )
+            .unwrap();
+        }
+        code.push_str("];\n\n");
+        all.push((name, ident));
+    }
+
+    code.push_str("/// Every animation in `assets/animations`, by file name\n");
+    code.push_str("pub static ALL: &[(&str, &[Frame])] = &[\n");
+    for (name, ident) in all {
+        writeln!(code, "    (\"{name}\", &{ident}),").unwrap();
+    }
+    code.push_str("];\n");
+    code
+}
+
+/// Parse the frames of an animation: a `frame <ms>` line followed by an
+/// 8x8 bitmap, for every frame.
+fn parse_animation(text: &str) -> Result<Vec<([u8; 8], u16)>, AssetError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let headers: Vec<usize> = (0..lines.len())
+        .filter(|&i| lines[i].trim_start().starts_with("frame"))
+        .collect();
+    if let Some(first) = lines.iter().position(|line| !line.trim().is_empty()) {
+        if headers.first() != Some(&first) {
+            return Err(AssetError::new(first + 1, "expected `frame <ms>`"));
+        }
+    }
+
+    let mut frames = Vec::new();
+    for (n, &header) in headers.iter().enumerate() {
+        let value = lines[header].trim().strip_prefix("frame").unwrap().trim();
+        let duration_ms = value
+            .strip_suffix("ms")
+            .unwrap_or(value)
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|&ms| ms > 0)
+            .ok_or_else(|| {
+                AssetError::new(header + 1, format!("invalid frame duration `{value}`"))
+            })?;
+
+        // Parse the bitmap as if it were its own file, then point errors
+        // back at the animation's lines: its line 1 follows the header,
+        // which is line `header + 1` of the file
+        let end = headers.get(n + 1).copied().unwrap_or(lines.len());
+        let bitmap = lines[header + 1..end].join("\n");
+        let rows = parse_bitmap(&bitmap)
+            .map_err(|error| AssetError::new(header + 1 + error.line, error.message))?;
+        frames.push((rows, duration_ms));
+    }
+
+    if frames.is_empty() {
+        return Err(AssetError::new(1, "animation has no frames"));
+    }
+    Ok(frames)
+}
+
+/// Parse an 8x8 ASCII-art bitmap; column `n` becomes bit `n` of its row.
+fn parse_bitmap(text: &str) -> Result<[u8; 8], AssetError> {
+    let mut rows = [0u8; 8];
+    let mut count = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        if count == 8 {
+            return Err(AssetError::new(line_number, "expected 8 rows, found more"));
+        }
+        if line.chars().count() != 8 {
+            return Err(AssetError::new(
+                line_number,
+                format!("expected 8 columns, found {}", line.chars().count()),
+            ));
+        }
+        for (column, c) in line.chars().enumerate() {
+            match c {
+                '#' => rows[count] |= 1 << column,
+                '.' => {}
+                _ => {
+                    return Err(AssetError::new(
+                        line_number,
+                        format!("column {}: expected `#` or `.`, found `{c}`", column + 1),
+                    ))
+                }
+            }
+        }
+        count += 1;
+    }
+
+    if count != 8 {
+        let last_line = text.lines().count().max(1);
+        return Err(AssetError::new(
+            last_line,
+            format!("expected 8 rows, found {count}"),
+        ));
+    }
+    Ok(rows)
+}
+
+fn parse_rtttl(text: &str) -> Result<Vec<Note>, AssetError> {
+    let song = rtttl::Song::parse(text.trim_end()).map_err(|error| {
+        let (position, message) = match error {
+            rtttl::Error::MissingSection => (0, "expected `name:defaults:notes`"),
+            rtttl::Error::InvalidDefault { position } => (position, "invalid default"),
+            rtttl::Error::InvalidNote { position } => (position, "invalid note"),
+        };
+        let line = text[..position].matches('\n').count() + 1;
+        let column = position - text[..position].rfind('\n').map_or(0, |i| i + 1) + 1;
+        AssetError::new(line, format!("column {column}: {message}"))
+    })?;
+    Ok(song.notes().collect())
+}
+
+/// Parse the subset of ABC notation used for simple melodies.
+///
+/// Supported: the `L:` (unit length), `Q:` (tempo) and `K:` (key) headers,
+/// notes `A-G a-g` with `^`/`_`/`=` accidentals, `'`/`,` octave marks and
+/// lengths like `2`, `/2` or `3/2`, rests `z` and bar lines. Chords, ties,
+/// triplets and grace notes are rejected.
+fn parse_abc(text: &str) -> Result<Vec<Note>, AssetError> {
+    let mut unit = (1, 8);
+    let mut whole_ms = 2000; // 1/4=120
+    let mut key = [0i8; 7];
+    let mut notes = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let code = line.split('%').next().unwrap();
+        let line = code.trim();
+        let error = |message: String| AssetError::new(line_number, message);
+
+        // Header fields look like `K:G`
+        let bytes = line.as_bytes();
+        if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            let value = line[2..].trim();
+            match bytes[0] {
+                b'L' => {
+                    unit = parse_fraction(value)
+                        .ok_or_else(|| error(format!("invalid unit length `{value}`")))?
+                }
+                b'Q' => {
+                    whole_ms = parse_tempo(value)
+                        .ok_or_else(|| error(format!("invalid tempo `{value}`")))?
+                }
+                b'K' => {
+                    key = key_signature(value)
+                        .ok_or_else(|| error(format!("unsupported key `{value}`")))?
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let mut bar = key;
+        // Columns count from the start of the line, indentation included
+        let chars: Vec<char> = code.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let column = i + 1;
+            match chars[i] {
+                ' ' | '\t' => i += 1,
+                '|' | ':' | ']' => {
+                    // Accidentals only last until the end of the bar
+                    bar = key;
+                    i += 1;
+                }
+                '^' | '_' | '=' | 'A'..='G' | 'a'..='g' | 'z' => {
+                    // Accidental
+                    let mut accidental = None;
+                    while i < chars.len() && matches!(chars[i], '^' | '_' | '=') {
+                        let step = match chars[i] {
+                            '^' => 1,
+                            '_' => -1,
+                            _ => 0,
+                        };
+                        accidental = Some(accidental.unwrap_or(0) + step);
+                        i += 1;
+                    }
+
+                    // Pitch
+                    let letter = *chars
+                        .get(i)
+                        .ok_or_else(|| error(format!("column {column}: missing note")))?;
+                    i += 1;
+                    let pitch = match letter {
+                        'z' if accidental.is_none() => None,
+                        'A'..='G' => Some((letter, 4)),
+                        'a'..='g' => Some((letter.to_ascii_uppercase(), 5)),
+                        _ => {
+                            return Err(error(format!(
+                                "column {column}: expected a note, found `{letter}`"
+                            )))
+                        }
+                    };
+
+                    // Octave marks
+                    let mut octave_shift = 0i32;
+                    while i < chars.len() && matches!(chars[i], '\'' | ',') {
+                        octave_shift += if chars[i] == '\'' { 1 } else { -1 };
+                        i += 1;
+                    }
+
+                    // Length multiplier
+                    let start = i;
+                    while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '/') {
+                        i += 1;
+                    }
+                    let length: String = chars[start..i].iter().collect();
+                    let (num, den) = parse_length(&length).ok_or_else(|| {
+                        error(format!("column {}: invalid length `{length}`", start + 1))
+                    })?;
+                    let duration = whole_ms
+                        .checked_mul(unit.0)
+                        .and_then(|ms| ms.checked_mul(num))
+                        .zip(unit.1.checked_mul(den))
+                        .and_then(|(ms, den)| u16::try_from(ms / den).ok())
+                        .ok_or_else(|| {
+                            error(format!("column {column}: note longer than 65535 ms"))
+                        })?;
+
+                    let Some((letter, octave)) = pitch else {
+                        notes.push(Note::rest(duration));
+                        continue;
+                    };
+                    let degree = "CDEFGAB".find(letter).unwrap();
+                    if let Some(accidental) = accidental {
+                        bar[degree] = accidental;
+                    }
+                    let semitone = [0, 2, 4, 5, 7, 9, 11][degree] + bar[degree] as i32;
+                    let octave = octave + octave_shift + semitone.div_euclid(12);
+                    if !(1..=8).contains(&octave) {
+                        return Err(error(format!("column {column}: note out of range")));
+                    }
+                    let freq = rtttl::frequency(semitone.rem_euclid(12) as u8, octave as u8);
+                    notes.push(Note::new(freq, duration));
+                }
+                other => {
+                    return Err(error(format!(
+                        "column {column}: unsupported symbol `{other}`"
+                    )))
+                }
+            }
+        }
+    }
+    Ok(notes)
+}
+
+/// `3/8` -> (3, 8)
+fn parse_fraction(value: &str) -> Option<(u32, u32)> {
+    let (num, den) = value.split_once('/')?;
+    let (num, den) = (num.trim().parse().ok()?, den.trim().parse().ok()?);
+    (num > 0 && den > 0).then_some((num, den))
+}
+
+/// Note length suffix: ``, `2`, `/`, `/4`, `3/2`
+fn parse_length(value: &str) -> Option<(u32, u32)> {
+    let (num, den) = match value.split_once('/') {
+        None => (value, "1"),
+        Some((num, "")) => (num, "2"),
+        Some((num, den)) => (num, den),
+    };
+    let num = if num.is_empty() { 1 } else { num.parse().ok()? };
+    let den = den.parse().ok()?;
+    (num > 0 && den > 0).then_some((num, den))
+}
+
+/// `Q:1/4=120` or `Q:120` (quarter notes) -> length of a whole note in ms
+fn parse_tempo(value: &str) -> Option<u32> {
+    let (beat, bpm) = match value.split_once('=') {
+        Some((beat, bpm)) => (parse_fraction(beat)?, bpm),
+        None => ((1, 4), value),
+    };
+    let bpm: u32 = bpm.trim().parse().ok().filter(|&bpm| bpm > 0)?;
+    let whole_ms = 60_000u32.checked_mul(beat.1)? / beat.0.checked_mul(bpm)?;
+    (whole_ms > 0).then_some(whole_ms)
+}
+
+/// Accidentals of the key signature for C D E F G A B.
+fn key_signature(value: &str) -> Option<[i8; 7]> {
+    #[rustfmt::skip]
+    const MAJOR: [(&str, i8); 15] = [
+        ("C", 0), ("G", 1), ("D", 2), ("A", 3), ("E", 4), ("B", 5), ("F#", 6), ("C#", 7),
+        ("F", -1), ("Bb", -2), ("Eb", -3), ("Ab", -4), ("Db", -5), ("Gb", -6), ("Cb", -7),
+    ];
+    #[rustfmt::skip]
+    const MINOR: [(&str, i8); 15] = [
+        ("A", 0), ("E", 1), ("B", 2), ("F#", 3), ("C#", 4), ("G#", 5), ("D#", 6), ("A#", 7),
+        ("D", -1), ("G", -2), ("C", -3), ("F", -4), ("Bb", -5), ("Eb", -6), ("Ab", -7),
+    ];
+
+    let value = value.split_whitespace().next().unwrap_or("C");
+    let (tonic, table) = match value.strip_suffix('m') {
+        Some(tonic) => (tonic, &MINOR),
+        None => (value.strip_suffix("maj").unwrap_or(value), &MAJOR),
+    };
+    let count = table.iter().find(|(name, _)| *name == tonic)?.1;
+
+    // Sharps are added in the order F C G D A E B, flats in the reverse order
+    let order = if count > 0 { "FCGDAEB" } else { "BEADGCF" };
+    let mut key = [0i8; 7];
+    for letter in order.chars().take(count.unsigned_abs() as usize) {
+        key["CDEFGAB".find(letter).unwrap()] = count.signum();
+    }
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abc(text: &str) -> Vec<Note> {
+        parse_abc(text).unwrap()
+    }
+
+    fn abc_freqs(text: &str) -> Vec<u16> {
+        abc(text).iter().map(|note| note.freq_hz).collect()
+    }
+
+    fn abc_error(text: &str) -> (usize, String) {
+        let error = parse_abc(text).err().unwrap();
+        (error.line, error.message)
+    }
+
+    #[test]
+    fn abc_lengths() {
+        // An eighth note at 1/4=120 is 250 ms
+        let durations: Vec<u16> = abc("L:1/8\nQ:1/4=120\nC C2 C/2 C3/2 C/ z4\n")
+            .iter()
+            .map(|note| note.duration_ms)
+            .collect();
+        assert_eq!(durations, [250, 500, 125, 375, 125, 1000]);
+
+        assert_eq!(
+            abc("L:1/4\nQ:60\nc z"),
+            [Note::new(rtttl::frequency(0, 5), 1000), Note::rest(1000)]
+        );
+    }
+
+    #[test]
+    fn abc_accidentals_last_until_the_bar_line() {
+        let (f, f_sharp) = (rtttl::frequency(5, 4), rtttl::frequency(6, 4));
+        assert_eq!(abc_freqs("^F F | F"), [f_sharp, f_sharp, f]);
+        // A natural holds for the rest of the bar too, in every octave
+        assert_eq!(
+            abc_freqs("_B =B b | B"),
+            [
+                rtttl::frequency(10, 4),
+                rtttl::frequency(11, 4),
+                rtttl::frequency(11, 5),
+                rtttl::frequency(11, 4),
+            ]
+        );
+        // B sharp is the next octave's C
+        assert_eq!(abc_freqs("^B"), [rtttl::frequency(0, 5)]);
+    }
+
+    #[test]
+    fn abc_key_signatures() {
+        assert_eq!(key_signature("C"), Some([0; 7]));
+        assert_eq!(key_signature("G"), Some([0, 0, 0, 1, 0, 0, 0]));
+        assert_eq!(key_signature("Dmaj"), Some([1, 0, 0, 1, 0, 0, 0]));
+        assert_eq!(key_signature("Bb"), Some([0, 0, -1, 0, 0, 0, -1]));
+        assert_eq!(key_signature("Em"), key_signature("G"));
+        assert_eq!(key_signature("H"), None);
+
+        // The key holds in every bar, accidentals only until the bar line
+        let (f, f_sharp) = (rtttl::frequency(5, 4), rtttl::frequency(6, 4));
+        assert_eq!(abc_freqs("K:G\nF =F | F"), [f_sharp, f, f_sharp]);
+    }
+
+    #[test]
+    fn abc_rejects_overflow() {
+        let too_long = "column 1: note longer than 65535 ms".to_string();
+        // Too long for a u16
+        assert_eq!(abc_error("C99999"), (1, too_long.clone()));
+        // Too long for the u32 it is computed in
+        assert_eq!(abc_error("K:C\nC9999999"), (2, too_long));
+        assert_eq!(
+            abc_error("X:1\nQ:1/100000=1"),
+            (2, "invalid tempo `1/100000=1`".to_string())
+        );
+        assert_eq!(abc_error("Q:0"), (1, "invalid tempo `0`".to_string()));
+    }
+
+    #[test]
+    fn abc_error_positions() {
+        // Columns count the indentation
+        assert_eq!(
+            abc_error("K:C\n  x"),
+            (2, "column 3: unsupported symbol `x`".to_string())
+        );
+        assert_eq!(
+            abc_error("C D |\n\tE2 F/0"),
+            (2, "column 6: invalid length `/0`".to_string())
+        );
+        assert_eq!(
+            abc_error("C [CEG] % chords"),
+            (1, "column 3: unsupported symbol `[`".to_string())
+        );
+        assert_eq!(abc_error("K:H"), (1, "unsupported key `H`".to_string()));
+    }
+
+    #[test]
+    fn rtttl_error_positions() {
+        assert_eq!(parse_rtttl("x:d=4,o=5,b=120:c,8e6,p").unwrap().len(), 3);
+        let error = parse_rtttl("x:d=4,o=5,b=120:\nc,x").err().unwrap();
+        assert_eq!(
+            (error.line, error.message.as_str()),
+            (2, "column 3: invalid note")
+        );
+    }
+
+    #[test]
+    fn bitmap_errors() {
+        let rows = "#.......\n".repeat(8);
+        assert_eq!(parse_bitmap(&rows).unwrap(), [1; 8]);
+
+        let error = |text: &str| {
+            let error = parse_bitmap(text).err().unwrap();
+            (error.line, error.message)
+        };
+        assert_eq!(
+            error(&format!("{rows}\n#.......\n")),
+            (10, "expected 8 rows, found more".to_string())
+        );
+        assert_eq!(
+            error("#......\n"),
+            (1, "expected 8 columns, found 7".to_string())
+        );
+        assert_eq!(
+            error("........\n#...o...\n"),
+            (2, "column 5: expected `#` or `.`, found `o`".to_string())
+        );
+        assert_eq!(
+            error("########\n\n########\n"),
+            (3, "expected 8 rows, found 2".to_string())
+        );
+    }
+
+    #[test]
+    fn asset_identifiers() {
+        let mut taken = HashMap::new();
+        let names = asset_names(Path::new("melodies/happy-birthday.rtttl"), &mut taken).unwrap();
+        assert_eq!(names, ("happy-birthday".into(), "HAPPY_BIRTHDAY".into()));
+
+        asset_names(Path::new("melodies/mario.abc"), &mut taken).unwrap();
+        let error = asset_names(Path::new("melodies/mario.rtttl"), &mut taken)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.message,
+            "`mario.abc` and `mario.rtttl` both become `MARIO`"
+        );
+        assert!(asset_names(Path::new("melodies/happy_birthday.abc"), &mut taken).is_err());
+
+        assert!(asset_names(Path::new("all.txt"), &mut HashMap::new()).is_err());
+        assert!(asset_names(Path::new("8ball.txt"), &mut HashMap::new()).is_err());
+    }
+
+    fn error_line(text: &str) -> usize {
+        parse_animation(text).err().unwrap().line
+    }
+
+    #[test]
+    fn animation_errors_point_at_the_file_line() {
+        let rows = "#.......\n".repeat(8);
+        assert_eq!(
+            parse_animation(&format!("frame 100\n{rows}frame 50ms\n{rows}"))
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // Bad character in the second frame, on line 12 of the file
+        let text = format!("frame 100\n{rows}frame 100\n#.......\n#..x....\n");
+        assert_eq!(error_line(&text), 12);
+
+        // Second frame too short, its last row on line 13
+        let text = format!("frame 100\n{rows}frame 100\n{}", "#.......\n".repeat(3));
+        assert_eq!(error_line(&text), 13);
+
+        assert_eq!(error_line(&format!("frame 0\n{rows}")), 1);
+    }
 }
@@ -0,0 +1,121 @@
+//! Matrix Animations
+//!
+//! This example plays the animations from `assets/animations/` on the 8x8 LED matrix
+//! (a bouncing ball, a heartbeat and a spinner). The frames stay in flash and are
+//! timed by the millisecond clock, so the button is read the whole time.
+//! Click the button for the next animation, double-click to switch between looping,
+//! ping-pong and playing once, and hold it to try the next transition between frames.
+//!
+//! ## Hardware Connections
+//! - **8x8 LED Matrix Module with HT16K33**:
+//!   - VCC → 5V on Arduino
+//!   - GND → GND on Arduino
+//!   - SDA → A4 (SDA) on Arduino Uno
+//!   - SCL → A5 (SCL) on Arduino Uno
+//! - **Button Module**:
+//!   - G (GND) → GND on Arduino
+//!   - V (VCC) → Not connected (using internal pull-up)
+//!   - S (Signal) → Pin D2
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example animation`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
+//! Add a file to `assets/animations/` to add an animation.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+use arduino_hal::I2c;
+use rust_sensor_playground::animation::{Animator, Direction, Playback, Transition};
+use rust_sensor_playground::animations;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Duration, Instant};
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Orientation};
+use rust_sensor_playground::settings::SettingsStore;
+
+const PLAYBACKS: [Playback; 3] = [Playback::Loop, Playback::PingPong, Playback::Once];
+
+// (transition, duration in ms)
+const TRANSITIONS: [(Transition, u32); 5] = [
+    (Transition::Cut, 0),
+    (Transition::Dissolve, 60),
+    (Transition::Slide(Direction::Left), 80),
+    (Transition::Wipe(Direction::Down), 80),
+    (Transition::Fade, 120),
+];
+
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // Start the millisecond clock for the frame timing
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
+    // Initialize I2C and the HT16K33
+    let i2c = I2c::new(
+        dp.TWI,
+        pins.a4.into_pull_up_input(), // SDA
+        pins.a5.into_pull_up_input(), // SCL
+        50000,
+    );
+    let mut display = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    display.init().unwrap();
+
+    // Brightness as stored in EEPROM (maximum on first boot)
+    let brightness = SettingsStore::new(arduino_hal::Eeprom::new(dp.EEPROM))
+        .load()
+        .unwrap_or_default()
+        .brightness;
+
+    // Button pin with pull-up resistor (LOW when pressed)
+    let mut button = Button::new(pins.d2.into_pull_up_input(), Active::Low);
+
+    let mut current = 0;
+    let mut playback = 0;
+    let mut transition = 0;
+    let mut animator = Animator::new(animations::ALL[current].1);
+
+    loop {
+        let now = Instant::now();
+
+        let changed = match button.update(now) {
+            Ok(Some(Event::Click)) => {
+                current = (current + 1) % animations::ALL.len();
+                true
+            }
+            Ok(Some(Event::DoubleClick)) => {
+                playback = (playback + 1) % PLAYBACKS.len();
+                true
+            }
+            Ok(Some(Event::LongPress(_))) => {
+                transition = (transition + 1) % TRANSITIONS.len();
+                true
+            }
+            _ => false,
+        };
+        if changed {
+            let (effect, ms) = TRANSITIONS[transition];
+            animator = Animator::new(animations::ALL[current].1);
+            animator.set_playback(PLAYBACKS[playback]);
+            animator.set_transition(effect, Duration::from_millis(ms));
+        }
+
+        // Only changed pictures are sent (a failed one is fixed by the next)
+        if let Some(shown) = animator.update(now) {
+            let _ = shown.flush(&mut display, brightness);
+        }
+    }
+}
+
+// FIX: Satisfies linker requirement for bare-metal exit
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
@@ -0,0 +1,96 @@
+//! Interrupt Button with Sleep
+//!
+//! This example runs a deliberately blocking rainbow on the RGB LED (each color is
+//! held with `delay_ms`) and still never misses a button press: the INT0 interrupt
+//! records presses in the background and the loop picks them up between colors.
+//! Every press skips ahead one color. After 10 seconds without a press the LED
+//! turns off and the Arduino goes into power-down sleep until the button is pressed.
+//!
+//! ## Hardware Connections
+//! - **RGB LED Module** (has V, R, G, B pins):
+//!   - V (VCC) → GND on Arduino (common cathode) or 5V (common anode - set `POLARITY`)
+//!   - G (Green) → Pin D13
+//!   - R (Red) → Pin D12
+//!   - B (Blue) → Pin D11
+//! - **Button Module**:
+//!   - G (GND) → GND on Arduino
+//!   - V (VCC) → Not connected (using internal pull-up)
+//!   - S (Signal) → Pin D2 (INT0)
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example button-wake`
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+use rust_sensor_playground::clock::{self, Deadline, Duration};
+use rust_sensor_playground::int0::Int0Button;
+use rust_sensor_playground::pwm::OnOff;
+use rust_sensor_playground::rgb::{Color, Polarity, RgbLed};
+
+const RAINBOW: [Color; 6] = [
+    Color::BLUE,
+    Color::CYAN,
+    Color::GREEN,
+    Color::YELLOW,
+    Color::RED,
+    Color::MAGENTA,
+];
+
+// V to GND: common cathode, V to 5V: common anode
+const POLARITY: Polarity = Polarity::CommonCathode;
+
+const SLEEP_AFTER: Duration = Duration::from_secs(10);
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // RGB LED pins
+    let mut led = RgbLed::new(
+        OnOff::new(pins.d12.into_output()),
+        OnOff::new(pins.d13.into_output()),
+        OnOff::new(pins.d11.into_output()),
+    )
+    .with_polarity(POLARITY);
+
+    // Clock for the press timestamps, INT0 for the button
+    clock::init(dp.TC0);
+    let mut button = Int0Button::new(dp.EXINT, pins.d2.into_pull_up_input());
+    unsafe { avr_device::interrupt::enable() };
+
+    let mut color = 0;
+    let mut sleep_at = Deadline::after(SLEEP_AFTER);
+
+    loop {
+        let _ = led.set_color(RAINBOW[color]);
+
+        // Blocking on purpose - presses are queued by the interrupt meanwhile
+        arduino_hal::delay_ms(400);
+        color = (color + 1) % RAINBOW.len();
+
+        // Every press that happened during the delay skips one color
+        while let Some(edge) = button.pop() {
+            if edge.pressed {
+                color = (color + 1) % RAINBOW.len();
+                sleep_at = Deadline::after(SLEEP_AFTER);
+            }
+        }
+
+        if sleep_at.expired() {
+            let _ = led.set_color(Color::BLACK);
+            button.power_down(&dp.CPU);
+
+            // Woken by a press; it only wakes us, so drop it
+            button.take_press();
+            sleep_at = Deadline::after(SLEEP_AFTER);
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
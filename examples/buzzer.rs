@@ -1,7 +1,10 @@
 //! Buzzer Pattern Player
 //! 
 //! This example plays different musical patterns on a piezo buzzer.
-//! Press a button to cycle through patterns: Happy Birthday, Star Wars, and Super Mario.
+//! Click the button to cycle through patterns: Happy Birthday, Star Wars, Super Mario and
+//! Twinkle Twinkle (one per file in `assets/melodies/`). Double-click to restart the
+//! current pattern, hold the button to stop it. The selected pattern is kept in EEPROM.
+//! Notes are generated by Timer1 in the background, so the button works mid-song.
 //!
 //! ## Hardware Connections
 //! - **Buzzer Module (Active/Passive)**:
@@ -15,125 +18,72 @@
 //!
 //! ## Usage
 //! Flash to Arduino: `cargo run --example buzzer`
-//! Press the button to cycle through different musical patterns.
+//! Click the button to cycle through different musical patterns.
 
 #![no_std]
 #![no_main]
 
 use panic_halt as _;
+use core::iter::Copied;
+use core::slice::Iter;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Instant};
+use rust_sensor_playground::melody;
+use rust_sensor_playground::settings::SettingsStore;
+use rust_sensor_playground::tone::{Note, Player, Tone, ToneGenerator};
+
+// Every song in assets/melodies - drop an RTTTL or ABC file there to add a pattern
+fn song_player(index: usize) -> Player<Copied<Iter<'static, Note>>> {
+    Player::new(melody::ALL[index].1.iter().copied())
+}
 
 #[arduino_hal::entry]
 fn main() -> ! {
     let dp = arduino_hal::Peripherals::take().unwrap();
     let pins = arduino_hal::pins!(dp);
 
-    // Pin 8 (PB0) for Buzzer
-    let mut buzzer = pins.d8.into_output();
+    // Pin 8 (PB0) for Buzzer, toggled by the Timer1 interrupt
+    let mut tone = Tone::new(dp.TC1, pins.d8.into_output());
 
-    // Pin 2 (PD2) for Button - using internal pull-up
-    let button = pins.d2.into_pull_up_input();
+    // Pin 2 (PD2) for Button - using internal pull-up, so LOW when pressed
+    let mut button = Button::new(pins.d2.into_pull_up_input(), Active::Low);
 
-    // Track current pattern and button state
-    let mut current_pattern = 0;
-    let mut last_button_state = button.is_high();
-    let mut pattern_playing = false;
+    // Timer0 keeps the time for the button
+    clock::init(dp.TC0);
 
-    loop {
-        // Check for button press (pull-up means LOW when pressed)
-        let button_state = button.is_high();
-        if !button_state && last_button_state {
-            // Button was just pressed
-            current_pattern = (current_pattern + 1) % 3;
-            pattern_playing = false; // Reset to start new pattern
-            // Debounce delay
-            arduino_hal::delay_ms(300);
-        }
-        last_button_state = button_state;
+    // Enable interrupts so the clock and the tone generator can run
+    unsafe { avr_device::interrupt::enable() };
 
-        // Play the current pattern once, then wait
-        if !pattern_playing {
-            match current_pattern {
-                0 => pattern_happy_birthday(&mut buzzer),
-                1 => pattern_star_wars(&mut buzzer),
-                2 => pattern_mario(&mut buzzer),
-                _ => {}
+    // Start with the pattern selected last time
+    let mut store = SettingsStore::new(arduino_hal::Eeprom::new(dp.EEPROM));
+    let mut settings = store.load().unwrap_or_default();
+    let mut current_pattern = settings.melody as usize % melody::ALL.len();
+    let mut player = song_player(current_pattern);
+
+    loop {
+        match button.update(Instant::now()) {
+            Ok(Some(Event::Click)) => {
+                // Stop the song and start the next one
+                current_pattern = (current_pattern + 1) % melody::ALL.len();
+                tone.stop();
+                player = song_player(current_pattern);
+                settings.melody = current_pattern as u8;
+                store.save(&settings);
             }
-            pattern_playing = true;
+            Ok(Some(Event::DoubleClick)) => {
+                tone.stop();
+                player = song_player(current_pattern);
+            }
+            Ok(Some(Event::LongPress(_))) => {
+                // An exhausted player keeps the buzzer silent
+                tone.stop();
+                player = Player::new([].iter().copied());
+            }
+            _ => {}
         }
 
-        // Small delay to check button frequently
-        arduino_hal::delay_ms(50);
-    }
-}
-
-// Pattern 0: Happy Birthday
-fn pattern_happy_birthday(
-    buzzer: &mut arduino_hal::port::Pin<arduino_hal::port::mode::Output, arduino_hal::hal::port::PB0>
-) {
-    // Happy birthday to you
-    play_tone(buzzer, 262, 250);  // C
-    play_tone(buzzer, 262, 250);  // C
-    play_tone(buzzer, 294, 500);  // D
-    play_tone(buzzer, 262, 500);  // C
-    play_tone(buzzer, 349, 500);  // F
-    play_tone(buzzer, 330, 1000); // E
-    arduino_hal::delay_ms(500);
-}
-
-// Pattern 1: Star Wars Imperial March
-fn pattern_star_wars(
-    buzzer: &mut arduino_hal::port::Pin<arduino_hal::port::mode::Output, arduino_hal::hal::port::PB0>
-) {
-    play_tone(buzzer, 392, 500);  // G
-    play_tone(buzzer, 392, 500);  // G
-    play_tone(buzzer, 392, 500);  // G
-    play_tone(buzzer, 311, 350);  // Eb
-    play_tone(buzzer, 466, 150);  // Bb
-    play_tone(buzzer, 392, 500);  // G
-    play_tone(buzzer, 311, 350);  // Eb
-    play_tone(buzzer, 466, 150);  // Bb
-    play_tone(buzzer, 392, 1000); // G
-    arduino_hal::delay_ms(500);
-}
-
-// Pattern 2: Super Mario Bros
-fn pattern_mario(
-    buzzer: &mut arduino_hal::port::Pin<arduino_hal::port::mode::Output, arduino_hal::hal::port::PB0>
-) {
-    play_tone(buzzer, 659, 150);  // E
-    play_tone(buzzer, 659, 150);  // E
-    arduino_hal::delay_ms(150);
-    play_tone(buzzer, 659, 150);  // E
-    arduino_hal::delay_ms(150);
-    play_tone(buzzer, 523, 150);  // C
-    play_tone(buzzer, 659, 150);  // E
-    arduino_hal::delay_ms(150);
-    play_tone(buzzer, 784, 150);  // G
-    arduino_hal::delay_ms(450);
-    play_tone(buzzer, 392, 150);  // G (lower)
-    arduino_hal::delay_ms(500);
-}
-
-// --- The Sound Engine ---
-fn play_tone(
-    pin: &mut arduino_hal::port::Pin<arduino_hal::port::mode::Output, arduino_hal::hal::port::PB0>, 
-    freq_hz: u32, 
-    duration_ms: u32
-) {
-    if freq_hz == 0 {
-        // FIX: Removed "as u16" here as well
-        arduino_hal::delay_ms(duration_ms);
-        return;
-    }
-
-    let delay_us = 1_000_000 / freq_hz / 2;
-    let cycles = (duration_ms * 1000) / (delay_us * 2);
-
-    for _ in 0..cycles {
-        pin.set_high();
-        arduino_hal::delay_us(delay_us);
-        pin.set_low();
-        arduino_hal::delay_us(delay_us);
+        // Keep the current pattern going; it plays once, then stays silent
+        player.poll(&mut tone);
     }
 }
 
@@ -1,8 +1,17 @@
 //! Joystick Controlled Dot on 8x8 LED Matrix
-//! 
-//! This example uses a joystick to control a dot on an 8x8 LED matrix.
-//! Move the joystick to move the dot around the display.
-//! Press the button to toggle between drawing mode (leaves a trail) and normal mode (single dot).
+//!
+//! This example uses a joystick to control a dot on an 8x8 LED matrix and paint with it.
+//! Move the joystick to move the dot around the display: a small push moves it slowly
+//! for fine drawing, pushing all the way moves it fast. While the pen is up the dot leaves
+//! a trail that slowly fades out. Click the button to put the pen down or lift it again,
+//! hold it to switch tools (pen, eraser, toggle), double-click it to undo the last stroke.
+//! The dot shows the tool: solid for the pen, dim for the eraser, blinking for toggle.
+//! The fading uses four brightness levels, made by switching the LEDs faster than the
+//! eye can follow.
+//!
+//! The drawing is kept in EEPROM whenever the pen is lifted and comes back after a reset.
+//! More drawings can be saved to and loaded from EEPROM slots over the serial console,
+//! which can also print the drawing as ASCII art (ready for `assets/bitmaps/`) or hex rows.
 //!
 //! ## Hardware Connections
 //! - **8x8 LED Matrix Module with HT16K33**:
@@ -20,8 +29,12 @@
 //!
 //! ## Usage
 //! Flash to Arduino: `cargo run --example joystick-dot`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
 //! Move the joystick to control the dot position on the LED matrix.
-//! Press the button to toggle drawing mode - when enabled, the dot leaves a trail!
+//! Click the button to start drawing with the current tool, click again to stop.
+//! Type `?` in the serial console (57600 baud) for the save, load and export commands.
+//! The joystick rest position is measured on first boot and kept in EEPROM; hold the
+//! button while resetting the board to measure it again.
 
 #![no_std]
 #![no_main]
@@ -30,26 +43,52 @@ use panic_halt as _;
 use arduino_hal::prelude::*;
 use arduino_hal::I2c;
 use arduino_hal::adc;
+use rust_sensor_playground::button::{Active, Button, Event};
+use embedded_graphics::pixelcolor::{Gray2, GrayColor};
+use rust_sensor_playground::clock::{self, Deadline, Duration, Instant};
+use rust_sensor_playground::cursor::{Cursor, Edge};
+use rust_sensor_playground::gray::{FrameModulator, GrayMatrix8x8};
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Orientation};
+use rust_sensor_playground::joystick::Joystick;
+use rust_sensor_playground::paint::{self, CanvasStore, Paint, Tool};
+use rust_sensor_playground::settings::SettingsStore;
+
+// Dot speed with the stick pushed all the way, in pixels per second
+const MAX_SPEED: u8 = 12;
+
+// Strokes that can be undone
+const UNDO_STEPS: usize = 8;
+
+// Canvas slot the drawing is kept in while you paint
+const AUTOSAVE_SLOT: u8 = 0;
 
-// HT16K33 Register definitions
-const HT16K33_ADDR: u8 = 0x70;
-const HT16K33_CMD_OSCILLATOR_ON: u8 = 0x21;
-const HT16K33_CMD_DISPLAY_ON: u8 = 0x81;
-const HT16K33_CMD_BRIGHTNESS: u8 = 0xE0;
-const HT16K33_DISPLAY_RAM: u8 = 0x00;
+const HELP: &str = "commands:\r
+  s0-s7  save the drawing to a slot (s0 is also saved when lifting the pen or undoing)\r
+  l0-l7  load a drawing from a slot\r
+  a      print the drawing as ASCII art\r
+  h      print the drawing as hex rows\r
+  u      undo\r
+  c      clear\r
+  t      next tool\r
+  w      wrap around the edges on/off\r
+";
 
-// Joystick calibration
-// Typical joystick values: center ~512, min ~0, max ~1023
-const CENTER_THRESHOLD: u16 = 300; // Larger deadzone
-const CENTER_VALUE: u16 = 512;
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
 
 #[arduino_hal::entry]
 fn main() -> ! {
     let dp = arduino_hal::Peripherals::take().unwrap();
     let pins = arduino_hal::pins!(dp);
-    
+    let mut serial = arduino_hal::default_serial!(dp, pins, 57600);
+
+    // Start the millisecond clock
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
     // Initialize I2C for LED matrix
-    let mut i2c = I2c::new(
+    let i2c = I2c::new(
         dp.TWI,
         pins.a4.into_pull_up_input(), // SDA
         pins.a5.into_pull_up_input(), // SCL
@@ -58,127 +97,177 @@ fn main() -> ! {
 
     // Initialize ADC for joystick
     let mut adc = adc::Adc::new(dp.ADC, Default::default());
-    
-    // Set up analog pins for joystick
-    let x_axis = pins.a0.into_analog_input(&mut adc);
-    let y_axis = pins.a1.into_analog_input(&mut adc);
-    
-    // Set up button pin
+
+    // Set up analog pins and button (LOW when pressed due to pull-up) for joystick
     let button = pins.d2.into_pull_up_input();
+    let recalibrate = button.is_low();
+    let mut joystick = Joystick::new(
+        pins.a0.into_analog_input(&mut adc).into_channel(),
+        pins.a1.into_analog_input(&mut adc).into_channel(),
+    )
+    .with_button(Button::new(button, Active::Low));
+
+    // Calibration and brightness from EEPROM (the defaults on first boot)
+    let mut eeprom = arduino_hal::Eeprom::new(dp.EEPROM);
+    let mut store = SettingsStore::new(&mut eeprom);
+    let stored = store.load();
+    let mut settings = stored.unwrap_or_default();
+    settings.configure_joystick(&mut joystick);
+    if stored.is_none() || recalibrate {
+        // Measure the rest position - don't touch the stick while starting up
+        joystick.calibrate(&mut adc, 16);
+        settings.joystick_center = joystick.center();
+        store.save(&settings);
+    }
 
     // Initialize HT16K33
-    let _ = i2c.write(HT16K33_ADDR, &[HT16K33_CMD_OSCILLATOR_ON]);
-    arduino_hal::delay_ms(10);
-    
-    let _ = i2c.write(HT16K33_ADDR, &[HT16K33_CMD_DISPLAY_ON]);
-    arduino_hal::delay_ms(10);
-    
-    let _ = i2c.write(HT16K33_ADDR, &[HT16K33_CMD_BRIGHTNESS | 0x0F]);
-    arduino_hal::delay_ms(10);
-
-    // Initial dot position (center of 8x8 matrix)
-    let mut dot_x: u8 = 4;
-    let mut dot_y: u8 = 4;
-    
-    // Drawing mode state
-    let mut drawing_mode = false;
-    let mut canvas: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 0]; // Persistent canvas for drawing
-    
-    // Button debouncing
-    let mut last_button_state = true; // Pull-up means HIGH when not pressed
-    
-    // Movement timing
-    let mut delay_counter: u8 = 0;
-    const MOVE_DELAY: u8 = 10; // Movement speed control
+    let mut matrix = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    matrix.init().unwrap();
+    let _ = matrix.set_brightness(settings.brightness);
+
+    // Dot starting in the center, with speed following the stick
+    let mut cursor = Cursor::new(8, 8);
+    cursor.set_max_speed(MAX_SPEED);
+
+    // The drawing, picking up where it was left before the reset
+    let mut canvases = CanvasStore::new(&mut eeprom);
+    let mut paint: Paint<UNDO_STEPS> = Paint::new();
+    if let Some(canvas) = canvases.load(AUTOSAVE_SLOT) {
+        paint.set_canvas(canvas);
+    }
+    let mut trail = GrayMatrix8x8::new(); // Fades out behind the dot while the pen is up
+
+    // Fading, blinking and redraw timing
+    const FADE_INTERVAL: Duration = Duration::from_millis(400);
+    const BLINK_INTERVAL: Duration = Duration::from_millis(250);
+    let mut next_fade = Deadline::after(FADE_INTERVAL);
+    let mut next_blink = Deadline::after(BLINK_INTERVAL);
+    let mut blink = true;
+    let mut modulator = FrameModulator::new();
+    // Last frame handed to the modulator, held steady during EEPROM and serial work
+    let mut shown = GrayMatrix8x8::new();
+
+    // First letter of a two-letter command (`s` or `l`)
+    let mut command = None;
+    let _ = ufmt::uwrite!(serial, "{}", HELP);
 
     loop {
+        let now = Instant::now();
+
         // Read joystick position
-        let x_raw = x_axis.analog_read(&mut adc);
-        let y_raw = y_axis.analog_read(&mut adc);
-        
-        // Check button for mode toggle (LOW when pressed due to pull-up)
-        let button_state = button.is_high();
-        if !button_state && last_button_state {
-            // Button just pressed - toggle drawing mode
-            drawing_mode = !drawing_mode;
-            
-            // If entering drawing mode, clear the canvas
-            if drawing_mode {
-                canvas = [0, 0, 0, 0, 0, 0, 0, 0];
+        let position = joystick.position(&mut adc);
+
+        // Pen, tools and undo on the button
+        match joystick.button().update(now) {
+            Ok(Some(Event::Click)) => {
+                if paint.is_drawing() {
+                    paint.pen_up();
+                    let _ = modulator.hold(&shown, &mut matrix);
+                    canvases.save(AUTOSAVE_SLOT, paint.canvas());
+                } else {
+                    paint.pen_down();
+                }
             }
-            
-            // Debounce delay
-            arduino_hal::delay_ms(200);
-        }
-        last_button_state = button_state;
-        
-        // Update position based on joystick (only every MOVE_DELAY iterations)
-        if delay_counter >= MOVE_DELAY {
-            delay_counter = 0;
-            
-            // X-axis movement (left/right)
-            // Lower values = left, higher values = right
-            if x_raw < CENTER_VALUE - CENTER_THRESHOLD {
-                // Move left
-                if dot_x > 0 {
-                    dot_x -= 1;
+            Ok(Some(Event::DoubleClick)) => {
+                // A double-click never reports a click first, so the pen is
+                // only down from an earlier click: end that stroke, then
+                // take back the latest one
+                if paint.is_drawing() {
+                    paint.pen_up();
                 }
-            } else if x_raw > CENTER_VALUE + CENTER_THRESHOLD {
-                // Move right
-                if dot_x < 7 {
-                    dot_x += 1;
+                if paint.undo() {
+                    let _ = modulator.hold(&shown, &mut matrix);
+                    canvases.save(AUTOSAVE_SLOT, paint.canvas());
                 }
             }
-            
-            // Y-axis movement (up/down)
-            // Lower values = up, higher values = down
-            if y_raw < CENTER_VALUE - CENTER_THRESHOLD {
-                // Move up (decrease Y)
-                if dot_y > 0 {
-                    dot_y -= 1;
+            Ok(Some(Event::LongPress(_))) => paint.set_tool(paint.tool().next()),
+            _ => {}
+        }
+
+        // Everything else comes from the serial console
+        if let Ok(byte) = serial.read() {
+            // Commands write to the EEPROM or print for tens of milliseconds
+            let _ = modulator.hold(&shown, &mut matrix);
+            match (command.take(), byte) {
+                (Some(b's'), b'0'..=b'7') => {
+                    canvases.save(byte - b'0', paint.canvas());
+                    let _ = ufmt::uwriteln!(serial, "saved\r");
                 }
-            } else if y_raw > CENTER_VALUE + CENTER_THRESHOLD {
-                // Move down (increase Y)
-                if dot_y < 7 {
-                    dot_y += 1;
+                (Some(b'l'), b'0'..=b'7') => match canvases.load(byte - b'0') {
+                    Some(canvas) => paint.set_canvas(canvas),
+                    None => {
+                        let _ = ufmt::uwriteln!(serial, "empty slot\r");
+                    }
+                },
+                (_, b's' | b'l') => command = Some(byte),
+                (_, b'a') => {
+                    let _ = paint::write_ascii(&mut serial, paint.canvas());
+                }
+                (_, b'h') => {
+                    let _ = paint::write_hex(&mut serial, paint.canvas());
+                }
+                (_, b'u') => {
+                    // Like the double-click: end the stroke before taking one back
+                    if paint.is_drawing() {
+                        paint.pen_up();
+                    }
+                    if paint.undo() {
+                        canvases.save(AUTOSAVE_SLOT, paint.canvas());
+                    }
+                }
+                (_, b'c') => paint.clear(),
+                (_, b't') => paint.set_tool(paint.tool().next()),
+                (_, b'w') => cursor.set_edge(match cursor.edge() {
+                    Edge::Clamp => Edge::Wrap,
+                    Edge::Wrap => Edge::Clamp,
+                }),
+                (_, b'\r' | b'\n' | b' ') => {}
+                _ => {
+                    let _ = ufmt::uwrite!(serial, "{}", HELP);
                 }
             }
         }
-        delay_counter += 1;
-        
-        // In drawing mode, add current position to canvas
-        if drawing_mode {
-            canvas[dot_y as usize] |= 1u8 << dot_x;
+
+        // Move by the stick's deflection for the time since the last pass
+        cursor.update(position, now);
+        let (dot_x, dot_y) = cursor.pixel();
+        paint.paint(dot_x, dot_y);
+
+        // The trail dims one level per FADE_INTERVAL until it's gone
+        if next_fade.expired() {
+            next_fade = Deadline::after(FADE_INTERVAL);
+            trail.fade();
+        }
+        if !paint.is_drawing() {
+            trail.set_pixel(dot_x as i32, dot_y as i32, Gray2::new(2));
+        }
+        if next_blink.expired() {
+            next_blink = Deadline::after(BLINK_INTERVAL);
+            blink = !blink;
+        }
+
+        // Create display pattern: the drawing over the trail (brighter while the pen
+        // is down) and the dot on top, showing the tool
+        let mut frame = trail;
+        let level = if paint.is_drawing() { Gray2::WHITE } else { Gray2::new(2) };
+        for y in 0..8 {
+            for x in 0..8 {
+                if paint.canvas().pixel(x, y) {
+                    frame.set_pixel(x, y, level);
+                }
+            }
         }
-        
-        // Create display pattern
-        // In drawing mode: show canvas with current dot
-        // In normal mode: show only current dot
-        let row0_val: u8 = if drawing_mode { canvas[0] | if dot_y == 0 { 1u8 << dot_x } else { 0 } } else { if dot_y == 0 { 1u8 << dot_x } else { 0 } };
-        let row1_val: u8 = if drawing_mode { canvas[1] | if dot_y == 1 { 1u8 << dot_x } else { 0 } } else { if dot_y == 1 { 1u8 << dot_x } else { 0 } };
-        let row2_val: u8 = if drawing_mode { canvas[2] | if dot_y == 2 { 1u8 << dot_x } else { 0 } } else { if dot_y == 2 { 1u8 << dot_x } else { 0 } };
-        let row3_val: u8 = if drawing_mode { canvas[3] | if dot_y == 3 { 1u8 << dot_x } else { 0 } } else { if dot_y == 3 { 1u8 << dot_x } else { 0 } };
-        let row4_val: u8 = if drawing_mode { canvas[4] | if dot_y == 4 { 1u8 << dot_x } else { 0 } } else { if dot_y == 4 { 1u8 << dot_x } else { 0 } };
-        let row5_val: u8 = if drawing_mode { canvas[5] | if dot_y == 5 { 1u8 << dot_x } else { 0 } } else { if dot_y == 5 { 1u8 << dot_x } else { 0 } };
-        let row6_val: u8 = if drawing_mode { canvas[6] | if dot_y == 6 { 1u8 << dot_x } else { 0 } } else { if dot_y == 6 { 1u8 << dot_x } else { 0 } };
-        let row7_val: u8 = if drawing_mode { canvas[7] | if dot_y == 7 { 1u8 << dot_x } else { 0 } } else { if dot_y == 7 { 1u8 << dot_x } else { 0 } };
-        
-        // Write display buffer to HT16K33
-        let write_buf: [u8; 17] = [
-            HT16K33_DISPLAY_RAM,
-            row0_val, 0,
-            row1_val, 0,
-            row2_val, 0,
-            row3_val, 0,
-            row4_val, 0,
-            row5_val, 0,
-            row6_val, 0,
-            row7_val, 0,
-        ];
-        
-        let _ = i2c.write(HT16K33_ADDR, &write_buf);
-        
-        // Small delay for smooth updates
-        arduino_hal::delay_ms(10);
+        let dot = match paint.tool() {
+            Tool::Pen => Gray2::WHITE,
+            Tool::Eraser => Gray2::new(1),
+            Tool::Toggle if blink => Gray2::WHITE,
+            Tool::Toggle => Gray2::BLACK,
+        };
+        frame.set_pixel(dot_x as i32, dot_y as i32, dot);
+
+        // Show the next subframe when it's due: only changed rows go over I2C, nothing
+        // while everything is fully on or off (a failed subframe is redrawn next pass)
+        let _ = modulator.update(&frame, &mut matrix, now);
+        shown = frame;
     }
 }
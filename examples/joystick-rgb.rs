@@ -4,7 +4,12 @@
 //! Moving the joystick in different directions changes the LED color.
 //! Center position turns the LED off.
 //!
-//! ## Color Map
+//! Click the joystick button to switch to the color wheel: the stick angle picks
+//! the hue (right is red, up-left green, down-left blue) and pushing it further
+//! makes the color brighter. Double-click to have the distance fade from white
+//! to the full color instead.
+//!
+//! ## Color Map (direction mode)
 //! - Center: Off (LED off)
 //! - Up: Blue
 //! - Down: Yellow
@@ -32,142 +37,117 @@
 //! ## Usage
 //! Flash to Arduino: `cargo run --example joystick-rgb`
 //! Move the joystick to see different colors on the RGB LED!
-//! Press the joystick button to flash the current color 3 times!
+//! Hold the joystick button to flash the current color 5 times!
 
 #![no_std]
 #![no_main]
 
 use panic_halt as _;
 use arduino_hal::adc;
-
-// Joystick calibration
-const CENTER_VALUE: u16 = 512;
-const THRESHOLD: u16 = 300; // Larger deadzone around center for "off" state
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Instant};
+use rust_sensor_playground::joystick::{Direction8, Joystick, NORMALIZED_MAX};
+use rust_sensor_playground::pwm::SoftPwm;
+use rust_sensor_playground::rgb::{Color, Hsv, Polarity, RadiusMode, RgbLed};
 
 // Button flash settings
 const FLASH_COUNT: u8 = 5;
 const FLASH_INTERVAL: u32 = 80; // milliseconds
 
+enum Mode {
+    Directions,
+    Wheel(RadiusMode),
+}
+
+fn color_for(direction: Option<Direction8>) -> Color {
+    match direction {
+        None => Color::BLACK,
+        Some(Direction8::UpLeft) => Color::MAGENTA,
+        Some(Direction8::UpRight) => Color::WHITE,
+        Some(Direction8::DownLeft) => Color::GREEN,
+        Some(Direction8::DownRight) => Color::new(128, 0, 255), // Purple
+        Some(Direction8::Left) => Color::RED,
+        Some(Direction8::Right) => Color::CYAN,
+        Some(Direction8::Up) => Color::BLUE,
+        Some(Direction8::Down) => Color::YELLOW,
+    }
+}
+
 #[arduino_hal::entry]
 fn main() -> ! {
     let dp = arduino_hal::Peripherals::take().unwrap();
     let pins = arduino_hal::pins!(dp);
+
+    // Timer0 keeps the time for the button debouncing, Timer2 dims the LED
+    clock::init(dp.TC0);
+    let mut pwm = SoftPwm::new(dp.TC2);
+    unsafe { avr_device::interrupt::enable() };
     
-    // RGB LED pins
-    let mut red = pins.d11.into_output();
-    let mut green = pins.d12.into_output();
-    let mut blue = pins.d13.into_output();
-    
-    // Joystick button pin (active low with pull-up)
-    let button = pins.d2.into_pull_up_input();
+    // RGB LED pins - this module is common anode, so LOW is on
+    let mut led = RgbLed::new(
+        pwm.channel(pins.d11.into_output()).unwrap(),
+        pwm.channel(pins.d12.into_output()).unwrap(),
+        pwm.channel(pins.d13.into_output()).unwrap(),
+    )
+    .with_polarity(Polarity::CommonAnode);
     
     // Initialize ADC for joystick
     let mut adc = adc::Adc::new(dp.ADC, Default::default());
     
-    // Set up analog pins for joystick
-    let x_axis = pins.a0.into_analog_input(&mut adc);
-    let y_axis = pins.a1.into_analog_input(&mut adc);
+    // Set up analog pins and button (active low with pull-up) for joystick
+    let mut joystick = Joystick::new(
+        pins.a0.into_analog_input(&mut adc).into_channel(),
+        pins.a1.into_analog_input(&mut adc).into_channel(),
+    )
+    .with_button(Button::new(pins.d2.into_pull_up_input(), Active::Low));
+    // The kit module reports up as a high Y value
+    joystick.set_invert_y(true);
+    // Measure the rest position - don't touch the stick while starting up
+    joystick.calibrate(&mut adc, 16);
     
     // Start with LED off
-    red.set_low();
-    green.set_low();
-    blue.set_low();
-
-    // Track button state for edge detection
-    let mut button_was_pressed = false;
+    let _ = led.set_color(Color::BLACK);
+    let mut mode = Mode::Directions;
 
     loop {
-        // Read joystick position
-        let x_raw = x_axis.analog_read(&mut adc);
-        let y_raw = y_axis.analog_read(&mut adc);
-        
-        // Determine joystick direction
-        // Note: Y-axis is inverted on some modules (Up is high value)
-        let x_left = x_raw < CENTER_VALUE - THRESHOLD;
-        let x_right = x_raw > CENTER_VALUE + THRESHOLD;
-        let y_up = y_raw > CENTER_VALUE + THRESHOLD;   // Swapped for hardware correction
-        let y_down = y_raw < CENTER_VALUE - THRESHOLD; // Swapped for hardware correction
-        let center = !x_left && !x_right && !y_up && !y_down;
-        
-        // Store current LED state before updating
-        let current_red = red.is_set_high();
-        let current_green = green.is_set_high();
-        let current_blue = blue.is_set_high();
-        
-        // Set RGB LED color based on joystick position
-        // Note: Common Anode LED means LOW is ON, HIGH is OFF
-        if center {
-            // Center: Off
-            red.set_high();
-            green.set_high();
-            blue.set_high();
-        } else if x_left && y_up {
-            // Up-Left: Magenta (Red + Blue)
-            red.set_low();
-            green.set_high();
-            blue.set_low();
-        } else if x_right && y_up {
-            // Up-Right: White (All On)
-            red.set_low();
-            green.set_low();
-            blue.set_low();
-        } else if x_left && y_down {
-            // Down-Left: Green
-            red.set_high();
-            green.set_low();
-            blue.set_high();
-        } else if x_right && y_down {
-            // Down-Right: Purple (Blue only)
-            red.set_high();
-            green.set_high();
-            blue.set_low();
-        } else if x_left {
-            // Left: Red
-            red.set_low();
-            green.set_high();
-            blue.set_high();
-        } else if x_right {
-            // Right: Cyan (Green + Blue)
-            red.set_high();
-            green.set_low();
-            blue.set_low();
-        } else if y_up {
-            // Up: Blue
-            red.set_high();
-            green.set_high();
-            blue.set_low();
-        } else if y_down {
-            // Down: Yellow (Red + Green)
-            red.set_low();
-            green.set_low();
-            blue.set_high();
-        }
-        
-        // Check for button press (active low)
-        let button_pressed = button.is_low();
+        // Set RGB LED color based on joystick position (off while centered)
+        let color = match mode {
+            Mode::Directions => color_for(joystick.direction(&mut adc)),
+            Mode::Wheel(radius) => {
+                let (angle, magnitude) = joystick.polar(&mut adc);
+                Hsv::from_polar(angle, magnitude, NORMALIZED_MAX as u16, radius).into()
+            }
+        };
+        let _ = led.set_color(color);
         
-        // Detect button press edge (wasn't pressed before, but is now)
-        if button_pressed && !button_was_pressed {
-            // Flash the current color 3 times
-            for _ in 0..FLASH_COUNT {
-                // Turn off (all high for common anode)
-                red.set_high();
-                green.set_high();
-                blue.set_high();
-                arduino_hal::delay_ms(FLASH_INTERVAL);
-                
-                // Restore current color
-                if current_red { red.set_high(); } else { red.set_low(); }
-                if current_green { green.set_high(); } else { green.set_low(); }
-                if current_blue { blue.set_high(); } else { blue.set_low(); }
-                arduino_hal::delay_ms(FLASH_INTERVAL);
+        match joystick.button().update(Instant::now()) {
+            Ok(Some(Event::Click)) => {
+                mode = match mode {
+                    Mode::Directions => Mode::Wheel(RadiusMode::Value),
+                    Mode::Wheel(_) => Mode::Directions,
+                };
+            }
+            Ok(Some(Event::DoubleClick)) => {
+                if let Mode::Wheel(radius) = mode {
+                    mode = Mode::Wheel(match radius {
+                        RadiusMode::Value => RadiusMode::Saturation,
+                        RadiusMode::Saturation => RadiusMode::Value,
+                    });
+                }
             }
+            Ok(Some(Event::LongPress(_))) => {
+                for _ in 0..FLASH_COUNT {
+                    let _ = led.set_color(Color::BLACK);
+                    arduino_hal::delay_ms(FLASH_INTERVAL);
+                    let _ = led.set_color(color);
+                    arduino_hal::delay_ms(FLASH_INTERVAL);
+                }
+            }
+            _ => {}
         }
         
-        button_was_pressed = button_pressed;
-        
         // Small delay for stability
-        arduino_hal::delay_ms(50);
+        arduino_hal::delay_ms(20);
     }
 }
 
@@ -0,0 +1,100 @@
+//! Keypad on the LED Matrix Backpack
+//!
+//! This example reads a 3x4 phone keypad wired to the key scan pins of the HT16K33
+//! backpack and shows the key that is held on the 8x8 LED matrix. The keys are read
+//! over I2C, so the keypad needs no Arduino pins at all.
+//!
+//! ## Hardware Connections
+//! - **8x8 LED Matrix Module with HT16K33**:
+//!   - VCC → 5V on Arduino
+//!   - GND → GND on Arduino
+//!   - SDA → A4 (SDA) on Arduino Uno
+//!   - SCL → A5 (SCL) on Arduino Uno
+//! - **3x4 Keypad** (on the backpack's key scan pads):
+//!   - Rows 1-4 → K1-K4
+//!   - Columns 1-3 → KS0-KS2
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example keypad`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+use arduino_hal::I2c;
+use rust_sensor_playground::clock::{self, Instant};
+use rust_sensor_playground::font;
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Key, Orientation};
+use rust_sensor_playground::keypad::{KeyEvent, Keypad};
+use rust_sensor_playground::matrix::{Matrix8x8, SIZE};
+
+// Key labels by K input (row of the keypad) and KS line (column)
+const LABELS: [[char; 3]; 4] = [
+    ['1', '2', '3'],
+    ['4', '5', '6'],
+    ['7', '8', '9'],
+    ['*', '0', '#'],
+];
+
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
+
+fn label(key: Key) -> Option<char> {
+    LABELS.get(key.column as usize)?.get(key.row as usize).copied()
+}
+
+// One character in the middle of the matrix
+fn draw_label(c: char) -> Matrix8x8 {
+    let mut frame = Matrix8x8::new();
+    let glyph = font::glyph(c);
+    let left = (SIZE as i32 - glyph.width() as i32) / 2;
+    for x in 0..glyph.width() {
+        let bits = glyph.column(x);
+        for y in 0..font::HEIGHT as i32 {
+            frame.set_pixel(left + x as i32, y, bits & 1 << y != 0);
+        }
+    }
+    frame
+}
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // Start the millisecond clock for the key scan timing
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
+    // Initialize I2C and the HT16K33
+    let i2c = I2c::new(
+        dp.TWI,
+        pins.a4.into_pull_up_input(), // SDA
+        pins.a5.into_pull_up_input(), // SCL
+        50000,
+    );
+    let mut display = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    display.init().unwrap();
+
+    let mut keypad = Keypad::new();
+
+    loop {
+        let frame = match keypad.update(&mut display, Instant::now()) {
+            Ok(Some(KeyEvent::Pressed(key))) => label(key).map(draw_label),
+            // Blank once the last key is up
+            Ok(Some(KeyEvent::Released(_))) if !keypad.pressed().any() => Some(Matrix8x8::new()),
+            _ => None,
+        };
+        if let Some(frame) = frame {
+            let _ = frame.flush(&mut display);
+        }
+    }
+}
+
+// FIX: Satisfies linker requirement for bare-metal exit
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
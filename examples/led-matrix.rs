@@ -1,7 +1,9 @@
 //! 8x8 LED Matrix Display with HT16K33 Driver
 //! 
 //! This example controls an 8x8 LED matrix using the HT16K33 I2C LED driver chip.
-//! It displays a heart shape and a smiley face, alternating every 3 seconds.
+//! It displays a heart shape and a smiley face, then a ring and a crossed box drawn with
+//! `embedded-graphics`, changing every 3 seconds with a slide, dissolve, wipe or fade.
+//! The bitmaps are drawn as ASCII art in `assets/bitmaps/`; add a file there to add a pattern.
 //!
 //! ## Hardware Connections
 //! - **8x8 LED Matrix Module with HT16K33**:
@@ -15,123 +17,137 @@
 //!
 //! ## Usage
 //! Flash to Arduino: `cargo run --example led-matrix`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
 
 #![no_std]
 #![no_main]
 
 use panic_halt as _;
-use arduino_hal::prelude::*;
 use arduino_hal::I2c;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle, Rectangle};
+use rust_sensor_playground::animation::{Change, Direction, Transition};
+use rust_sensor_playground::bitmaps;
+use rust_sensor_playground::clock::{self, Duration, Instant};
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Orientation};
+use rust_sensor_playground::matrix::Matrix8x8;
+use rust_sensor_playground::scheduler::Scheduler;
+use rust_sensor_playground::settings::SettingsStore;
 
-// HT16K33 Register definitions
-const HT16K33_ADDR: u8 = 0x70;
-const HT16K33_CMD_OSCILLATOR_ON: u8 = 0x21;
-const HT16K33_CMD_DISPLAY_ON: u8 = 0x81; // Display ON, no blinking
-const HT16K33_CMD_BRIGHTNESS: u8 = 0xE0; // Brightness command (0xE0-0xEF)
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
 
-// Display buffer address starts at 0x00
-const HT16K33_DISPLAY_RAM: u8 = 0x00;
+struct App {
+    matrix: Ht16k33<I2c>,
+    brightness: u8,
+    current: usize,
+    shown: Matrix8x8,
+    change: Option<Change>,
+}
+
+// Each pattern change uses the next of these
+const TRANSITIONS: [Transition; 4] = [
+    Transition::Slide(Direction::Left),
+    Transition::Dissolve,
+    Transition::Wipe(Direction::Down),
+    Transition::Fade,
+];
+const TRANSITION_TIME: Duration = Duration::from_millis(400);
+
+// Patterns drawn with embedded-graphics, shown after the bitmaps
+const SHAPES: usize = 2;
+
+fn draw_shape(index: usize, frame: &mut Matrix8x8) {
+    let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+    // Drawing into the frame can't fail
+    if index == 0 {
+        // Ring
+        let _ = Circle::new(Point::zero(), 8).into_styled(stroke).draw(frame);
+    } else {
+        // Crossed box
+        let _ = Rectangle::new(Point::zero(), Size::new(8, 8)).into_styled(stroke).draw(frame);
+        let _ = Line::new(Point::new(0, 0), Point::new(7, 7)).into_styled(stroke).draw(frame);
+        let _ = Line::new(Point::new(7, 0), Point::new(0, 7)).into_styled(stroke).draw(frame);
+    }
+}
+
+fn show_next_pattern(app: &mut App, now: Instant) {
+    let frame = match bitmaps::ALL.get(app.current) {
+        Some((_name, pattern)) => Matrix8x8::from_rows(*pattern),
+        None => {
+            let mut frame = Matrix8x8::new();
+            draw_shape(app.current - bitmaps::ALL.len(), &mut frame);
+            frame
+        }
+    };
+
+    // Blend from the pattern on display; `animate` plays the transition
+    let transition = TRANSITIONS[app.current % TRANSITIONS.len()];
+    app.change = Some(Change::new(app.shown, frame, transition, now, TRANSITION_TIME));
+    app.shown = frame;
+
+    // Move on to the next pattern (heart, smiley, ..., ring, crossed box)
+    app.current = (app.current + 1) % (bitmaps::ALL.len() + SHAPES);
+}
+
+fn animate(app: &mut App, now: Instant) {
+    let Some(change) = app.change else {
+        return;
+    };
+    // A failed write is simply retried with the next step
+    match change.shown_at(now) {
+        Some(shown) => {
+            let _ = shown.flush(&mut app.matrix, app.brightness);
+        }
+        None => {
+            app.change = None;
+            let _ = app.matrix.set_brightness(app.brightness);
+            let _ = change.target().flush(&mut app.matrix);
+        }
+    }
+}
 
 #[arduino_hal::entry]
 fn main() -> ! {
     let dp = arduino_hal::Peripherals::take().unwrap();
     let pins = arduino_hal::pins!(dp);
+
+    // Start the millisecond clock for the scheduler
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
     
     // Initialize I2C
-    let mut i2c = I2c::new(
+    let i2c = I2c::new(
         dp.TWI,
         pins.a4.into_pull_up_input(), // SDA
         pins.a5.into_pull_up_input(), // SCL
         50000, // 50kHz I2C clock (conservative for reliability)
     );
 
-    // Initialize HT16K33
-    // Turn on oscillator
-    let _ = i2c.write(HT16K33_ADDR, &[HT16K33_CMD_OSCILLATOR_ON]);
-    arduino_hal::delay_ms(10);
-    
-    // Turn on display (no blinking)
-    let _ = i2c.write(HT16K33_ADDR, &[HT16K33_CMD_DISPLAY_ON]);
-    arduino_hal::delay_ms(10);
-    
-    // Set brightness to maximum (0xEF = brightest)
-    let _ = i2c.write(HT16K33_ADDR, &[HT16K33_CMD_BRIGHTNESS | 0x0F]);
-    arduino_hal::delay_ms(10);
-
-    let mut show_heart = true;
-
-    loop {
-        // Define patterns for heart and smiley face
-        // Each row is represented as a u8 (we only use 8 bits for 8x8 matrix)
-        let row0_val: u8;
-        let row1_val: u8;
-        let row2_val: u8;
-        let row3_val: u8;
-        let row4_val: u8;
-        let row5_val: u8;
-        let row6_val: u8;
-        let row7_val: u8;
-        
-        if show_heart {
-            // Heart pattern
-            // 0b01100110
-            // 0b11111111
-            // 0b11111111
-            // 0b11111111
-            // 0b01111110
-            // 0b00111100
-            // 0b00011000
-            // 0b00000000
-            row0_val = 0b01100110;
-            row1_val = 0b11111111;
-            row2_val = 0b11111111;
-            row3_val = 0b11111111;
-            row4_val = 0b01111110;
-            row5_val = 0b00111100;
-            row6_val = 0b00011000;
-            row7_val = 0b00000000;
-        } else {
-            // Smiley face pattern
-            // 0b00111100
-            // 0b01000010
-            // 0b10100101
-            // 0b10000001
-            // 0b10100101
-            // 0b10011001
-            // 0b01000010
-            // 0b00111100
-            row0_val = 0b00111100;
-            row1_val = 0b01000010;
-            row2_val = 0b10100101;
-            row3_val = 0b10000001;
-            row4_val = 0b10100101;
-            row5_val = 0b10011001;
-            row6_val = 0b01000010;
-            row7_val = 0b00111100;
-        }
-        
-        // Write display buffer to HT16K33
-        // Build and send the complete data buffer
-        // We need to send: address, then 16 bytes (8 rows x 2 bytes each)
-        // For 8x8 matrix, high byte is 0 for each row
-        let write_buf: [u8; 17] = [
-            HT16K33_DISPLAY_RAM,
-            row0_val, 0,
-            row1_val, 0,
-            row2_val, 0,
-            row3_val, 0,
-            row4_val, 0,
-            row5_val, 0,
-            row6_val, 0,
-            row7_val, 0,
-        ];
-        
-        let _ = i2c.write(HT16K33_ADDR, &write_buf);
-        
-        // Display for 3 seconds
-        arduino_hal::delay_ms(3000);
-        
-        // Toggle between heart and smiley
-        show_heart = !show_heart;
-    }
+    // Initialize HT16K33 (oscillator on, display on, maximum brightness)
+    let mut matrix = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    matrix.init().unwrap();
+
+    // Brightness as stored in EEPROM (maximum on first boot)
+    let settings = SettingsStore::new(arduino_hal::Eeprom::new(dp.EEPROM))
+        .load()
+        .unwrap_or_default();
+    let _ = matrix.set_brightness(settings.brightness);
+
+    // Patterns come from the ASCII-art files in assets/bitmaps and the shapes
+    // above, each one displayed for 3 seconds; add more tasks to do other work meanwhile
+    let mut app = App {
+        matrix,
+        brightness: settings.brightness,
+        current: 0,
+        shown: Matrix8x8::new(),
+        change: None,
+    };
+    let mut scheduler: Scheduler<App, 2> = Scheduler::new();
+    let now = Instant::now();
+    scheduler.add(now, Duration::from_secs(3), show_next_pattern).unwrap();
+    scheduler.add(now, Duration::from_millis(20), animate).unwrap();
+    scheduler.run(&mut app)
 }
@@ -0,0 +1,124 @@
+//! Scrolling Text on the LED Matrix
+//!
+//! This example scrolls text across the 8x8 LED matrix: a greeting, then the
+//! current joystick position as a live reading, over and over. Each time the
+//! text has scrolled out, a fresh reading is taken.
+//! Click the joystick button to reverse the scroll direction, hold it to speed up.
+//!
+//! ## Hardware Connections
+//! - **8x8 LED Matrix Module with HT16K33**:
+//!   - VCC → 5V on Arduino
+//!   - GND → GND on Arduino
+//!   - SDA → A4 (SDA) on Arduino Uno
+//!   - SCL → A5 (SCL) on Arduino Uno
+//! - **Joystick Module** (has G, V, X, Y, B pins):
+//!   - G (GND) → GND on Arduino
+//!   - V (VCC) → 5V on Arduino
+//!   - X → A0 (Analog input for X-axis)
+//!   - Y → A1 (Analog input for Y-axis)
+//!   - B (Button) → Pin D2 (with internal pull-up resistor)
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example marquee`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+use arduino_hal::I2c;
+use arduino_hal::adc;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Duration, Instant};
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Orientation};
+use rust_sensor_playground::joystick::Joystick;
+use rust_sensor_playground::marquee::{Marquee, ScrollDirection, DEFAULT_SPEED};
+use rust_sensor_playground::matrix::{self, Matrix8x8};
+use rust_sensor_playground::settings::SettingsStore;
+
+const GREETING: &str = "Hello from Rust!";
+const FAST: Duration = Duration::from_millis(30);
+
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // Start the millisecond clock for the scrolling
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
+    // Initialize I2C and the HT16K33
+    let i2c = I2c::new(
+        dp.TWI,
+        pins.a4.into_pull_up_input(), // SDA
+        pins.a5.into_pull_up_input(), // SCL
+        50000,
+    );
+    let mut display = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    display.init().unwrap();
+
+    // Joystick with the stored calibration and the matrix brightness
+    let mut adc = adc::Adc::new(dp.ADC, Default::default());
+    let mut joystick = Joystick::new(
+        pins.a0.into_analog_input(&mut adc).into_channel(),
+        pins.a1.into_analog_input(&mut adc).into_channel(),
+    )
+    .with_button(Button::new(pins.d2.into_pull_up_input(), Active::Low));
+    let settings = SettingsStore::new(arduino_hal::Eeprom::new(dp.EEPROM))
+        .load()
+        .unwrap_or_default();
+    settings.configure_joystick(&mut joystick);
+    let _ = display.set_brightness(settings.brightness);
+
+    // Scroll each text once, then switch to the next one
+    let mut marquee: Marquee<24> = Marquee::new(matrix::SIZE);
+    marquee.set_looping(false);
+    marquee.set_text(GREETING);
+    let mut show_reading = true;
+
+    loop {
+        let now = Instant::now();
+
+        match joystick.button().update(now) {
+            Ok(Some(Event::Click)) => marquee.set_direction(match marquee.direction() {
+                ScrollDirection::Left => ScrollDirection::Right,
+                ScrollDirection::Right => ScrollDirection::Left,
+            }),
+            Ok(Some(Event::LongPress(_))) => marquee.set_speed(FAST),
+            Ok(Some(Event::Released)) => marquee.set_speed(DEFAULT_SPEED),
+            _ => {}
+        }
+
+        if marquee.is_finished() {
+            if show_reading {
+                // e.g. "X:+120 Y:-45", the position from -1000 to 1000
+                let (x, y) = joystick.position(&mut adc);
+                // ufmt has no `{:+}`, so the plus sign is written by hand
+                let sign = |v: i16| if v >= 0 { "+" } else { "" };
+                marquee.clear();
+                let _ = ufmt::uwrite!(marquee, "X:{}{} Y:{}{}", sign(x), x, sign(y), y);
+            } else {
+                marquee.set_text(GREETING);
+            }
+            show_reading = !show_reading;
+        }
+
+        // Redraw only when the text moved (a failed frame shows on the next step)
+        if marquee.update(now) {
+            let mut frame = Matrix8x8::new();
+            let _ = marquee.draw(&mut frame);
+            let _ = frame.flush(&mut display);
+        }
+    }
+}
+
+// FIX: Satisfies linker requirement for bare-metal exit
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
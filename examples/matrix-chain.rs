@@ -0,0 +1,104 @@
+//! Text Across Several LED Matrices
+//!
+//! This example joins four 8x8 LED matrix modules into one 32x8 display and scrolls
+//! text across all of them. Only the modules whose picture changed are written on
+//! each step. Click the button to switch between even brightness and a brightness
+//! gradient from left to right.
+//!
+//! ## Hardware Connections
+//! - **4x 8x8 LED Matrix Module with HT16K33**, all on the same I2C bus:
+//!   - VCC → 5V on Arduino
+//!   - GND → GND on Arduino
+//!   - SDA → A4 (SDA) on Arduino Uno
+//!   - SCL → A5 (SCL) on Arduino Uno
+//! - **Button Module**:
+//!   - G (GND) → GND on Arduino
+//!   - V (VCC) → Not connected (using internal pull-up)
+//!   - S (Signal) → Pin D2
+//!
+//! ## I2C Addresses
+//! Set the address jumpers so the modules are 0x70, 0x71, 0x72 and 0x73 from left to
+//! right (A0 bridged for 0x71, A1 for 0x72, both for 0x73).
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example matrix-chain`
+//! Set `ORIENTATION` if the images don't come out upright on your modules.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+use arduino_hal::I2c;
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Duration, Instant};
+use rust_sensor_playground::ht16k33::{Orientation, MAX_BRIGHTNESS};
+use rust_sensor_playground::marquee::Marquee;
+use rust_sensor_playground::matrix_chain::MatrixChain;
+
+const MODULES: usize = 4;
+const TEXT: &str = "Rust on four matrices!";
+const SPEED: Duration = Duration::from_millis(50);
+
+// How the matrix modules are mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // Start the millisecond clock for the scrolling
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
+    // One I2C bus for all the modules
+    let i2c = I2c::new(
+        dp.TWI,
+        pins.a4.into_pull_up_input(), // SDA
+        pins.a5.into_pull_up_input(), // SCL
+        50000,
+    );
+    let mut chain: MatrixChain<_, MODULES> = MatrixChain::new(i2c).with_orientation(ORIENTATION);
+    chain.init().unwrap();
+
+    // Button pin with pull-up resistor (LOW when pressed)
+    let mut button = Button::new(pins.d2.into_pull_up_input(), Active::Low);
+    let mut gradient = false;
+
+    let mut marquee: Marquee<32> = Marquee::new(chain.width());
+    marquee.set_text(TEXT);
+    marquee.set_speed(SPEED);
+
+    loop {
+        let now = Instant::now();
+
+        if let Ok(Some(Event::Click)) = button.update(now) {
+            gradient = !gradient;
+            for module in 0..MODULES {
+                // Full brightness on the left, dimmer to the right
+                let level = if gradient {
+                    MAX_BRIGHTNESS - module as u8 * 4
+                } else {
+                    MAX_BRIGHTNESS
+                };
+                let _ = chain.set_module_brightness(module, level);
+            }
+        }
+
+        // Redraw when the text moved; modules that look the same aren't sent
+        if marquee.update(now) {
+            let _ = chain.clear(BinaryColor::Off);
+            let _ = marquee.draw(&mut chain);
+            let _ = chain.flush();
+        }
+    }
+}
+
+// FIX: Satisfies linker requirement for bare-metal exit
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
@@ -0,0 +1,135 @@
+//! Traffic Light, Buzzer and LED Matrix at Once
+//!
+//! This example runs three of the other examples side by side on the cooperative
+//! scheduler: the traffic light chase, a melody on the buzzer and the LED matrix
+//! patterns. Each one is a task that does a little work and returns, so none of
+//! them blocks the others.
+//! Click the button to switch to the next melody.
+//!
+//! ## Hardware Connections
+//! - **Traffic Light Module**: R → D11, Y → D12, G → D13, GND → GND
+//! - **Buzzer Module**: S → D8, V → 5V, G → GND
+//! - **Button Module**: S → D2, G → GND (using internal pull-up)
+//! - **8x8 LED Matrix Module with HT16K33**: SDA → A4, SCL → A5, VCC → 5V, GND → GND
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example multitask`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+use core::iter::Copied;
+use core::slice::Iter;
+use arduino_hal::port::mode::{Input, Output, PullUp};
+use arduino_hal::port::Pin;
+use arduino_hal::I2c;
+use rust_sensor_playground::bitmaps;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Deadline, Duration, Instant};
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Orientation};
+use rust_sensor_playground::melody;
+use rust_sensor_playground::scheduler::Scheduler;
+use rust_sensor_playground::tone::{Note, Player, Tone, ToneGenerator};
+use rust_sensor_playground::traffic_light::{Pattern, Sequencer};
+
+type SongPlayer = Player<Copied<Iter<'static, Note>>>;
+
+fn song_player(index: usize) -> SongPlayer {
+    Player::new(melody::ALL[index].1.iter().copied())
+}
+
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
+
+struct App {
+    // Traffic light
+    red: Pin<Output>,
+    yellow: Pin<Output>,
+    green: Pin<Output>,
+    sequencer: Sequencer,
+    next_step: Deadline,
+    // Buzzer and button
+    tone: Tone,
+    player: SongPlayer,
+    song: usize,
+    button: Button<Pin<Input<PullUp>>>,
+    // LED matrix
+    matrix: Ht16k33<I2c>,
+    bitmap: usize,
+}
+
+// Pattern steps have different lengths, so the task checks its own deadline
+fn traffic_light(app: &mut App, now: Instant) {
+    if !app.next_step.expired_at(now) {
+        return;
+    }
+    let step = app.sequencer.next_step();
+    let _ = step.lights.apply(&mut app.red, &mut app.yellow, &mut app.green);
+    app.next_step = Deadline::after_from(now, Duration::from_millis(step.duration_ms as u32));
+}
+
+fn buzzer(app: &mut App, now: Instant) {
+    if let Ok(Some(Event::Click)) = app.button.update(now) {
+        app.song = (app.song + 1) % melody::ALL.len();
+        app.tone.stop();
+        app.player = song_player(app.song);
+    }
+
+    // Start the next note once the current one is over
+    app.player.poll(&mut app.tone);
+}
+
+fn show_bitmap(app: &mut App, _now: Instant) {
+    let (_name, pattern) = &bitmaps::ALL[app.bitmap];
+    let _ = app.matrix.write_rows(pattern);
+    app.bitmap = (app.bitmap + 1) % bitmaps::ALL.len();
+}
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // Timer0 keeps the time, Timer1 plays the notes
+    clock::init(dp.TC0);
+    let tone = Tone::new(dp.TC1, pins.d8.into_output());
+    unsafe { avr_device::interrupt::enable() };
+
+    let i2c = I2c::new(
+        dp.TWI,
+        pins.a4.into_pull_up_input(), // SDA
+        pins.a5.into_pull_up_input(), // SCL
+        50000,
+    );
+    let mut matrix = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    matrix.init().unwrap();
+
+    let now = Instant::now();
+    let mut app = App {
+        red: pins.d11.into_output().downgrade(),
+        yellow: pins.d12.into_output().downgrade(),
+        green: pins.d13.into_output().downgrade(),
+        sequencer: Sequencer::new(Pattern::Chase),
+        next_step: Deadline::at(now),
+        tone,
+        player: song_player(0),
+        song: 0,
+        button: Button::new(pins.d2.into_pull_up_input().downgrade(), Active::Low),
+        matrix,
+        bitmap: 0,
+    };
+
+    let mut scheduler: Scheduler<App, 3> = Scheduler::new();
+    scheduler.add(now, Duration::from_millis(10), traffic_light).unwrap();
+    scheduler.add(now, Duration::from_millis(5), buzzer).unwrap();
+    scheduler.add(now, Duration::from_secs(3), show_bitmap).unwrap();
+    scheduler.run(&mut app)
+}
+
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
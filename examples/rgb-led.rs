@@ -1,7 +1,9 @@
 //! RGB LED Color Cycler
 //! 
 //! This example cycles through different colors on an RGB LED module.
-//! Press a button to change between colors: Red, Green, Blue, Yellow, Cyan, Magenta, White, Rainbow Fade.
+//! Click the button to change between colors: Red, Green, Blue, Yellow, Cyan, Magenta, White, Rainbow Fade.
+//! The LED pins have no hardware PWM, so a Timer2 software PWM dims them for smooth fades.
+//! Set `POLARITY` to match your module.
 //!
 //! ## Hardware Connections
 //! - **RGB LED Module** (has V, R, G, B pins):
@@ -16,151 +18,99 @@
 //!
 //! ## Usage
 //! Flash to Arduino: `cargo run --example rgb-led`
-//! Press the button to cycle through different colors.
+//! Click the button to cycle through different colors, double-click to go back one,
+//! hold it to return to the rainbow fade.
 
 #![no_std]
 #![no_main]
 
 use panic_halt as _;
+use arduino_hal::port::mode::{Input, PullUp};
+use arduino_hal::port::Pin;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Duration, Instant};
+use rust_sensor_playground::pwm::{PwmChannel, SoftPwm};
+use rust_sensor_playground::rgb::{Color, Polarity, RgbLed};
+use rust_sensor_playground::scheduler::Scheduler;
+
+// V to GND: common cathode, V to 5V: common anode
+const POLARITY: Polarity = Polarity::CommonCathode;
+
+// The fixed colors, picked by the button
+const COLORS: [Color; 7] = [
+    Color::RED,
+    Color::GREEN,
+    Color::BLUE,
+    Color::YELLOW,
+    Color::CYAN,
+    Color::MAGENTA,
+    Color::WHITE,
+];
+
+// Crossfade between the fixed colors
+const FADE_TIME: Duration = Duration::from_millis(300);
+
+struct App {
+    led: RgbLed<PwmChannel, PwmChannel, PwmChannel>,
+    button: Button<Pin<Input<PullUp>>>,
+    // 0 is the rainbow fade, 1.. the fixed colors
+    current_color: usize,
+    wheel_position: u8,
+}
+
+fn poll_button(app: &mut App, now: Instant) {
+    let choices = COLORS.len() + 1;
+    app.current_color = match app.button.update(now) {
+        Ok(Some(Event::Click)) => (app.current_color + 1) % choices,
+        Ok(Some(Event::DoubleClick)) => (app.current_color + choices - 1) % choices,
+        Ok(Some(Event::LongPress(_))) => 0,
+        _ => return,
+    };
+    if app.current_color > 0 {
+        app.led.fade_to(COLORS[app.current_color - 1], FADE_TIME, now);
+    }
+}
+
+fn animate(app: &mut App, now: Instant) {
+    if app.current_color == 0 {
+        // Rainbow fade - once around the color wheel every ~5 seconds
+        let _ = app.led.set_color(Color::wheel(app.wheel_position));
+        app.wheel_position = app.wheel_position.wrapping_add(1);
+    } else {
+        let _ = app.led.update(now);
+    }
+}
 
 #[arduino_hal::entry]
 fn main() -> ! {
     let dp = arduino_hal::Peripherals::take().unwrap();
     let pins = arduino_hal::pins!(dp);
 
-    // RGB LED pins
-    let mut green = pins.d13.into_output();
-    let mut red = pins.d12.into_output();
-    let mut blue = pins.d11.into_output();
-    
-    // Button pin with pull-up resistor
-    let button = pins.d2.into_pull_up_input();
+    // Timer0 runs the scheduler clock, Timer2 the software PWM: only D11
+    // has hardware PWM, D12 and D13 don't
+    clock::init(dp.TC0);
+    let mut pwm = SoftPwm::new(dp.TC2);
+    unsafe { avr_device::interrupt::enable() };
 
-    // Track current color and button state
-    let mut current_color = 0;
-    let mut last_button_state = button.is_high();
-
-    loop {
-        // Check for button press (pull-up means LOW when pressed)
-        let button_state = button.is_high();
-        if !button_state && last_button_state {
-            // Button was just pressed - cycle to next color
-            current_color = (current_color + 1) % 8;
-            
-            // Debounce delay
-            arduino_hal::delay_ms(300);
-        }
-        last_button_state = button_state;
+    // RGB LED pins
+    let green = pwm.channel(pins.d13.into_output()).unwrap();
+    let red = pwm.channel(pins.d12.into_output()).unwrap();
+    let blue = pwm.channel(pins.d11.into_output()).unwrap();
 
-        // Set the current color
-        match current_color {
-            0 => {
-                // Rainbow fade - cycle through colors smoothly
-                // Blue -> Cyan -> Green -> Yellow -> Red -> Magenta -> Blue
-                for _ in 0..10 {
-                    // Blue to Cyan (fade in green)
-                    red.set_low(); green.set_high(); blue.set_high();
-                    arduino_hal::delay_ms(400);
-                    
-                    // Check for button press
-                    if !button.is_high() {
-                        break;
-                    }
-                    
-                    // Cyan to Green (fade out blue)
-                    red.set_low(); green.set_high(); blue.set_low();
-                    arduino_hal::delay_ms(400);
-                    
-                    // Check for button press
-                    if !button.is_high() {
-                        break;
-                    }
-                    
-                    // Green to Yellow (fade in red)
-                    red.set_high(); green.set_high(); blue.set_low();
-                    arduino_hal::delay_ms(400);
-                    
-                    // Check for button press
-                    if !button.is_high() {
-                        break;
-                    }
-                    
-                    // Yellow to Red (fade out green)
-                    red.set_high(); green.set_low(); blue.set_low();
-                    arduino_hal::delay_ms(400);
-                    
-                    // Check for button press
-                    if !button.is_high() {
-                        break;
-                    }
-                    
-                    // Red to Magenta (fade in blue)
-                    red.set_high(); green.set_low(); blue.set_high();
-                    arduino_hal::delay_ms(400);
-                    
-                    // Check for button press
-                    if !button.is_high() {
-                        break;
-                    }
-                    
-                    // Magenta to Blue (fade out red)
-                    red.set_low(); green.set_low(); blue.set_high();
-                    arduino_hal::delay_ms(400);
-                    
-                    // Check for button press
-                    if !button.is_high() {
-                        break;
-                    }
-                }
-            }
-            1 => {
-                // Red
-                red.set_high();
-                green.set_low();
-                blue.set_low();
-            }
-            2 => {
-                // Green
-                red.set_low();
-                green.set_high();
-                blue.set_low();
-            }
-            3 => {
-                // Blue
-                red.set_low();
-                green.set_low();
-                blue.set_high();
-            }
-            4 => {
-                // Yellow (Red + Green)
-                red.set_high();
-                green.set_high();
-                blue.set_low();
-            }
-            5 => {
-                // Cyan (Green + Blue)
-                red.set_low();
-                green.set_high();
-                blue.set_high();
-            }
-            6 => {
-                // Magenta (Red + Blue)
-                red.set_high();
-                green.set_low();
-                blue.set_high();
-            }
-            7 => {
-                // White (All on)
-                red.set_high();
-                green.set_high();
-                blue.set_high();
-            }
-            _ => {}
-        }
+    let mut app = App {
+        led: RgbLed::new(red, green, blue).with_polarity(POLARITY),
+        // Button pin with pull-up resistor (LOW when pressed)
+        button: Button::new(pins.d2.into_pull_up_input().downgrade(), Active::Low),
+        current_color: 0,
+        wheel_position: 0,
+    };
 
-        // Small delay to check button frequently
-        arduino_hal::delay_ms(50);
-    }
+    // The colors change every 20 ms while the button stays responsive
+    let mut scheduler: Scheduler<App, 2> = Scheduler::new();
+    let now = Instant::now();
+    scheduler.add(now, Duration::from_millis(5), poll_button).unwrap();
+    scheduler.add(now, Duration::from_millis(20), animate).unwrap();
+    scheduler.run(&mut app)
 }
 
 // FIX: Satisfies linker requirement for bare-metal exit
@@ -0,0 +1,219 @@
+//! Snake on the 8x8 LED Matrix
+//!
+//! This example plays Snake on the 8x8 LED matrix. Steer the snake with the joystick,
+//! eat the blinking food to grow and score, and don't run into the walls or your own
+//! tail - every food makes the snake a little faster. The buzzer beeps on every food
+//! and plays a tune when the game starts and ends.
+//!
+//! Between games the matrix scrolls the score and the high score, which is kept in EEPROM.
+//! Click the button to start a game or pause it. Hold the button between games to switch
+//! between solid walls and walls the snake passes through to the other side.
+//!
+//! ## Hardware Connections
+//! - **8x8 LED Matrix Module with HT16K33**:
+//!   - VCC → 5V on Arduino
+//!   - GND → GND on Arduino
+//!   - SDA → A4 (SDA) on Arduino Uno
+//!   - SCL → A5 (SCL) on Arduino Uno
+//!
+//! - **Joystick Module**:
+//!   - G (GND) → GND on Arduino
+//!   - V (VCC) → 5V on Arduino
+//!   - X → A0 (Analog input for X-axis)
+//!   - Y → A1 (Analog input for Y-axis)
+//!   - B (Button) → D2
+//!
+//! - **Buzzer Module**:
+//!   - G (GND) → GND on Arduino
+//!   - V (VCC) → 5V on Arduino
+//!   - S (Signal) → Pin D8
+//!
+//! ## Usage
+//! Flash to Arduino: `cargo run --example snake`
+//! Set `ORIENTATION` if the images don't come out upright on your module.
+//! Click the joystick button to start, then steer with the stick.
+
+#![no_std]
+#![no_main]
+
+use core::iter::Copied;
+use core::slice::Iter;
+
+use panic_halt as _;
+use arduino_hal::I2c;
+use arduino_hal::adc;
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Deadline, Duration, Instant};
+use rust_sensor_playground::ht16k33::{self, Ht16k33, Orientation};
+use rust_sensor_playground::joystick::Joystick;
+use rust_sensor_playground::marquee::Marquee;
+use rust_sensor_playground::matrix::{self, Matrix8x8};
+use rust_sensor_playground::settings::SettingsStore;
+use rust_sensor_playground::snake::{self, Game, HighScoreStore, Step, Walls};
+use rust_sensor_playground::tone::{Note, Player, Tone, ToneGenerator};
+
+// How long the crashed snake stays on the matrix before the score scrolls by
+const CRASH_PAUSE: Duration = Duration::from_millis(1500);
+const BLINK_INTERVAL: Duration = Duration::from_millis(200);
+
+// How the matrix module is mounted, e.g.
+// `Orientation::new().with_rotation(Rotation::Half).with_columns(ht16k33::OFFSET_COLUMNS)`
+const ORIENTATION: Orientation = Orientation::new();
+
+#[derive(Clone, Copy)]
+enum Mode {
+    // Scrolling the title or the last score, waiting for a click
+    Waiting,
+    Playing,
+    Paused,
+    // Showing the crashed snake until the deadline
+    Crashed(Deadline),
+}
+
+fn sound(notes: &'static [Note]) -> Player<Copied<Iter<'static, Note>>> {
+    Player::new(notes.iter().copied())
+}
+
+#[arduino_hal::entry]
+fn main() -> ! {
+    let dp = arduino_hal::Peripherals::take().unwrap();
+    let pins = arduino_hal::pins!(dp);
+
+    // Pin 8 (PB0) for the buzzer, toggled by the Timer1 interrupt
+    let mut tone = Tone::new(dp.TC1, pins.d8.into_output());
+
+    // Start the millisecond clock for the game steps
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
+    // Initialize I2C and the HT16K33
+    let i2c = I2c::new(
+        dp.TWI,
+        pins.a4.into_pull_up_input(), // SDA
+        pins.a5.into_pull_up_input(), // SCL
+        50000,
+    );
+    let mut display = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(ORIENTATION);
+    display.init().unwrap();
+
+    // Joystick with the stored calibration and the matrix brightness
+    let mut adc = adc::Adc::new(dp.ADC, Default::default());
+    let mut joystick = Joystick::new(
+        pins.a0.into_analog_input(&mut adc).into_channel(),
+        pins.a1.into_analog_input(&mut adc).into_channel(),
+    )
+    .with_button(Button::new(pins.d2.into_pull_up_input(), Active::Low));
+    let mut eeprom = arduino_hal::Eeprom::new(dp.EEPROM);
+    let settings = SettingsStore::new(&mut eeprom).load().unwrap_or_default();
+    settings.configure_joystick(&mut joystick);
+    let _ = display.set_brightness(settings.brightness);
+
+    // The high score lives next to the settings
+    let mut high_scores = HighScoreStore::new(&mut eeprom);
+    let mut best = high_scores.load();
+
+    let mut marquee: Marquee<24> = Marquee::new(matrix::SIZE);
+    let _ = ufmt::uwrite!(marquee, "SNAKE  Best {}", best);
+
+    let mut walls = Walls::Solid;
+    let mut game = Game::new(0);
+    let mut mode = Mode::Waiting;
+    let mut player = sound(&[]);
+    let mut next_blink = Deadline::after(BLINK_INTERVAL);
+    let mut blink = true;
+
+    loop {
+        let now = Instant::now();
+
+        match (joystick.button().update(now), mode) {
+            (Ok(Some(Event::Click)), Mode::Waiting) => {
+                // How long it took to click is as random as it gets
+                game = Game::new(now.as_millis() as u16);
+                game.set_walls(walls);
+                mode = Mode::Playing;
+                tone.stop();
+                player = sound(snake::START_SOUND);
+            }
+            (Ok(Some(Event::Click)), Mode::Playing) => mode = Mode::Paused,
+            (Ok(Some(Event::Click)), Mode::Paused) => mode = Mode::Playing,
+            (Ok(Some(Event::LongPress(_))), Mode::Waiting) => {
+                walls = match walls {
+                    Walls::Solid => Walls::Wrap,
+                    Walls::Wrap => Walls::Solid,
+                };
+                marquee.set_text(match walls {
+                    Walls::Solid => "Walls",
+                    Walls::Wrap => "No walls",
+                });
+            }
+            _ => {}
+        }
+
+        if next_blink.expired() {
+            next_blink = Deadline::after(BLINK_INTERVAL);
+            blink = !blink;
+        }
+
+        match mode {
+            Mode::Waiting => {
+                // Redraw only when the text moved
+                if marquee.update(now) {
+                    let mut frame = Matrix8x8::new();
+                    let _ = marquee.draw(&mut frame);
+                    let _ = frame.flush(&mut display);
+                }
+            }
+            Mode::Playing | Mode::Paused => {
+                let playing = matches!(mode, Mode::Playing);
+                if let Some(direction) = joystick.direction(&mut adc).filter(|_| playing) {
+                    game.turn(direction);
+                }
+                let step = if playing { game.update(now) } else { None };
+                match step {
+                    Some(Step::Ate) => {
+                        tone.stop();
+                        player = sound(snake::EAT_SOUND);
+                    }
+                    Some(Step::Crashed | Step::Won) => {
+                        mode = Mode::Crashed(Deadline::after(CRASH_PAUSE));
+                        tone.stop();
+                        player = if high_scores.submit(game.score()) {
+                            best = game.score();
+                            sound(snake::HIGH_SCORE_SOUND)
+                        } else {
+                            sound(snake::CRASH_SOUND)
+                        };
+                    }
+                    _ => {}
+                }
+
+                // The food blinks; the paused snake blinks too. The driver only sends
+                // what changed, so drawing every pass is cheap.
+                let mut frame = game.frame();
+                if let Some((x, y)) = game.food() {
+                    frame.set_pixel(x as i32, y as i32, blink);
+                }
+                if !playing && !blink {
+                    frame = Matrix8x8::new();
+                }
+                let _ = frame.flush(&mut display);
+            }
+            Mode::Crashed(until) => {
+                if until.expired() {
+                    marquee.clear();
+                    let _ = ufmt::uwrite!(marquee, "Score {}  Best {}", game.score(), best);
+                    mode = Mode::Waiting;
+                }
+            }
+        }
+
+        // Keep the current sound effect going
+        player.poll(&mut tone);
+    }
+}
+
+// FIX: Satisfies linker requirement for bare-metal exit
+#[no_mangle]
+pub extern "C" fn exit(_code: i32) -> ! {
+    loop {}
+}
@@ -1,7 +1,7 @@
 //! Traffic Light Controller
 //! 
 //! This example simulates a traffic light using the KEYESTUDIO Traffic Light module.
-//! Press a button to cycle through different patterns: standard traffic sequence, 
+//! Click the button to cycle through different patterns: standard traffic sequence, 
 //! flashing yellow, and police lights.
 //!
 //! ## Hardware Connections
@@ -17,78 +17,65 @@
 //!
 //! ## Usage
 //! Flash to Arduino: `cargo run --example traffic-light`
-//! Press the button to cycle through different light patterns.
+//! Click the button to cycle through different light patterns, hold it to pause the lights.
+//! The selected pattern is kept in EEPROM.
 
 #![no_std]
 #![no_main]
 
 use panic_halt as _;
-use arduino_hal::prelude::*; // Import traits for delays
+use rust_sensor_playground::button::{Active, Button, Event};
+use rust_sensor_playground::clock::{self, Deadline, Duration, Instant};
+use rust_sensor_playground::settings::SettingsStore;
+use rust_sensor_playground::traffic_light::{Lights, Pattern, Sequencer};
 
 #[arduino_hal::entry]
 fn main() -> ! {
     let dp = arduino_hal::Peripherals::take().unwrap();
     let pins = arduino_hal::pins!(dp);
 
+    // Start the millisecond clock
+    clock::init(dp.TC0);
+    unsafe { avr_device::interrupt::enable() };
+
     // MAPPING: Green=13, Yellow=12, Red=11, Button=2
     let mut green = pins.d13.into_output();
     let mut yellow = pins.d12.into_output();
     let mut red = pins.d11.into_output();
-    let button = pins.d2.into_pull_up_input(); // Use pull-up like buzzer.rs
+    let mut button = Button::new(pins.d2.into_pull_up_input(), Active::Low); // Use pull-up like buzzer.rs
 
-    let mut current_pattern: u8 = 0;
-    let mut last_button_state = button.is_high();
+    // Patterns: "Knight Rider" chase, police strobe, "warning" flash
+    // starting with the one selected last time
+    let mut store = SettingsStore::new(arduino_hal::Eeprom::new(dp.EEPROM));
+    let mut settings = store.load().unwrap_or_default();
+    let mut sequencer = Sequencer::new(Pattern::from_index(settings.light_pattern));
+    let mut next_step = Deadline::after(Duration::ZERO);
+    let mut paused = false;
 
     loop {
-        // Check for button press (pull-up means LOW when pressed)
-        let button_state = button.is_high();
-        if !button_state && last_button_state {
-            // Button was just pressed - cycle to next pattern
-            current_pattern = (current_pattern + 1) % 3;
-            
-            // Turn off all LEDs when switching patterns
-            red.set_low();
-            yellow.set_low();
-            green.set_low();
-            
-            // Debounce delay
-            arduino_hal::delay_ms(300);
-        }
-        last_button_state = button_state;
+        match button.update(Instant::now()) {
+            Ok(Some(Event::Click)) => {
+                // Cycle to next pattern
+                sequencer.set_pattern(sequencer.pattern().next());
+                settings.light_pattern = sequencer.pattern().index();
+                store.save(&settings);
+                paused = false;
 
-        // Execute the current pattern
-        match current_pattern {
-            0 => {
-                // --- PATTERN 1: The "Knight Rider" Bounce (Chase) ---
-                // Red -> Yellow -> Green -> Yellow -> Red
-                
-                // Down
-                red.set_high(); arduino_hal::delay_ms(100); red.set_low();
-                yellow.set_high(); arduino_hal::delay_ms(100); yellow.set_low();
-                green.set_high(); arduino_hal::delay_ms(100); green.set_low();
-                
-                // Up
-                yellow.set_high(); arduino_hal::delay_ms(100); yellow.set_low();
-            }
-            1 => {
-                // --- PATTERN 2: Police Strobe (Fast Flash) ---
-                // Flash Red fast 3 times, then Green fast 3 times
-                for _ in 0..3 {
-                    red.set_high(); arduino_hal::delay_ms(40); red.set_low(); arduino_hal::delay_ms(40);
-                }
-                for _ in 0..3 {
-                    green.set_high(); arduino_hal::delay_ms(40); green.set_low(); arduino_hal::delay_ms(40);
-                }
-            }
-            2 => {
-                // --- PATTERN 3: "Warning" (All ON) ---
-                red.set_high(); yellow.set_high(); green.set_high();
-                arduino_hal::delay_ms(200);
-                red.set_low(); yellow.set_low(); green.set_low();
-                arduino_hal::delay_ms(200);
+                // Turn off all LEDs when switching patterns
+                Lights::OFF.apply(&mut red, &mut yellow, &mut green).unwrap();
+                next_step = Deadline::after(Duration::ZERO);
             }
+            Ok(Some(Event::LongPress(_))) => paused = !paused,
             _ => {}
         }
+
+        // Show the next step of the current pattern once this one is over,
+        // the button stays responsive in between
+        if !paused && next_step.expired() {
+            let step = sequencer.next_step();
+            step.lights.apply(&mut red, &mut yellow, &mut green).unwrap();
+            next_step = Deadline::after(Duration::from_millis(step.duration_ms as u32));
+        }
     }
 }
 
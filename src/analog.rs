@@ -0,0 +1,18 @@
+//! Analog Input Abstraction
+//!
+//! `embedded-hal` 1.0 has no ADC trait, so drivers that need analog readings
+//! (like the joystick) use [`AnalogRead`] instead. The ADC itself is passed
+//! to every read, matching how `arduino-hal` shares one ADC between channels.
+
+/// A single analog channel read through a shared ADC
+pub trait AnalogRead<ADC> {
+    /// Take one blocking 10-bit sample (0-1023).
+    fn analog_read(&mut self, adc: &mut ADC) -> u16;
+}
+
+#[cfg(target_arch = "avr")]
+impl AnalogRead<arduino_hal::Adc> for arduino_hal::adc::Channel {
+    fn analog_read(&mut self, adc: &mut arduino_hal::Adc) -> u16 {
+        adc.read_blocking(self)
+    }
+}
@@ -0,0 +1,506 @@
+//! Matrix Animations
+//!
+//! Plays frame sequences on the 8x8 matrix without blocking. An animation is
+//! a table of [`Frame`]s in program memory, each with its own duration; the
+//! ASCII-art files in `assets/animations/` are turned into such tables at
+//! build time (see [`crate::animations`]).
+//!
+//! [`Animator`] steps through the frames by time: once, looping, or back and
+//! forth ([`Playback`]). Between two frames it can run a [`Transition`] -
+//! a wipe, a slide, a dissolve or a fade through the display brightness.
+//! [`Change`] runs a single transition between any two frames, e.g. for a
+//! slideshow.
+//!
+//! Both return a [`Shown`] whenever the picture changes; its
+//! [`flush`](Shown::flush) sends it to the display.
+//!
+//! ## Example
+//! ```ignore
+//! let mut animator = Animator::new(&animations::SPINNER);
+//! animator.set_transition(Transition::Dissolve, Duration::from_millis(200));
+//! loop {
+//!     if let Some(shown) = animator.update(Instant::now()) {
+//!         shown.flush(&mut ht16k33, settings.brightness).unwrap();
+//!     }
+//! }
+//! ```
+
+use embedded_hal::i2c::I2c;
+
+use crate::clock::{Duration, Instant};
+use crate::ht16k33::{self, Ht16k33, ROWS};
+use crate::matrix::Matrix8x8;
+use crate::progmem;
+
+/// One picture of an animation and how long it stays up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Frame {
+    pub rows: [u8; ROWS],
+    pub duration_ms: u16,
+}
+
+impl Frame {
+    pub const fn new(rows: [u8; ROWS], duration_ms: u16) -> Self {
+        Self { rows, duration_ms }
+    }
+
+    pub fn matrix(&self) -> Matrix8x8 {
+        Matrix8x8::from_rows(self.rows)
+    }
+}
+
+/// What happens after the last frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Playback {
+    /// Stop on the last frame
+    Once,
+    /// Start over at the first frame
+    #[default]
+    Loop,
+    /// Play backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// Direction a [`Transition`] moves in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// How one frame turns into the next
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transition {
+    /// Switch at once
+    #[default]
+    Cut,
+    /// An edge moving in the direction uncovers the new frame
+    Wipe(Direction),
+    /// Both frames move in the direction, the new one pushing the old out
+    Slide(Direction),
+    /// The new frame appears pixel by pixel
+    Dissolve,
+    /// The display dims out and back up with the new frame
+    Fade,
+}
+
+impl Transition {
+    /// The picture `progress` (0-255) of the way from `from` to `to`.
+    pub fn blend(self, from: &Matrix8x8, to: &Matrix8x8, progress: u8) -> Shown {
+        let (from, to) = (from.rows(), to.rows());
+        // Columns or rows covered so far
+        let k = progress as usize * 8 / 256;
+        let mut rows = [0u8; ROWS];
+        let mut level = u8::MAX;
+        match self {
+            Transition::Cut => rows = *to,
+            Transition::Wipe(direction) => {
+                for y in 0..ROWS {
+                    rows[y] = match direction {
+                        // Bit x is column x, so the left edge is bit 0
+                        Direction::Left => mix(from[y], to[y], (0xFF00u16 >> k) as u8),
+                        Direction::Right => mix(from[y], to[y], ((1u16 << k) - 1) as u8),
+                        Direction::Up => {
+                            if y >= ROWS - k {
+                                to[y]
+                            } else {
+                                from[y]
+                            }
+                        }
+                        Direction::Down => {
+                            if y < k {
+                                to[y]
+                            } else {
+                                from[y]
+                            }
+                        }
+                    };
+                }
+            }
+            Transition::Slide(direction) => {
+                for y in 0..ROWS {
+                    rows[y] = match direction {
+                        Direction::Left => {
+                            ((from[y] as u16 >> k) | (to[y] as u16) << (8 - k)) as u8
+                        }
+                        Direction::Right => ((from[y] as u16) << k | to[y] as u16 >> (8 - k)) as u8,
+                        Direction::Up if y + k < ROWS => from[y + k],
+                        Direction::Up => to[y + k - ROWS],
+                        Direction::Down if y >= k => from[y - k],
+                        Direction::Down => to[y + ROWS - k],
+                    };
+                }
+            }
+            Transition::Dissolve => {
+                let shown = progress as usize * 64 / 256;
+                for y in 0..ROWS {
+                    let mut mask = 0;
+                    for x in 0..8 {
+                        // 37 is coprime to 64: a fixed shuffle of all pixels
+                        if (y * 8 + x) * 37 % 64 < shown {
+                            mask |= 1 << x;
+                        }
+                    }
+                    rows[y] = mix(from[y], to[y], mask);
+                }
+            }
+            Transition::Fade => {
+                let (frame, distance) = if progress < 128 {
+                    (from, 127 - progress)
+                } else {
+                    (to, progress - 128)
+                };
+                rows = *frame;
+                level = distance * 2 + 1;
+            }
+        }
+        Shown {
+            frame: Matrix8x8::from_rows(rows),
+            level,
+        }
+    }
+}
+
+// Bits set in `mask` from `to`, the others from `from`
+fn mix(from: u8, to: u8, mask: u8) -> u8 {
+    from & !mask | to & mask
+}
+
+/// A picture to put on the display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shown {
+    pub frame: Matrix8x8,
+    /// Brightness relative to the configured one, 0-255
+    pub level: u8,
+}
+
+impl Shown {
+    fn full(frame: Matrix8x8) -> Self {
+        Self {
+            frame,
+            level: u8::MAX,
+        }
+    }
+
+    /// Display brightness for `level` at a configured `brightness`;
+    /// `None` for a blank display.
+    pub fn brightness(&self, brightness: u8) -> Option<u8> {
+        // One step more than the brightness levels: the lowest is blank
+        let scaled = (brightness as u16 + 2) * self.level as u16 / 256;
+        (self.level > 0 && scaled > 0).then(|| scaled as u8 - 1)
+    }
+
+    /// Send the picture to the display, scaling `brightness` by the level.
+    pub fn flush<I2C, E>(
+        &self,
+        display: &mut Ht16k33<I2C>,
+        brightness: u8,
+    ) -> Result<(), ht16k33::Error<E>>
+    where
+        I2C: I2c<Error = E>,
+    {
+        match self.brightness(brightness) {
+            Some(level) => {
+                display.set_brightness(level)?;
+                self.frame.flush(display)
+            }
+            None => Matrix8x8::new().flush(display),
+        }
+    }
+}
+
+/// One transition between two frames, started at a given time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Change {
+    from: Matrix8x8,
+    to: Matrix8x8,
+    transition: Transition,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Change {
+    pub fn new(
+        from: Matrix8x8,
+        to: Matrix8x8,
+        transition: Transition,
+        start: Instant,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            from,
+            to,
+            transition,
+            start,
+            duration,
+        }
+    }
+
+    pub fn target(&self) -> &Matrix8x8 {
+        &self.to
+    }
+
+    /// The picture at `now`, or `None` once the transition has finished.
+    pub fn shown_at(&self, now: Instant) -> Option<Shown> {
+        let elapsed = now.duration_since(self.start).as_millis();
+        let total = self.duration.as_millis();
+        if elapsed >= total {
+            return None;
+        }
+        let progress = (elapsed as u64 * 256 / total as u64) as u8;
+        Some(self.transition.blend(&self.from, &self.to, progress))
+    }
+}
+
+/// Plays a [`Frame`] table from program memory
+pub struct Animator {
+    frames: &'static [Frame],
+    playback: Playback,
+    transition: Transition,
+    transition_time: Duration,
+    index: usize,
+    forward: bool,
+    // When the current frame came up, `None` until the first update
+    start: Option<Instant>,
+    shown: Option<Shown>,
+}
+
+impl Animator {
+    /// Play `frames` (a table in program memory) from the first frame,
+    /// looping, with cuts between frames.
+    pub fn new(frames: &'static [Frame]) -> Self {
+        Self {
+            frames,
+            playback: Playback::Loop,
+            transition: Transition::Cut,
+            transition_time: Duration::ZERO,
+            index: 0,
+            forward: true,
+            start: None,
+            shown: None,
+        }
+    }
+
+    pub fn set_playback(&mut self, playback: Playback) {
+        self.playback = playback;
+    }
+
+    /// Run `transition` for `duration` after each frame.
+    pub fn set_transition(&mut self, transition: Transition, duration: Duration) {
+        self.transition = transition;
+        self.transition_time = duration;
+    }
+
+    /// Start over at the first frame.
+    pub fn restart(&mut self) {
+        self.index = 0;
+        self.forward = true;
+        self.start = None;
+        self.shown = None;
+    }
+
+    /// Index of the frame shown (or being transitioned from).
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// `true` once a [`Playback::Once`] animation shows its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.playback == Playback::Once
+            && self.index + 1 >= self.frames.len()
+            && self.start.is_some()
+    }
+
+    /// Advance to `now`. Returns the picture to show if it changed since the
+    /// last call.
+    pub fn update(&mut self, now: Instant) -> Option<Shown> {
+        let shown = self.shown_at(now)?;
+        if self.shown == Some(shown) {
+            return None;
+        }
+        self.shown = Some(shown);
+        Some(shown)
+    }
+
+    fn shown_at(&mut self, now: Instant) -> Option<Shown> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let mut start = *self.start.get_or_insert(now);
+        loop {
+            let frame = self.frame(self.index);
+            // Zero-length frames would never let the loop catch up
+            let hold_end = start + Duration::from_millis(frame.duration_ms.max(1) as u32);
+            if !now.is_at_or_after(hold_end) || self.is_finished() {
+                return Some(Shown::full(frame.matrix()));
+            }
+            let next = self.next_index();
+            let change = Change::new(
+                frame.matrix(),
+                self.frame(next).matrix(),
+                self.transition,
+                hold_end,
+                self.transition_time,
+            );
+            if let Some(shown) = change.shown_at(now) {
+                return Some(shown);
+            }
+
+            start = hold_end + self.transition_time;
+            if self.playback == Playback::PingPong && (next == 0 || next + 1 == self.frames.len()) {
+                self.forward = next == 0;
+            }
+            self.index = next;
+            self.start = Some(start);
+        }
+    }
+
+    fn next_index(&self) -> usize {
+        let last = self.frames.len() - 1;
+        match self.playback {
+            _ if last == 0 => 0,
+            Playback::PingPong if !self.forward => self.index - 1,
+            Playback::Once | Playback::PingPong => (self.index + 1).min(last),
+            Playback::Loop => (self.index + 1) % self.frames.len(),
+        }
+    }
+
+    fn frame(&self, index: usize) -> Frame {
+        progmem::read_value(&self.frames[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockI2c;
+
+    static FRAMES: [Frame; 3] = [
+        Frame::new([1; ROWS], 100),
+        Frame::new([2; ROWS], 100),
+        Frame::new([4; ROWS], 100),
+    ];
+
+    fn row(shown: Option<Shown>) -> Option<u8> {
+        shown.map(|shown| shown.frame.rows()[0])
+    }
+
+    fn at(ms: u32) -> Instant {
+        Instant::from_millis(ms)
+    }
+
+    #[test]
+    fn plays_frames_by_duration() {
+        let mut animator = Animator::new(&FRAMES);
+        assert_eq!(row(animator.update(at(1000))), Some(1));
+        assert_eq!(animator.update(at(1099)), None);
+        assert_eq!(row(animator.update(at(1100))), Some(2));
+        assert_eq!(row(animator.update(at(1200))), Some(4));
+        assert_eq!(row(animator.update(at(1300))), Some(1));
+        // A late update catches up
+        assert_eq!(row(animator.update(at(1550))), Some(4));
+    }
+
+    #[test]
+    fn once_stops_on_the_last_frame() {
+        let mut animator = Animator::new(&FRAMES);
+        animator.set_playback(Playback::Once);
+        animator.update(at(0));
+        assert!(!animator.is_finished());
+        assert_eq!(row(animator.update(at(5000))), Some(4));
+        assert!(animator.is_finished());
+        assert_eq!(animator.update(at(6000)), None);
+    }
+
+    #[test]
+    fn ping_pong_turns_around() {
+        let mut animator = Animator::new(&FRAMES);
+        animator.set_playback(Playback::PingPong);
+        let rows: Vec<_> = (0..7)
+            .map(|i| row(animator.update(at(i * 100))).unwrap())
+            .collect();
+        assert_eq!(rows, [1, 2, 4, 2, 1, 2, 4]);
+    }
+
+    #[test]
+    fn transitions_run_between_frames() {
+        static HALVES: [Frame; 2] = [Frame::new([0x0F; ROWS], 100), Frame::new([0xF0; ROWS], 100)];
+        let mut animator = Animator::new(&HALVES);
+        animator.set_transition(
+            Transition::Wipe(Direction::Right),
+            Duration::from_millis(80),
+        );
+        animator.update(at(0));
+        // Halfway: the left 4 columns already show the next frame
+        assert_eq!(row(animator.update(at(140))), Some(0));
+        assert_eq!(row(animator.update(at(180))), Some(0xF0));
+        assert_eq!(animator.update(at(279)), None);
+        assert_eq!(animator.index(), 1);
+    }
+
+    #[test]
+    fn wipes_and_slides() {
+        let from = Matrix8x8::from_rows([0xFF; ROWS]);
+        let to = Matrix8x8::new();
+        let blend = |transition: Transition| transition.blend(&from, &to, 128).frame;
+        assert_eq!(blend(Transition::Wipe(Direction::Left)).rows()[0], 0x0F);
+        assert_eq!(blend(Transition::Wipe(Direction::Right)).rows()[0], 0xF0);
+        assert_eq!(
+            blend(Transition::Wipe(Direction::Down)).rows(),
+            &[0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(blend(Transition::Slide(Direction::Left)).rows()[0], 0x0F);
+        assert_eq!(
+            blend(Transition::Slide(Direction::Up)).rows(),
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]
+        );
+
+        let arrow = Matrix8x8::from_rows([0b0000_0001; ROWS]);
+        let slid = Transition::Slide(Direction::Right)
+            .blend(&arrow, &arrow, 64)
+            .frame;
+        assert_eq!(slid.rows()[0], 0b0000_0100);
+    }
+
+    #[test]
+    fn dissolve_covers_every_pixel() {
+        let from = Matrix8x8::new();
+        let to = Matrix8x8::from_rows([0xFF; ROWS]);
+        let lit = |progress| {
+            let frame = Transition::Dissolve.blend(&from, &to, progress).frame;
+            frame.rows().iter().map(|row| row.count_ones()).sum::<u32>()
+        };
+        assert_eq!(lit(0), 0);
+        assert_eq!(lit(128), 32);
+        assert_eq!(lit(255), 63);
+    }
+
+    #[test]
+    fn fade_dims_through_blank() {
+        let from = Matrix8x8::from_rows([1; ROWS]);
+        let to = Matrix8x8::from_rows([2; ROWS]);
+        let fade = |progress| Transition::Fade.blend(&from, &to, progress);
+        assert_eq!((fade(0).frame, fade(0).level), (from, 255));
+        assert_eq!(fade(127).level, 1);
+        assert_eq!((fade(128).frame, fade(128).level), (to, 1));
+        assert_eq!(fade(0).brightness(15), Some(15));
+        assert_eq!(fade(64).brightness(15), Some(7));
+        assert_eq!(fade(64).brightness(0), None);
+        assert_eq!(fade(255).brightness(0), Some(0));
+        assert_eq!(fade(127).brightness(15), None);
+    }
+
+    #[test]
+    fn blank_frames_are_flushed_empty() {
+        let i2c = MockI2c::new();
+        let mut display = Ht16k33::new(i2c.clone(), ht16k33::DEFAULT_ADDRESS);
+        let shown =
+            Transition::Fade.blend(&Matrix8x8::from_rows([1; ROWS]), &Matrix8x8::new(), 127);
+        shown.flush(&mut display, 15).unwrap();
+        assert_eq!(i2c.writes().len(), 1);
+        assert_eq!(display.buffer(), &[0; 2 * ROWS]);
+    }
+}
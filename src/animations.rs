@@ -0,0 +1,31 @@
+//! Animations for the 8x8 LED Matrix
+//!
+//! Generated at build time from the files in `assets/animations/`: every
+//! frame is a `frame <ms>` line with the frame's duration, followed by an
+//! 8x8 ASCII-art bitmap like the ones in `assets/bitmaps/`. Each file
+//! becomes a [`Frame`] table in program memory named after it
+//! (`spinner.txt` -> [`SPINNER`]), ready for
+//! [`Animator`](crate::animation::Animator).
+
+use crate::animation::Frame;
+
+include!(concat!(env!("OUT_DIR"), "/animations.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_match_ascii_art() {
+        assert_eq!(SPINNER.len(), 4);
+        assert_eq!(SPINNER[0], Frame::new([0b00011000; 8], 120));
+        assert_eq!(SPINNER[2].rows[3], 0xFF);
+    }
+
+    #[test]
+    fn all_lists_every_animation() {
+        let names: Vec<_> = ALL.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["bounce", "heartbeat", "spinner"]);
+        assert_eq!(ALL[2].1.len(), SPINNER.len());
+    }
+}
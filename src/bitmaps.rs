@@ -0,0 +1,26 @@
+//! Bitmaps for the 8x8 LED Matrix
+//!
+//! Generated at build time from the ASCII-art files in `assets/bitmaps/`:
+//! eight lines of eight characters, `#` for a lit pixel and `.` for an unlit
+//! one. Each file becomes a `[u8; 8]` row table named after it
+//! (`heart.txt` -> [`HEART`]), ready for [`crate::ht16k33::Ht16k33::write_rows`].
+
+include!(concat!(env!("OUT_DIR"), "/bitmaps.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heart_matches_ascii_art() {
+        assert_eq!(HEART[0], 0b01100110);
+        assert_eq!(HEART[6], 0b00011000);
+        assert_eq!(HEART[7], 0);
+    }
+
+    #[test]
+    fn all_lists_every_bitmap() {
+        assert_eq!(ALL[0], ("heart", HEART));
+        assert_eq!(ALL[1], ("smiley", SMILEY));
+    }
+}
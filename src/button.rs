@@ -0,0 +1,342 @@
+//! Debounced Push Button
+//!
+//! Turns the raw level of a button pin into events. A change of level only
+//! counts once the pin has kept it for [`Timing::debounce`], so the main
+//! loop never has to sleep after an edge. Call [`Button::update`] every few
+//! milliseconds (from the main loop or a scheduler task) and act on the
+//! events it returns:
+//!
+//! - [`Event::Pressed`] / [`Event::Released`] on every debounced edge
+//! - [`Event::Click`] after a short press, once no second press followed
+//!   within [`Timing::double_click`]
+//! - [`Event::DoubleClick`] instead of two clicks
+//! - [`Event::LongPress`] once when held for [`Timing::long_press`]; the
+//!   release that ends it is not a click
+//! - [`Event::Repeat`] while held, with [`Timing::repeat`] enabled
+//!
+//! ## Example
+//! ```ignore
+//! let mut button = Button::new(pins.d2.into_pull_up_input(), Active::Low);
+//! loop {
+//!     match button.update(Instant::now()) {
+//!         Ok(Some(Event::Click)) => next_song(),
+//!         Ok(Some(Event::LongPress(_))) => stop(),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use embedded_hal::digital::InputPin;
+
+use crate::clock::{Deadline, Duration, Instant};
+
+/// Pin level of a pressed button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Active {
+    /// Pressed pulls the pin to GND (the kit button with the internal pull-up)
+    Low,
+    /// Pressed pulls the pin to VCC
+    High,
+}
+
+/// Something that happened to the button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Pressed,
+    Released,
+    Click,
+    DoubleClick,
+    /// Held for the long-press time; carries how long it has been held
+    LongPress(Duration),
+    /// Auto-repeat while held
+    Repeat,
+}
+
+/// Timing of the button events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timing {
+    /// How long the pin has to keep a new level before it counts
+    pub debounce: Duration,
+    /// Longest gap between two clicks of a double click, `None` to report
+    /// every click right away
+    pub double_click: Option<Duration>,
+    /// Hold time of a long press, `None` to disable long presses
+    pub long_press: Option<Duration>,
+    /// Delay before the first repeat and interval between the following
+    /// ones, `None` to disable auto-repeat
+    pub repeat: Option<(Duration, Duration)>,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(20),
+            double_click: Some(Duration::from_millis(300)),
+            long_press: Some(Duration::from_millis(800)),
+            repeat: None,
+        }
+    }
+}
+
+/// Debounced button on an input pin
+pub struct Button<P> {
+    pin: P,
+    active: Active,
+    timing: Timing,
+    raw: bool,
+    raw_since: Instant,
+    pressed: bool,
+    pressed_at: Instant,
+    long_pressed: bool,
+    next_repeat: Option<Deadline>,
+    clicks: u8,
+    click_deadline: Option<Deadline>,
+    pending: Option<Event>,
+}
+
+impl<P: InputPin> Button<P> {
+    /// Button with the [`Timing::default`] timing, starting released.
+    pub fn new(pin: P, active: Active) -> Self {
+        Self {
+            pin,
+            active,
+            timing: Timing::default(),
+            raw: false,
+            raw_since: Instant::from_millis(0),
+            pressed: false,
+            pressed_at: Instant::from_millis(0),
+            long_pressed: false,
+            next_repeat: None,
+            clicks: 0,
+            click_deadline: None,
+            pending: None,
+        }
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    /// Debounced state.
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// How long the button has been held, zero while released.
+    pub fn held_for(&self, now: Instant) -> Duration {
+        if self.pressed {
+            now.duration_since(self.pressed_at)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// Sample the pin and return the next event, if any.
+    ///
+    /// Returns at most one event per call; call it again soon to get the
+    /// rest (a release can be followed by a click, for example).
+    pub fn update(&mut self, now: Instant) -> Result<Option<Event>, P::Error> {
+        if let Some(event) = self.pending.take() {
+            return Ok(Some(event));
+        }
+
+        let active = match self.active {
+            Active::Low => self.pin.is_low()?,
+            Active::High => self.pin.is_high()?,
+        };
+        if active != self.raw {
+            self.raw = active;
+            self.raw_since = now;
+        }
+        if self.raw != self.pressed && now.duration_since(self.raw_since) >= self.timing.debounce {
+            self.pressed = self.raw;
+            return Ok(Some(if self.pressed {
+                self.on_press(now)
+            } else {
+                self.on_release(now)
+            }));
+        }
+
+        if self.pressed {
+            let held = now.duration_since(self.pressed_at);
+            if let Some(long_press) = self.timing.long_press {
+                if !self.long_pressed && held >= long_press {
+                    self.long_pressed = true;
+                    self.clicks = 0;
+                    self.click_deadline = None;
+                    return Ok(Some(Event::LongPress(held)));
+                }
+            }
+            if let (Some(deadline), Some((_, interval))) = (self.next_repeat, self.timing.repeat) {
+                if deadline.expired_at(now) {
+                    self.next_repeat = Some(Deadline::after_from(now, interval));
+                    return Ok(Some(Event::Repeat));
+                }
+            }
+        } else if let Some(deadline) = self.click_deadline {
+            if deadline.expired_at(now) {
+                self.click_deadline = None;
+                self.clicks = 0;
+                return Ok(Some(Event::Click));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Give the pin back.
+    pub fn release(self) -> P {
+        self.pin
+    }
+
+    fn on_press(&mut self, now: Instant) -> Event {
+        self.pressed_at = now;
+        self.long_pressed = false;
+        self.next_repeat = self
+            .timing
+            .repeat
+            .map(|(delay, _)| Deadline::after_from(now, delay));
+        Event::Pressed
+    }
+
+    fn on_release(&mut self, now: Instant) -> Event {
+        self.next_repeat = None;
+        if self.long_pressed {
+            return Event::Released;
+        }
+        self.clicks += 1;
+        match self.timing.double_click {
+            _ if self.clicks >= 2 => {
+                self.clicks = 0;
+                self.click_deadline = None;
+                self.pending = Some(Event::DoubleClick);
+            }
+            Some(window) => self.click_deadline = Some(Deadline::after_from(now, window)),
+            None => {
+                self.clicks = 0;
+                self.pending = Some(Event::Click);
+            }
+        }
+        Event::Released
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockPin;
+
+    // Active-low button on a pull-up pin, released
+    fn button() -> (Button<MockPin>, MockPin) {
+        let pin = MockPin::new(true);
+        (Button::new(pin.clone(), Active::Low), pin)
+    }
+
+    // Update every millisecond from `from` to `to`, collecting the events
+    fn run(button: &mut Button<MockPin>, from: u32, to: u32) -> Vec<(u32, Event)> {
+        (from..to)
+            .filter_map(|ms| {
+                let event = button.update(Instant::from_millis(ms)).unwrap();
+                event.map(|event| (ms, event))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn debounces_bouncing_contacts() {
+        let (mut button, pin) = button();
+        // Contacts bounce for a few ms, then settle pressed at 5 ms
+        for (ms, level) in [(0, false), (2, true), (3, false), (4, true), (5, false)] {
+            pin.set(level);
+            assert_eq!(button.update(Instant::from_millis(ms)), Ok(None));
+        }
+        assert_eq!(run(&mut button, 6, 30), [(25, Event::Pressed)]);
+        assert!(button.is_pressed());
+    }
+
+    #[test]
+    fn click_waits_for_double_click_window() {
+        let (mut button, pin) = button();
+        pin.set(false);
+        run(&mut button, 0, 100);
+        pin.set(true);
+        assert_eq!(
+            run(&mut button, 100, 500),
+            [(120, Event::Released), (420, Event::Click)]
+        );
+    }
+
+    #[test]
+    fn double_click() {
+        let (mut button, pin) = button();
+        let mut events = Vec::new();
+        for (from, to, level) in [(0, 80, false), (80, 200, true), (200, 280, false), (280, 400, true)] {
+            pin.set(level);
+            events.extend(run(&mut button, from, to));
+        }
+        let events: Vec<_> = events.into_iter().map(|(_, event)| event).collect();
+        assert_eq!(
+            events,
+            [
+                Event::Pressed,
+                Event::Released,
+                Event::Pressed,
+                Event::Released,
+                Event::DoubleClick
+            ]
+        );
+    }
+
+    #[test]
+    fn long_press_is_not_a_click() {
+        let (mut button, pin) = button();
+        pin.set(false);
+        assert_eq!(
+            run(&mut button, 0, 1000),
+            [
+                (20, Event::Pressed),
+                (820, Event::LongPress(Duration::from_millis(800)))
+            ]
+        );
+        pin.set(true);
+        assert_eq!(run(&mut button, 1000, 2000), [(1020, Event::Released)]);
+    }
+
+    #[test]
+    fn auto_repeat_while_held() {
+        let (mut button, pin) = button();
+        button.set_timing(Timing {
+            double_click: None,
+            long_press: None,
+            repeat: Some((Duration::from_millis(500), Duration::from_millis(100))),
+            ..Timing::default()
+        });
+        pin.set(false);
+        let repeats: Vec<_> = run(&mut button, 0, 800)
+            .into_iter()
+            .filter(|(_, event)| *event == Event::Repeat)
+            .map(|(ms, _)| ms)
+            .collect();
+        assert_eq!(repeats, [520, 620, 720]);
+        assert_eq!(button.held_for(Instant::from_millis(800)), Duration::from_millis(780));
+
+        // Without double-click detection a click follows the release right away
+        pin.set(true);
+        assert_eq!(
+            run(&mut button, 800, 900),
+            [(820, Event::Released), (821, Event::Click)]
+        );
+    }
+
+    #[test]
+    fn active_high() {
+        let pin = MockPin::new(false);
+        let mut button = Button::new(pin.clone(), Active::High);
+        assert_eq!(run(&mut button, 0, 50), []);
+        pin.set(true);
+        assert_eq!(run(&mut button, 50, 100), [(70, Event::Pressed)]);
+    }
+}
@@ -0,0 +1,319 @@
+//! Monotonic Clock
+//!
+//! `millis()`/`micros()` in the style of the Arduino core, driven by the
+//! Timer0 overflow interrupt. Timer0 counts at 16 MHz / 64 = 250 kHz, so it
+//! overflows every 1024 µs; the interrupt adds one millisecond per overflow
+//! and carries the extra 24 µs over, adding a second millisecond whenever
+//! they add up to a full one.
+//!
+//! [`Instant`], [`Duration`] and [`Deadline`] use 32-bit millisecond counters
+//! that wrap after about 49 days; all comparisons are wrap-safe as long as
+//! the intervals involved are shorter than half of that.
+//!
+//! ## Example
+//! ```ignore
+//! clock::init(dp.TC0);
+//! unsafe { avr_device::interrupt::enable() };
+//!
+//! let mut blink = Deadline::after(Duration::from_millis(500));
+//! loop {
+//!     if blink.expired() {
+//!         blink = Deadline::after(Duration::from_millis(500));
+//!         led.toggle();
+//!     }
+//! }
+//! ```
+
+use core::ops::{Add, Sub};
+
+/// Microseconds per Timer0 tick (prescaler 64 at 16 MHz)
+pub const MICROS_PER_TICK: u32 = 4;
+
+/// Microseconds between two Timer0 overflows (256 ticks)
+pub const MICROS_PER_OVERFLOW: u32 = 256 * MICROS_PER_TICK;
+
+// Whole milliseconds and the leftover in units of 8 µs per overflow
+const MILLIS_INC: u32 = MICROS_PER_OVERFLOW / 1000;
+const FRACT_INC: u8 = ((MICROS_PER_OVERFLOW % 1000) >> 3) as u8;
+const FRACT_MAX: u8 = (1000 >> 3) as u8;
+
+/// A span of time in milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration(u32);
+
+impl Duration {
+    pub const ZERO: Duration = Duration(0);
+
+    pub const fn from_millis(ms: u32) -> Self {
+        Duration(ms)
+    }
+
+    pub const fn from_secs(secs: u32) -> Self {
+        Duration(secs * 1000)
+    }
+
+    pub const fn as_millis(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, other: Duration) -> Duration {
+        Duration(self.0.wrapping_add(other.0))
+    }
+}
+
+/// A point in time, as read from `millis()`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instant(u32);
+
+impl Instant {
+    pub const fn from_millis(ms: u32) -> Self {
+        Instant(ms)
+    }
+
+    pub const fn as_millis(&self) -> u32 {
+        self.0
+    }
+
+    /// Time from `earlier` to `self`, zero if `earlier` is actually later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        let diff = self.0.wrapping_sub(earlier.0);
+        if diff as i32 >= 0 {
+            Duration(diff)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    /// `true` if `self` is at or after `other`, across counter wrap-around.
+    pub fn is_at_or_after(&self, other: Instant) -> bool {
+        self.0.wrapping_sub(other.0) as i32 >= 0
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0.wrapping_add(duration.0))
+    }
+}
+
+impl Sub for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A point in time to wait for without blocking
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    pub const fn at(at: Instant) -> Self {
+        Self { at }
+    }
+
+    /// Deadline `duration` after `now`.
+    pub fn after_from(now: Instant, duration: Duration) -> Self {
+        Self::at(now + duration)
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// `true` once `now` has reached the deadline.
+    pub fn expired_at(&self, now: Instant) -> bool {
+        now.is_at_or_after(self.at)
+    }
+
+    /// Time left until the deadline, zero once it has expired.
+    pub fn remaining_at(&self, now: Instant) -> Duration {
+        self.at.duration_since(now)
+    }
+}
+
+/// Advance the millisecond counter by one Timer0 overflow.
+///
+/// Returns the new `(millis, fract)` pair; `fract` accumulates the
+/// sub-millisecond remainder in units of 8 µs.
+pub fn overflow(millis: u32, fract: u8) -> (u32, u8) {
+    let mut millis = millis.wrapping_add(MILLIS_INC);
+    let mut fract = fract + FRACT_INC;
+    if fract >= FRACT_MAX {
+        fract -= FRACT_MAX;
+        millis = millis.wrapping_add(1);
+    }
+    (millis, fract)
+}
+
+/// Microseconds from an overflow count and the current counter value.
+pub fn micros_from(overflows: u32, count: u8) -> u32 {
+    overflows
+        .wrapping_mul(256)
+        .wrapping_add(count as u32)
+        .wrapping_mul(MICROS_PER_TICK)
+}
+
+#[cfg(target_arch = "avr")]
+pub use self::timer::{init, micros, millis};
+
+#[cfg(target_arch = "avr")]
+impl Instant {
+    /// The current time.
+    pub fn now() -> Self {
+        Instant(millis())
+    }
+
+    /// Time passed since `self`.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+#[cfg(target_arch = "avr")]
+impl Deadline {
+    /// Deadline `duration` from now.
+    pub fn after(duration: Duration) -> Self {
+        Self::after_from(Instant::now(), duration)
+    }
+
+    /// `true` once the deadline has been reached.
+    pub fn expired(&self) -> bool {
+        self.expired_at(Instant::now())
+    }
+
+    /// Time left until the deadline, zero once it has expired.
+    pub fn remaining(&self) -> Duration {
+        self.remaining_at(Instant::now())
+    }
+}
+
+#[cfg(target_arch = "avr")]
+mod timer {
+    use core::cell::Cell;
+
+    use arduino_hal::pac::TC0;
+    use avr_device::interrupt::{self, Mutex};
+
+    use super::{micros_from, overflow};
+
+    static MILLIS: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+    static FRACT: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
+    static OVERFLOWS: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+
+    /// Start Timer0 as the system clock.
+    ///
+    /// Global interrupts must be enabled for the clock to run.
+    pub fn init(tc0: TC0) {
+        // Normal mode, prescaler 64, overflow interrupt
+        tc0.tccr0a().write(|w| w.wgm0().set(0b00));
+        tc0.tccr0b().write(|w| w.cs0().prescale_64());
+        tc0.timsk0().write(|w| w.toie0().set_bit());
+    }
+
+    /// Milliseconds since [`init`].
+    pub fn millis() -> u32 {
+        interrupt::free(|cs| MILLIS.borrow(cs).get())
+    }
+
+    /// Microseconds since [`init`], with a resolution of 4 µs.
+    pub fn micros() -> u32 {
+        interrupt::free(|cs| {
+            // SAFETY: read-only access to the counter and flag registers
+            let tc0 = unsafe { &*TC0::ptr() };
+            let mut overflows = OVERFLOWS.borrow(cs).get();
+            let count = tc0.tcnt0().read().bits();
+            // An overflow that happened while interrupts were off isn't counted yet
+            if tc0.tifr0().read().tov0().bit_is_set() && count < 255 {
+                overflows = overflows.wrapping_add(1);
+            }
+            micros_from(overflows, count)
+        })
+    }
+
+    #[avr_device::interrupt(atmega328p)]
+    fn TIMER0_OVF() {
+        interrupt::free(|cs| {
+            let (millis, fract) = overflow(MILLIS.borrow(cs).get(), FRACT.borrow(cs).get());
+            MILLIS.borrow(cs).set(millis);
+            FRACT.borrow(cs).set(fract);
+            let overflows = OVERFLOWS.borrow(cs);
+            overflows.set(overflows.get().wrapping_add(1));
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflow_carries_fraction() {
+        let mut state = (0, 0);
+        for _ in 0..125 {
+            state = overflow(state.0, state.1);
+        }
+        // 125 overflows of 1024 µs = 128 ms exactly
+        assert_eq!(state, (128, 0));
+    }
+
+    #[test]
+    fn millis_tracks_real_time() {
+        let mut state = (0, 0);
+        for n in 1..=10_000u32 {
+            state = overflow(state.0, state.1);
+            let real_ms = n * MICROS_PER_OVERFLOW / 1000;
+            assert!(state.0 == real_ms || state.0 + 1 == real_ms, "{n}");
+        }
+    }
+
+    #[test]
+    fn micros_resolution() {
+        assert_eq!(micros_from(0, 1), 4);
+        assert_eq!(micros_from(2, 10), 2 * 1024 + 40);
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(Duration::from_secs(3).as_millis(), 3000);
+        assert_eq!(
+            Duration::from_millis(250) + Duration::from_millis(750),
+            Duration::from_secs(1)
+        );
+        let start = Instant::from_millis(1000);
+        assert_eq!(Instant::from_millis(1500) - start, Duration::from_millis(500));
+        assert_eq!(Instant::from_millis(900) - start, Duration::ZERO);
+    }
+
+    #[test]
+    fn deadline_expires() {
+        let deadline = Deadline::after_from(Instant::from_millis(100), Duration::from_millis(50));
+        assert!(!deadline.expired_at(Instant::from_millis(149)));
+        assert_eq!(
+            deadline.remaining_at(Instant::from_millis(120)),
+            Duration::from_millis(30)
+        );
+        assert!(deadline.expired_at(Instant::from_millis(150)));
+        assert!(deadline.expired_at(Instant::from_millis(10_000)));
+        assert_eq!(deadline.remaining_at(Instant::from_millis(200)), Duration::ZERO);
+    }
+
+    #[test]
+    fn deadline_survives_wraparound() {
+        let now = Instant::from_millis(u32::MAX - 10);
+        let deadline = Deadline::after_from(now, Duration::from_millis(20));
+        assert_eq!(deadline.instant(), Instant::from_millis(9));
+        assert!(!deadline.expired_at(Instant::from_millis(u32::MAX)));
+        assert!(!deadline.expired_at(Instant::from_millis(5)));
+        assert!(deadline.expired_at(Instant::from_millis(9)));
+    }
+}
@@ -0,0 +1,297 @@
+//! Joystick Cursor
+//!
+//! Moves a cursor with the speed of the stick instead of one pixel per step:
+//! a little push creeps along pixel by pixel, pushing all the way moves at
+//! [`Cursor::set_max_speed`]. The position is kept in fixed point with
+//! [`FRACTION_BITS`] bits below the pixel, and what is left below that is
+//! carried over to the next update, so slow movement adds up even when the
+//! cursor is updated every millisecond.
+//!
+//! A [`Curve`] shapes the response: with [`Curve::Quadratic`] (the default)
+//! half a push gives a quarter of the speed, which leaves more of the stick's
+//! travel for fine movement. At the edge the cursor stops or comes back on
+//! the other side, see [`Edge`].
+//!
+//! ## Example
+//! ```ignore
+//! let mut cursor = Cursor::new(8, 8);
+//! loop {
+//!     if cursor.update(joystick.position(&mut adc), Instant::now()) {
+//!         let (x, y) = cursor.pixel();
+//!     }
+//! }
+//! ```
+
+use crate::clock::Instant;
+use crate::joystick::NORMALIZED_MAX;
+
+/// Bits of the position below one pixel
+pub const FRACTION_BITS: u32 = 8;
+
+/// Speed at full deflection unless set otherwise, in pixels per second
+pub const DEFAULT_MAX_SPEED: u8 = 16;
+
+// Longer gaps between updates count as this long, so a stalled loop doesn't
+// make the cursor jump
+const MAX_STEP_MS: u32 = 50;
+
+const ONE: i32 = 1 << FRACTION_BITS;
+
+// speed / NORMALIZED_MAX pixels per second, times ONE / 1000 ms, is
+// speed * (ONE / 8) / DIVISOR 1/256 pixels per ms (both divided by 8 to stay
+// within an i32 at full speed)
+const DIVISOR: i32 = NORMALIZED_MAX as i32 * 1000 / 8;
+
+/// How the speed follows the deflection of the stick
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Curve {
+    /// Speed proportional to the deflection
+    Linear,
+    /// Deflection squared: fine control near the center
+    #[default]
+    Quadratic,
+    /// Deflection cubed: even finer near the center, fast at the edge
+    Cubic,
+}
+
+impl Curve {
+    /// Shape a normalized deflection, keeping its sign and range.
+    pub fn apply(self, value: i16) -> i16 {
+        let value = value.clamp(-NORMALIZED_MAX, NORMALIZED_MAX) as i32;
+        let max = NORMALIZED_MAX as i32;
+        let shaped = match self {
+            Curve::Linear => value,
+            Curve::Quadratic => value * value.abs() / max,
+            Curve::Cubic => value * value / max * value / max,
+        };
+        shaped as i16
+    }
+}
+
+/// What happens at the edge of the area
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Edge {
+    /// Stop at the edge
+    #[default]
+    Clamp,
+    /// Leave on one side, come back on the other
+    Wrap,
+}
+
+/// A cursor on a `width` x `height` pixel area
+pub struct Cursor {
+    // Position in 1/256 pixels
+    x: i32,
+    y: i32,
+    // Movement below 1/256 pixel not applied yet, in 1/DIVISOR of that
+    rest: (i32, i32),
+    width: u8,
+    height: u8,
+    max_speed: u8,
+    curve: Curve,
+    edge: Edge,
+    last: Option<Instant>,
+}
+
+impl Cursor {
+    /// A cursor in the middle of the area.
+    pub fn new(width: u8, height: u8) -> Self {
+        let mut cursor = Self {
+            x: 0,
+            y: 0,
+            rest: (0, 0),
+            width: width.max(1),
+            height: height.max(1),
+            max_speed: DEFAULT_MAX_SPEED,
+            curve: Curve::default(),
+            edge: Edge::default(),
+            last: None,
+        };
+        cursor.set_pixel(width / 2, height / 2);
+        cursor
+    }
+
+    /// Speed at full deflection, in pixels per second.
+    pub fn set_max_speed(&mut self, pixels_per_second: u8) {
+        self.max_speed = pixels_per_second;
+    }
+
+    pub fn set_curve(&mut self, curve: Curve) {
+        self.curve = curve;
+    }
+
+    pub fn edge(&self) -> Edge {
+        self.edge
+    }
+
+    pub fn set_edge(&mut self, edge: Edge) {
+        self.edge = edge;
+    }
+
+    /// The pixel the cursor is on.
+    pub fn pixel(&self) -> (u8, u8) {
+        (
+            (self.x >> FRACTION_BITS) as u8,
+            (self.y >> FRACTION_BITS) as u8,
+        )
+    }
+
+    /// The exact position, in 1/256 pixels.
+    pub fn position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// Move to the middle of a pixel.
+    pub fn set_pixel(&mut self, x: u8, y: u8) {
+        self.x = self.fit(x as i32 * ONE + ONE / 2, self.width);
+        self.y = self.fit(y as i32 * ONE + ONE / 2, self.height);
+        self.rest = (0, 0);
+    }
+
+    /// Move by the stick `position` (normalized, in screen coordinates) for
+    /// the time since the last update. Returns `true` if the cursor moved to
+    /// another pixel.
+    pub fn update(&mut self, position: (i16, i16), now: Instant) -> bool {
+        let Some(last) = self.last.replace(now) else {
+            // The first call only starts the timing
+            return false;
+        };
+        let ms = now.duration_since(last).as_millis().min(MAX_STEP_MS) as i32;
+        let before = self.pixel();
+        let (dx, rest_x) = self.step(position.0, ms, self.rest.0);
+        let (dy, rest_y) = self.step(position.1, ms, self.rest.1);
+        self.rest = (rest_x, rest_y);
+        self.x = self.fit(self.x + dx, self.width);
+        self.y = self.fit(self.y + dy, self.height);
+        self.pixel() != before
+    }
+
+    // Distance in 1/256 pixels covered in `ms` at deflection `value`, plus
+    // the `rest` of earlier updates, and the new rest
+    fn step(&self, value: i16, ms: i32, rest: i32) -> (i32, i32) {
+        let speed = self.curve.apply(value) as i32 * self.max_speed as i32;
+        let distance = speed * ms * (ONE / 8) + rest;
+        (distance / DIVISOR, distance % DIVISOR)
+    }
+
+    fn fit(&self, value: i32, size: u8) -> i32 {
+        let end = size as i32 * ONE;
+        match self.edge {
+            Edge::Clamp => value.clamp(0, end - 1),
+            Edge::Wrap => value.rem_euclid(end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Update every 10 ms for `ms` with the stick held at `position`
+    fn hold(cursor: &mut Cursor, position: (i16, i16), from: u32, ms: u32) {
+        for t in (from..=from + ms).step_by(10) {
+            cursor.update(position, Instant::from_millis(t));
+        }
+    }
+
+    #[test]
+    fn speed_follows_the_deflection() {
+        let mut cursor = Cursor::new(64, 8);
+        cursor.set_curve(Curve::Linear);
+        cursor.set_pixel(0, 0);
+        // Full right for one second: DEFAULT_MAX_SPEED pixels
+        hold(&mut cursor, (NORMALIZED_MAX, 0), 0, 1000);
+        assert_eq!(cursor.pixel(), (16, 0));
+
+        // Half way for one second: half as far
+        cursor.set_pixel(0, 0);
+        hold(&mut cursor, (NORMALIZED_MAX / 2, 0), 2000, 1000);
+        assert_eq!(cursor.pixel(), (8, 0));
+
+        // Fastest setting: 255 pixels per second, 25.5 pixels on from the
+        // middle of pixel 0
+        let mut cursor = Cursor::new(64, 8);
+        cursor.set_max_speed(u8::MAX);
+        cursor.set_pixel(0, 0);
+        hold(&mut cursor, (NORMALIZED_MAX, 0), 0, 100);
+        assert_eq!(cursor.position().0, 26 * ONE);
+        assert_eq!(cursor.pixel(), (26, 0));
+    }
+
+    #[test]
+    fn slow_movement_adds_up() {
+        let mut cursor = Cursor::new(8, 8);
+        cursor.set_curve(Curve::Linear);
+        cursor.set_pixel(0, 0);
+        // 20/256 pixel per 50 ms update: a whole pixel every 13 updates
+        cursor.update((100, 0), Instant::from_millis(0));
+        let moved = (1..=40)
+            .filter(|n| cursor.update((100, 0), Instant::from_millis(n * 50)))
+            .count();
+        assert_eq!(moved, 3);
+        assert_eq!(cursor.pixel(), (3, 0));
+    }
+
+    #[test]
+    fn fast_updates_keep_the_fraction() {
+        // The default quadratic curve and an update every millisecond, as
+        // in a busy main loop: each one moves less than 1/256 pixel
+        let mut cursor = Cursor::new(64, 8);
+        cursor.set_max_speed(12);
+        cursor.set_pixel(0, 0);
+        for t in 0..=2000 {
+            cursor.update(
+                (NORMALIZED_MAX / 2, -NORMALIZED_MAX / 2),
+                Instant::from_millis(t),
+            );
+        }
+        // A quarter of 12 pixels per second for two seconds
+        assert_eq!(cursor.position().0, ONE / 2 + 6 * ONE);
+        assert_eq!(cursor.pixel(), (6, 0));
+
+        let mut cursor = Cursor::new(64, 8);
+        cursor.set_max_speed(12);
+        cursor.set_pixel(0, 0);
+        for t in 0..=2000 {
+            cursor.update((700, 0), Instant::from_millis(t));
+        }
+        // 0.49 * 12 pixels per second for two seconds: 11.76 pixels
+        assert_eq!(cursor.pixel(), (12, 0));
+    }
+
+    #[test]
+    fn curves() {
+        assert_eq!(Curve::Linear.apply(500), 500);
+        assert_eq!(Curve::Quadratic.apply(500), 250);
+        assert_eq!(Curve::Quadratic.apply(-500), -250);
+        assert_eq!(Curve::Cubic.apply(-500), -125);
+        assert_eq!(Curve::Cubic.apply(NORMALIZED_MAX), NORMALIZED_MAX);
+    }
+
+    #[test]
+    fn edges() {
+        let mut cursor = Cursor::new(8, 8);
+        assert_eq!(cursor.pixel(), (4, 4));
+        hold(&mut cursor, (-NORMALIZED_MAX, NORMALIZED_MAX), 0, 2000);
+        assert_eq!(cursor.pixel(), (0, 7));
+
+        let mut cursor = Cursor::new(8, 8);
+        cursor.set_edge(Edge::Wrap);
+        cursor.set_pixel(7, 0);
+        // Half a second to the right: 8 pixels, around once and back to 7
+        hold(&mut cursor, (NORMALIZED_MAX, 0), 0, 500);
+        assert_eq!(cursor.pixel(), (7, 0));
+        hold(&mut cursor, (NORMALIZED_MAX, 0), 510, 60);
+        assert_eq!(cursor.pixel(), (0, 0));
+    }
+
+    #[test]
+    fn long_gaps_dont_jump() {
+        let mut cursor = Cursor::new(64, 8);
+        cursor.set_pixel(0, 0);
+        cursor.update((NORMALIZED_MAX, 0), Instant::from_millis(0));
+        cursor.update((NORMALIZED_MAX, 0), Instant::from_millis(5000));
+        // Counted as 50 ms: 0.8 pixels
+        assert_eq!(cursor.position().0, ONE / 2 + 204);
+    }
+}
@@ -0,0 +1,300 @@
+//! Four Brightness Levels on the Matrix
+//!
+//! The HT16K33 only switches each LED on or off and has one brightness for
+//! the whole display. [`GrayMatrix8x8`] keeps two bit planes per pixel
+//! instead, as an `embedded-graphics` [`DrawTarget`] of [`Gray2`], and
+//! [`FrameModulator`] shows them as quickly alternating subframes: the high
+//! plane in two of every three, the low plane in the third. An LED is then
+//! lit 0, 1, 2 or 3 thirds of the time, which the eye sees as four levels.
+//!
+//! The subframes are polled from the main loop and timed by the millisecond
+//! clock like everything else, not sent from a timer interrupt (that would
+//! need the I2C bus inside the interrupt). [`FrameModulator::update`] has
+//! to be called at least every [`SUBFRAME`]; a late call shows as flicker,
+//! and blocking work such as an EEPROM write or serial output freezes
+//! whichever subframe was sent last. Call [`FrameModulator::hold`] before
+//! such work to show a steady two-level picture in the meantime.
+//!
+//! Only pixels between off and full change from one subframe to the next,
+//! and the driver only sends changed rows, so the I2C traffic stays small.
+//!
+//! ## Example
+//! ```ignore
+//! let mut frame = GrayMatrix8x8::new();
+//! frame.set_pixel(3, 3, Gray2::new(1));
+//! let mut modulator = FrameModulator::new();
+//! loop {
+//!     modulator.update(&frame, &mut ht16k33, Instant::now())?;
+//! }
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_graphics::pixelcolor::{Gray2, GrayColor};
+use embedded_graphics::prelude::*;
+use embedded_hal::i2c::I2c;
+
+use crate::clock::{Duration, Instant};
+use crate::ht16k33::{self, Ht16k33, ROWS};
+use crate::matrix::{Matrix8x8, SIZE};
+
+/// Time each subframe is shown; three make up a frame (about 80 Hz)
+pub const SUBFRAME: Duration = Duration::from_millis(4);
+
+/// Number of subframes per frame
+pub const SUBFRAMES: u8 = 3;
+
+/// Highest level of a pixel (always on)
+pub const MAX_LEVEL: u8 = 3;
+
+/// An 8x8 frame with four levels per pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GrayMatrix8x8 {
+    // Bit 0 and bit 1 of every pixel, laid out like `Matrix8x8` rows
+    planes: [[u8; ROWS]; 2],
+}
+
+impl GrayMatrix8x8 {
+    /// An empty (all off) frame.
+    pub const fn new() -> Self {
+        Self {
+            planes: [[0; ROWS]; 2],
+        }
+    }
+
+    /// The level at `(x, y)`; off outside the matrix.
+    pub fn pixel(&self, x: i32, y: i32) -> Gray2 {
+        if !Self::contains(x, y) {
+            return Gray2::BLACK;
+        }
+        let bit = |plane: &[u8; ROWS]| (plane[y as usize] >> x) & 1;
+        Gray2::new(bit(&self.planes[0]) | bit(&self.planes[1]) << 1)
+    }
+
+    /// Set a pixel; coordinates outside the matrix are ignored.
+    pub fn set_pixel(&mut self, x: i32, y: i32, level: Gray2) {
+        if !Self::contains(x, y) {
+            return;
+        }
+        for (n, plane) in self.planes.iter_mut().enumerate() {
+            if level.luma() & 1 << n != 0 {
+                plane[y as usize] |= 1 << x;
+            } else {
+                plane[y as usize] &= !(1 << x);
+            }
+        }
+    }
+
+    /// Dim every lit pixel by one level, e.g. to let a trail fade out.
+    pub fn fade(&mut self) {
+        let [low, high] = &mut self.planes;
+        for (low, high) in low.iter_mut().zip(high.iter_mut()) {
+            // Subtract one from each 2-bit pixel: 3 -> 2 -> 1 -> 0
+            let (l, h) = (*low, *high);
+            *low = !l & h;
+            *high = h & l;
+        }
+    }
+
+    /// The LEDs that are on in subframe `n` (0 to [`SUBFRAMES`] - 1).
+    pub fn subframe(&self, n: u8) -> Matrix8x8 {
+        let plane = if n % SUBFRAMES == 1 { 0 } else { 1 };
+        Matrix8x8::from_rows(self.planes[plane])
+    }
+
+    fn contains(x: i32, y: i32) -> bool {
+        (0..SIZE as i32).contains(&x) && (0..SIZE as i32).contains(&y)
+    }
+}
+
+impl From<Matrix8x8> for GrayMatrix8x8 {
+    /// Lit pixels at full level.
+    fn from(frame: Matrix8x8) -> Self {
+        Self {
+            planes: [*frame.rows(); 2],
+        }
+    }
+}
+
+impl OriginDimensions for GrayMatrix8x8 {
+    fn size(&self) -> Size {
+        Size::new(SIZE, SIZE)
+    }
+}
+
+impl DrawTarget for GrayMatrix8x8 {
+    type Color = Gray2;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, level) in pixels {
+            self.set_pixel(point.x, point.y, level);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, level: Self::Color) -> Result<(), Self::Error> {
+        for (n, plane) in self.planes.iter_mut().enumerate() {
+            *plane = [if level.luma() & 1 << n != 0 { 0xFF } else { 0 }; ROWS];
+        }
+        Ok(())
+    }
+}
+
+/// Shows a [`GrayMatrix8x8`] as alternating subframes
+#[derive(Debug, Default)]
+pub struct FrameModulator {
+    subframe: u8,
+    last: Option<Instant>,
+}
+
+impl FrameModulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send the next subframe of `frame` when one is due. Returns `true` if
+    /// a subframe was sent.
+    pub fn update<I2C, E>(
+        &mut self,
+        frame: &GrayMatrix8x8,
+        display: &mut Ht16k33<I2C>,
+        now: Instant,
+    ) -> Result<bool, ht16k33::Error<E>>
+    where
+        I2C: I2c<Error = E>,
+    {
+        if let Some(last) = self.last {
+            if now.duration_since(last) < SUBFRAME {
+                return Ok(false);
+            }
+            self.subframe = (self.subframe + 1) % SUBFRAMES;
+        }
+        self.last = Some(now);
+        frame.subframe(self.subframe).flush(display)?;
+        Ok(true)
+    }
+
+    /// Show `frame` without modulation, levels 2 and 3 on and 0 and 1 off,
+    /// until the next [`update`](Self::update), which starts a new frame.
+    /// Call it before work that blocks for longer than a subframe.
+    pub fn hold<I2C, E>(
+        &mut self,
+        frame: &GrayMatrix8x8,
+        display: &mut Ht16k33<I2C>,
+    ) -> Result<(), ht16k33::Error<E>>
+    where
+        I2C: I2c<Error = E>,
+    {
+        self.subframe = 0;
+        self.last = None;
+        frame.subframe(0).flush(display)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ht16k33::DEFAULT_ADDRESS;
+    use crate::mock::MockI2c;
+    use embedded_graphics::primitives::{Line, PrimitiveStyle};
+
+    #[test]
+    fn levels_and_subframes() {
+        let mut frame = GrayMatrix8x8::new();
+        for level in 0..=MAX_LEVEL {
+            frame.set_pixel(level as i32, 0, Gray2::new(level));
+        }
+        assert_eq!(frame.pixel(2, 0), Gray2::new(2));
+        assert_eq!(frame.pixel(9, 0), Gray2::BLACK);
+
+        // Each pixel is lit in as many subframes as its level
+        for x in 0..4 {
+            let lit = (0..SUBFRAMES)
+                .filter(|&n| frame.subframe(n).pixel(x, 0))
+                .count();
+            assert_eq!(lit, x as usize);
+        }
+    }
+
+    #[test]
+    fn fading_steps_down_to_off() {
+        let mut frame = GrayMatrix8x8::from(Matrix8x8::from_rows([0b1; ROWS]));
+        frame.set_pixel(1, 0, Gray2::new(1));
+        let levels = |frame: &GrayMatrix8x8| (frame.pixel(0, 0).luma(), frame.pixel(1, 0).luma());
+        assert_eq!(levels(&frame), (3, 1));
+        frame.fade();
+        assert_eq!(levels(&frame), (2, 0));
+        frame.fade();
+        frame.fade();
+        assert_eq!(levels(&frame), (0, 0));
+        frame.fade();
+        assert_eq!(frame, GrayMatrix8x8::new());
+    }
+
+    #[test]
+    fn draws_gray_primitives() {
+        let mut frame = GrayMatrix8x8::new();
+        Line::new(Point::new(0, 7), Point::new(7, 7))
+            .into_styled(PrimitiveStyle::with_stroke(Gray2::new(2), 1))
+            .draw(&mut frame)
+            .unwrap();
+        assert_eq!(frame.pixel(5, 7), Gray2::new(2));
+        frame.clear(Gray2::WHITE).unwrap();
+        assert_eq!(frame.pixel(5, 7), Gray2::WHITE);
+    }
+
+    #[test]
+    fn modulator_cycles_subframes() {
+        let i2c = MockI2c::new();
+        let mut display = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        let mut frame = GrayMatrix8x8::new();
+        frame.set_pixel(0, 0, Gray2::new(1));
+        let mut modulator = FrameModulator::new();
+
+        let mut shown = Vec::new();
+        for ms in 0..12 {
+            if modulator
+                .update(&frame, &mut display, Instant::from_millis(ms))
+                .unwrap()
+            {
+                shown.push(display.buffer()[0]);
+            }
+        }
+        // Only the low-plane subframe lights the pixel
+        assert_eq!(shown, [0, 1, 0]);
+    }
+
+    #[test]
+    fn hold_shows_the_high_plane() {
+        let i2c = MockI2c::new();
+        let mut display = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        let mut frame = GrayMatrix8x8::new();
+        frame.set_pixel(0, 0, Gray2::new(1));
+        frame.set_pixel(1, 0, Gray2::new(2));
+        let mut modulator = FrameModulator::new();
+
+        modulator
+            .update(&frame, &mut display, Instant::from_millis(0))
+            .unwrap();
+        modulator
+            .update(&frame, &mut display, Instant::from_millis(4))
+            .unwrap();
+        assert_eq!(display.buffer()[0], 0b01);
+
+        modulator.hold(&frame, &mut display).unwrap();
+        assert_eq!(display.buffer()[0], 0b10);
+        // After the blocking work, the next update starts over right away
+        assert!(modulator
+            .update(&frame, &mut display, Instant::from_millis(100))
+            .unwrap());
+        assert_eq!(display.buffer()[0], 0b10);
+        assert!(modulator
+            .update(&frame, &mut display, Instant::from_millis(104))
+            .unwrap());
+        assert_eq!(display.buffer()[0], 0b01);
+    }
+}
@@ -0,0 +1,754 @@
+//! HT16K33 LED Matrix Driver
+//!
+//! Driver for the HT16K33 I2C LED controller used on the 8x8 LED matrix
+//! module. It keeps a copy of the display RAM in memory; drawing calls only
+//! touch that buffer and [`Ht16k33::write_display`] sends it to the chip.
+//!
+//! The driver also remembers what it last sent, so [`Ht16k33::write_display`]
+//! only transmits the bytes that changed (the chip's RAM address
+//! auto-increments, so a run of bytes is one write) and nothing at all for
+//! an unchanged frame. Redrawing every few milliseconds is cheap that way;
+//! [`Ht16k33::stats`] shows how much was actually sent.
+//!
+//! ## Display RAM Layout
+//! The chip has 8 rows (COM0-COM7) of 16 bits each. An 8x8 matrix only uses
+//! the low byte of every row, so columns 8-15 are only relevant for 16x8 and
+//! bicolor backpacks.
+//!
+//! ## Key Scan
+//! The chip also scans a matrix of up to 13x3 keys (K1-K13 against
+//! KS0-KS2) and debounces them itself. [`Ht16k33::read_keys`] reads which
+//! keys are down; [`crate::keypad::Keypad`] turns that into key events.
+//! The INT pin can signal changes, see [`Ht16k33::set_interrupt_pin`].
+//!
+//! ## Orientation
+//! Modules are mounted every which way, and not every backpack wires bit `n`
+//! of a row to column `n`. An [`Orientation`] maps the upright image onto the
+//! display RAM: the image is flipped first, then rotated clockwise, and its
+//! columns are finally looked up in a column table. [`Ht16k33::set_rows`],
+//! [`Ht16k33::set_pixel`] and [`Ht16k33::pixel`] go through it, so the same
+//! bitmaps come out upright on any module.
+//!
+//! ```ignore
+//! // Mounted upside down, on a backpack with the columns offset by one
+//! let orientation = Orientation::new()
+//!     .with_rotation(Rotation::Half)
+//!     .with_columns(OFFSET_COLUMNS);
+//! let mut matrix = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS).with_orientation(orientation);
+//! ```
+//!
+//! ## Example
+//! ```ignore
+//! let mut matrix = Ht16k33::new(i2c, ht16k33::DEFAULT_ADDRESS);
+//! matrix.init()?;
+//! matrix.set_pixel(3, 4, true)?;
+//! matrix.write_display()?;
+//! ```
+
+use embedded_hal::i2c::I2c;
+
+/// Default I2C address (0x70-0x77 can be selected with solder jumpers on some modules)
+pub const DEFAULT_ADDRESS: u8 = 0x70;
+
+/// Number of rows (COM lines) in display RAM
+pub const ROWS: usize = 8;
+
+/// Number of columns (ROW lines) in display RAM
+pub const COLUMNS: usize = 16;
+
+/// Highest value accepted by [`Ht16k33::set_brightness`]
+pub const MAX_BRIGHTNESS: u8 = 15;
+
+// HT16K33 command definitions
+const CMD_DISPLAY_RAM: u8 = 0x00; // Display RAM starts at address 0x00
+const CMD_SYSTEM_SETUP: u8 = 0x20; // Bit 0 turns the oscillator on
+const CMD_DISPLAY_SETUP: u8 = 0x80; // Bit 0 = display on, bits 1-2 = blink rate
+const CMD_BRIGHTNESS: u8 = 0xE0; // Brightness command (0xE0-0xEF)
+const CMD_ROW_INT: u8 = 0xA0; // Bit 0 = INT output instead of ROW15, bit 1 = active high
+const CMD_KEY_RAM: u8 = 0x40; // Key data RAM, 2 bytes per KS line
+const CMD_INT_FLAG: u8 = 0x60; // Nonzero when key data changed, cleared by reading the keys
+
+/// Blink rate of the whole display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Blink {
+    Off,
+    /// 2 Hz
+    Fast,
+    /// 1 Hz
+    Medium,
+    /// 0.5 Hz
+    Slow,
+}
+
+impl Blink {
+    fn bits(self) -> u8 {
+        match self {
+            Blink::Off => 0b000,
+            Blink::Fast => 0b010,
+            Blink::Medium => 0b100,
+            Blink::Slow => 0b110,
+        }
+    }
+}
+
+/// Clockwise rotation of the image on the module
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    /// 90° clockwise
+    Quarter,
+    /// 180°
+    Half,
+    /// 270° clockwise (90° counterclockwise)
+    ThreeQuarters,
+}
+
+/// Column table for modules wired the straight way: bit `n` is column `n`
+pub const STRAIGHT_COLUMNS: [u8; ROWS] = [0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Column table for backpacks with the columns offset by one, like the
+/// Adafruit mini 8x8, where column 0 is wired to the last bit
+pub const OFFSET_COLUMNS: [u8; ROWS] = [7, 0, 1, 2, 3, 4, 5, 6];
+
+/// Column table for the red LEDs of a bicolor backpack (the green ones
+/// use [`STRAIGHT_COLUMNS`])
+pub const RED_COLUMNS: [u8; ROWS] = [8, 9, 10, 11, 12, 13, 14, 15];
+
+/// How the 8x8 image is mapped onto the display RAM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Orientation {
+    rotation: Rotation,
+    flip_horizontal: bool,
+    flip_vertical: bool,
+    columns: [u8; ROWS],
+}
+
+impl Orientation {
+    /// Upright, not flipped, with [`STRAIGHT_COLUMNS`].
+    pub const fn new() -> Self {
+        Self {
+            rotation: Rotation::None,
+            flip_horizontal: false,
+            flip_vertical: false,
+            columns: STRAIGHT_COLUMNS,
+        }
+    }
+
+    pub const fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Mirror the image left to right and/or top to bottom (before rotating).
+    pub const fn with_flip(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.flip_horizontal = horizontal;
+        self.flip_vertical = vertical;
+        self
+    }
+
+    /// The display RAM column (0-15) each column of the rotated image is
+    /// wired to.
+    pub const fn with_columns(mut self, columns: [u8; ROWS]) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// The display RAM `(column, row)` showing pixel `(x, y)` of the image;
+    /// both must be below 8.
+    pub fn map(&self, x: u8, y: u8) -> (u8, u8) {
+        let last = ROWS as u8 - 1;
+        let x = if self.flip_horizontal { last - x } else { x };
+        let y = if self.flip_vertical { last - y } else { y };
+        let (x, y) = match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Quarter => (last - y, x),
+            Rotation::Half => (last - x, last - y),
+            Rotation::ThreeQuarters => (y, last - x),
+        };
+        (self.columns[x as usize] % COLUMNS as u8, y)
+    }
+
+    fn is_straight(&self) -> bool {
+        *self == Self::new()
+    }
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of key scan lines (KS0-KS2)
+pub const KEY_ROWS: usize = 3;
+
+/// Number of key inputs per scan line (K1-K13)
+pub const KEY_COLUMNS: usize = 13;
+
+/// One key of the scan matrix
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    /// Scan line, 0-2 for KS0-KS2
+    pub row: u8,
+    /// Key input, 0-12 for K1-K13
+    pub column: u8,
+}
+
+impl Key {
+    pub const fn new(row: u8, column: u8) -> Self {
+        Self { row, column }
+    }
+}
+
+/// Which keys of the scan matrix are down, bit `n` of row `r` being
+/// [`Key::new(r, n)`](Key::new)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Keys(pub [u16; KEY_ROWS]);
+
+impl Keys {
+    pub fn is_pressed(&self, key: Key) -> bool {
+        (key.row as usize) < KEY_ROWS
+            && (key.column as usize) < KEY_COLUMNS
+            && self.0[key.row as usize] & 1 << key.column != 0
+    }
+
+    /// `true` while any key is down.
+    pub fn any(&self) -> bool {
+        self.0.iter().any(|&row| row != 0)
+    }
+
+    /// The keys down in one but not the other.
+    pub fn changes(&self, other: &Keys) -> Keys {
+        let mut changes = *self;
+        for (row, other) in changes.0.iter_mut().zip(other.0) {
+            *row ^= other;
+        }
+        changes
+    }
+
+    /// The first key down, scan line by scan line.
+    pub fn first(&self) -> Option<Key> {
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(_, &row)| row != 0)
+            .map(|(row, bits)| Key::new(row as u8, bits.trailing_zeros() as u8))
+    }
+}
+
+/// What the shared ROW15/INT pin does
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InterruptPin {
+    /// Drives LED row 15 (the power-on default)
+    #[default]
+    Row15,
+    /// Goes low when the key data changed
+    ActiveLow,
+    /// Goes high when the key data changed
+    ActiveHigh,
+}
+
+// A new write costs the address and the RAM address byte, so changed bytes
+// closer together than this are sent in one run
+const RUN_GAP: usize = 2;
+
+/// What [`Ht16k33::write_display`] has sent so far
+///
+/// The counters wrap around, so a board flushing every few milliseconds
+/// keeps running after weeks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlushStats {
+    /// Calls to [`Ht16k33::write_display`]
+    pub flushes: u32,
+    /// Flushes with nothing to send
+    pub skipped: u32,
+    /// I2C writes of display RAM
+    pub writes: u32,
+    /// Bytes in those writes, including the RAM address
+    pub bytes: u32,
+}
+
+/// Errors returned by the driver
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error<E> {
+    /// The I2C bus reported an error
+    I2c(E),
+    /// Brightness level above [`MAX_BRIGHTNESS`]
+    InvalidBrightness(u8),
+    /// Pixel coordinates outside of the display RAM
+    OutOfBounds,
+}
+
+/// HT16K33 driver with an in-memory copy of the display RAM
+pub struct Ht16k33<I2C> {
+    i2c: I2C,
+    address: u8,
+    buffer: [u8; ROWS * 2],
+    // What the chip shows, `None` until known (before the first write or
+    // after a failed one)
+    shown: Option<[u8; ROWS * 2]>,
+    stats: FlushStats,
+    display_on: bool,
+    blink: Blink,
+    orientation: Orientation,
+}
+
+impl<I2C, E> Ht16k33<I2C>
+where
+    I2C: I2c<Error = E>,
+{
+    /// Create a driver for the chip at `address`. Nothing is sent until [`init`](Self::init).
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        Self {
+            i2c,
+            address,
+            buffer: [0; ROWS * 2],
+            shown: None,
+            stats: FlushStats::default(),
+            display_on: false,
+            blink: Blink::Off,
+            orientation: Orientation::new(),
+        }
+    }
+
+    /// Map all drawing through `orientation` (see the module docs).
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Change the orientation; takes effect for what is drawn afterwards.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Start the oscillator, clear the display and turn it on at full brightness.
+    pub fn init(&mut self) -> Result<(), Error<E>> {
+        self.set_oscillator(true)?;
+        self.clear();
+        self.write_display()?;
+        self.set_brightness(MAX_BRIGHTNESS)?;
+        self.blink = Blink::Off;
+        self.set_display(true)
+    }
+
+    /// Turn the internal oscillator on or off (off = standby mode).
+    pub fn set_oscillator(&mut self, on: bool) -> Result<(), Error<E>> {
+        self.command(CMD_SYSTEM_SETUP | on as u8)
+    }
+
+    /// Turn the display on or off without touching display RAM.
+    pub fn set_display(&mut self, on: bool) -> Result<(), Error<E>> {
+        self.display_on = on;
+        self.write_display_setup()
+    }
+
+    /// Set the blink rate of the whole display.
+    pub fn set_blink(&mut self, blink: Blink) -> Result<(), Error<E>> {
+        self.blink = blink;
+        self.write_display_setup()
+    }
+
+    /// Set the global brightness, from 0 (dimmest) to [`MAX_BRIGHTNESS`].
+    pub fn set_brightness(&mut self, level: u8) -> Result<(), Error<E>> {
+        if level > MAX_BRIGHTNESS {
+            return Err(Error::InvalidBrightness(level));
+        }
+        self.command(CMD_BRIGHTNESS | level)
+    }
+
+    /// Use the ROW15/INT pin as key interrupt output, or for LEDs again.
+    pub fn set_interrupt_pin(&mut self, pin: InterruptPin) -> Result<(), Error<E>> {
+        let bits = match pin {
+            InterruptPin::Row15 => 0b00,
+            InterruptPin::ActiveLow => 0b01,
+            InterruptPin::ActiveHigh => 0b11,
+        };
+        self.command(CMD_ROW_INT | bits)
+    }
+
+    /// Read which keys are down, as debounced by the chip.
+    pub fn read_keys(&mut self) -> Result<Keys, Error<E>> {
+        let mut bytes = [0u8; KEY_ROWS * 2];
+        self.i2c
+            .write_read(self.address, &[CMD_KEY_RAM], &mut bytes)
+            .map_err(Error::I2c)?;
+        let mut keys = Keys::default();
+        for (row, pair) in keys.0.iter_mut().zip(bytes.chunks_exact(2)) {
+            *row = u16::from_le_bytes([pair[0], pair[1]]) & ((1 << KEY_COLUMNS) - 1);
+        }
+        Ok(keys)
+    }
+
+    /// `true` if the key data changed since the keys were last read (the
+    /// same flag that drives the INT pin).
+    pub fn key_interrupt(&mut self) -> Result<bool, Error<E>> {
+        let mut flag = [0u8];
+        self.i2c
+            .write_read(self.address, &[CMD_INT_FLAG], &mut flag)
+            .map_err(Error::I2c)?;
+        Ok(flag[0] != 0)
+    }
+
+    /// Set or clear a single pixel in the buffer. Columns 0-7 go through the
+    /// [`Orientation`], columns 8-15 are addressed directly.
+    pub fn set_pixel(&mut self, x: u8, y: u8, on: bool) -> Result<(), Error<E>> {
+        let (index, mask) = self.locate(x, y)?;
+        if on {
+            self.buffer[index] |= mask;
+        } else {
+            self.buffer[index] &= !mask;
+        }
+        Ok(())
+    }
+
+    /// Read a single pixel from the buffer.
+    pub fn pixel(&self, x: u8, y: u8) -> Result<bool, Error<E>> {
+        let (index, mask) = self.locate(x, y)?;
+        Ok(self.buffer[index] & mask != 0)
+    }
+
+    /// Replace the 8x8 image, bit `x` of `rows[y]` being pixel `(x, y)`.
+    /// Upright and straight, that's the first 8 columns of every row.
+    pub fn set_rows(&mut self, rows: &[u8; ROWS]) {
+        if self.orientation.is_straight() {
+            for (y, row) in rows.iter().enumerate() {
+                self.buffer[y * 2] = *row;
+            }
+            return;
+        }
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..ROWS as u8 {
+                let (index, mask) = self.orientation_locate(x, y as u8);
+                if row & 1 << x != 0 {
+                    self.buffer[index] |= mask;
+                } else {
+                    self.buffer[index] &= !mask;
+                }
+            }
+        }
+    }
+
+    /// Clear the buffer.
+    pub fn clear(&mut self) {
+        self.buffer = [0; ROWS * 2];
+    }
+
+    /// Raw display RAM buffer, two bytes per row.
+    pub fn buffer(&self) -> &[u8; ROWS * 2] {
+        &self.buffer
+    }
+
+    /// Mutable access to the raw display RAM buffer.
+    pub fn buffer_mut(&mut self) -> &mut [u8; ROWS * 2] {
+        &mut self.buffer
+    }
+
+    /// Send the changes in the buffer to the chip.
+    pub fn write_display(&mut self) -> Result<(), Error<E>> {
+        self.stats.flushes = self.stats.flushes.wrapping_add(1);
+        let Some(shown) = self.shown else {
+            return self.write_ram(0, ROWS * 2);
+        };
+        let buffer = self.buffer;
+        let mut changed = (0..ROWS * 2).filter(|&i| buffer[i] != shown[i]);
+        let Some(mut start) = changed.next() else {
+            self.stats.skipped = self.stats.skipped.wrapping_add(1);
+            return Ok(());
+        };
+        let mut end = start + 1;
+        for i in changed {
+            if i - end > RUN_GAP {
+                self.write_ram(start, end)?;
+                start = i;
+            }
+            end = i + 1;
+        }
+        self.write_ram(start, end)
+    }
+
+    /// Send the whole buffer with the next [`write_display`](Self::write_display),
+    /// e.g. after the module lost power.
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    /// Start from display RAM the chip is known to show, so the next
+    /// [`write_display`](Self::write_display) only sends what differs, e.g.
+    /// when a driver is made for each write to a module on a shared bus.
+    pub fn with_shown(mut self, shown: Option<[u8; ROWS * 2]>) -> Self {
+        self.shown = shown;
+        self
+    }
+
+    /// The display RAM the chip shows, `None` until known.
+    pub fn shown(&self) -> Option<[u8; ROWS * 2]> {
+        self.shown
+    }
+
+    pub fn stats(&self) -> FlushStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = FlushStats::default();
+    }
+
+    /// Shorthand for [`set_rows`](Self::set_rows) followed by [`write_display`](Self::write_display).
+    pub fn write_rows(&mut self, rows: &[u8; ROWS]) -> Result<(), Error<E>> {
+        self.set_rows(rows);
+        self.write_display()
+    }
+
+    /// Give back the I2C bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    // Write buffer bytes `start..end` in one go
+    fn write_ram(&mut self, start: usize, end: usize) -> Result<(), Error<E>> {
+        // RAM address followed by the bytes from there
+        let mut write_buf = [0u8; ROWS * 2 + 1];
+        write_buf[0] = CMD_DISPLAY_RAM + start as u8;
+        write_buf[1..=end - start].copy_from_slice(&self.buffer[start..end]);
+        let len = end - start + 1;
+        self.stats.writes = self.stats.writes.wrapping_add(1);
+        self.stats.bytes = self.stats.bytes.wrapping_add(len as u32);
+        if let Err(error) = self.i2c.write(self.address, &write_buf[..len]) {
+            // Part of it may have arrived
+            self.shown = None;
+            return Err(Error::I2c(error));
+        }
+        let shown = self.shown.get_or_insert(self.buffer);
+        shown[start..end].copy_from_slice(&self.buffer[start..end]);
+        Ok(())
+    }
+
+    fn write_display_setup(&mut self) -> Result<(), Error<E>> {
+        self.command(CMD_DISPLAY_SETUP | self.blink.bits() | self.display_on as u8)
+    }
+
+    fn command(&mut self, command: u8) -> Result<(), Error<E>> {
+        self.i2c.write(self.address, &[command]).map_err(Error::I2c)
+    }
+
+    fn locate(&self, x: u8, y: u8) -> Result<(usize, u8), Error<E>> {
+        if x as usize >= COLUMNS || y as usize >= ROWS {
+            return Err(Error::OutOfBounds);
+        }
+        if (x as usize) < ROWS {
+            return Ok(self.orientation_locate(x, y));
+        }
+        Ok(Self::raw_locate(x, y))
+    }
+
+    // Buffer index and bit of image pixel `(x, y)`, both below 8
+    fn orientation_locate(&self, x: u8, y: u8) -> (usize, u8) {
+        let (column, row) = self.orientation.map(x, y);
+        Self::raw_locate(column, row)
+    }
+
+    fn raw_locate(column: u8, row: u8) -> (usize, u8) {
+        (row as usize * 2 + column as usize / 8, 1 << (column % 8))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockI2c;
+
+    #[test]
+    fn init_sequence() {
+        let i2c = MockI2c::new();
+        let mut matrix = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        matrix.init().unwrap();
+
+        let writes = i2c.writes();
+        assert_eq!(writes.len(), 4);
+        assert!(writes.iter().all(|(address, _)| *address == 0x70));
+        assert_eq!(writes[0].1, [0x21]);
+        assert_eq!(writes[1].1, [0u8; 17]);
+        assert_eq!(writes[2].1, [0xEF]);
+        assert_eq!(writes[3].1, [0x81]);
+    }
+
+    #[test]
+    fn rows_use_low_byte() {
+        let i2c = MockI2c::new();
+        let mut matrix = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        matrix.write_rows(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+        assert_eq!(
+            i2c.writes()[0].1,
+            [0x00, 1, 0, 2, 0, 3, 0, 4, 0, 5, 0, 6, 0, 7, 0, 8, 0]
+        );
+    }
+
+    #[test]
+    fn pixels() {
+        let mut matrix = Ht16k33::new(MockI2c::new(), DEFAULT_ADDRESS);
+        matrix.set_pixel(3, 2, true).unwrap();
+        matrix.set_pixel(9, 7, true).unwrap();
+        assert_eq!(matrix.buffer()[4], 0b0000_1000);
+        assert_eq!(matrix.buffer()[15], 0b0000_0010);
+        assert!(matrix.pixel(3, 2).unwrap());
+
+        matrix.set_pixel(3, 2, false).unwrap();
+        assert!(!matrix.pixel(3, 2).unwrap());
+        assert_eq!(matrix.set_pixel(16, 0, true), Err(Error::OutOfBounds));
+        assert_eq!(matrix.pixel(0, 8), Err(Error::OutOfBounds));
+    }
+
+    #[test]
+    fn rotations() {
+        // Where the top left pixel ends up
+        let corner = |rotation| Orientation::new().with_rotation(rotation).map(0, 0);
+        assert_eq!(corner(Rotation::None), (0, 0));
+        assert_eq!(corner(Rotation::Quarter), (7, 0));
+        assert_eq!(corner(Rotation::Half), (7, 7));
+        assert_eq!(corner(Rotation::ThreeQuarters), (0, 7));
+
+        // Flipped before rotating
+        let flipped = Orientation::new().with_flip(true, false);
+        assert_eq!(flipped.map(0, 0), (7, 0));
+        assert_eq!(flipped.with_rotation(Rotation::Quarter).map(0, 0), (7, 7));
+        assert_eq!(Orientation::new().with_flip(false, true).map(2, 0), (2, 7));
+    }
+
+    #[test]
+    fn rows_go_through_the_orientation() {
+        let i2c = MockI2c::new();
+        let orientation = Orientation::new().with_rotation(Rotation::Quarter);
+        let mut matrix = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS).with_orientation(orientation);
+        // A line along the top becomes a line down the right edge
+        matrix.write_rows(&[0xFF, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(
+            i2c.writes()[0].1[1..],
+            [0x80, 0, 0x80, 0, 0x80, 0, 0x80, 0, 0x80, 0, 0x80, 0, 0x80, 0, 0x80, 0]
+        );
+
+        // Pixels are mapped the same way
+        matrix.set_rows(&[0; ROWS]);
+        matrix.set_pixel(1, 0, true).unwrap();
+        assert_eq!(matrix.buffer()[2], 0x80);
+        assert!(matrix.pixel(1, 0).unwrap());
+        assert!(!matrix.pixel(0, 1).unwrap());
+    }
+
+    #[test]
+    fn column_tables() {
+        let mut matrix = Ht16k33::new(MockI2c::new(), DEFAULT_ADDRESS)
+            .with_orientation(Orientation::new().with_columns(OFFSET_COLUMNS));
+        matrix.set_rows(&[0b0000_0011, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(matrix.buffer()[0], 0b1000_0001);
+
+        // Red LEDs of a bicolor backpack, green ones left alone
+        matrix.buffer_mut()[0] = 0xFF;
+        matrix.set_orientation(Orientation::new().with_columns(RED_COLUMNS));
+        matrix.set_rows(&[0b0000_0101, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(matrix.buffer()[..2], [0xFF, 0b0000_0101]);
+        // Raw columns 8-15 still reach the same LEDs
+        assert!(matrix.pixel(10, 0).unwrap());
+    }
+
+    #[test]
+    fn key_scan() {
+        let i2c = MockI2c::new();
+        let mut matrix = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        matrix.set_interrupt_pin(InterruptPin::ActiveLow).unwrap();
+
+        // K1 and K13 on KS0, K3 on KS2, unused bits set
+        i2c.queue_read(&[0x01, 0x10, 0, 0, 0x04, 0xE0]);
+        let keys = matrix.read_keys().unwrap();
+        assert_eq!(keys, Keys([0x1001, 0, 0x0004]));
+        assert!(keys.is_pressed(Key::new(0, 12)));
+        assert!(!keys.is_pressed(Key::new(1, 0)));
+        assert!(!keys.is_pressed(Key::new(3, 0)));
+        assert!(!keys.is_pressed(Key::new(0, 16)));
+        assert_eq!(keys.first(), Some(Key::new(0, 0)));
+
+        i2c.queue_read(&[1]);
+        assert!(matrix.key_interrupt().unwrap());
+        assert_eq!(i2c.writes(), [(0x70, vec![0xA1]), (0x70, vec![0x40]), (0x70, vec![0x60])]);
+    }
+
+    #[test]
+    fn only_changes_are_sent() {
+        let i2c = MockI2c::new();
+        let mut matrix = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        matrix.init().unwrap();
+        let sent = |i2c: &MockI2c| i2c.writes().split_off(4);
+
+        // Unchanged frames cost nothing
+        matrix.write_rows(&[0; ROWS]).unwrap();
+        assert!(sent(&i2c).is_empty());
+
+        // One row: the RAM address and one byte
+        matrix.set_pixel(2, 3, true).unwrap();
+        matrix.write_display().unwrap();
+        assert_eq!(sent(&i2c), [(0x70, vec![0x06, 0b100])]);
+
+        // Rows 0 and 1 are close enough for one run, row 7 gets its own
+        matrix.set_rows(&[1, 1, 0, 0b100, 0, 0, 0, 1]);
+        matrix.write_display().unwrap();
+        assert_eq!(
+            sent(&i2c)[1..],
+            [(0x70, vec![0x00, 1, 0, 1]), (0x70, vec![0x0E, 1])]
+        );
+
+        let stats = matrix.stats();
+        assert_eq!(stats.flushes, 4);
+        assert_eq!(stats.skipped, 1);
+        assert_eq!(stats.writes, 4);
+        assert_eq!(stats.bytes, 17 + 2 + 4 + 2);
+    }
+
+    #[test]
+    fn everything_is_sent_after_a_failure() {
+        let i2c = MockI2c::new();
+        let mut matrix = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        matrix.write_rows(&[0; ROWS]).unwrap();
+        i2c.set_fail(true);
+        matrix.set_pixel(0, 0, true).unwrap();
+        assert!(matrix.write_display().is_err());
+        i2c.set_fail(false);
+        matrix.write_display().unwrap();
+        assert_eq!(i2c.writes()[1].1.len(), 17);
+
+        matrix.invalidate();
+        matrix.write_display().unwrap();
+        assert_eq!(i2c.writes()[2].1.len(), 17);
+    }
+
+    #[test]
+    fn brightness_and_blink() {
+        let i2c = MockI2c::new();
+        let mut matrix = Ht16k33::new(i2c.clone(), 0x71);
+        matrix.set_brightness(3).unwrap();
+        assert_eq!(matrix.set_brightness(16), Err(Error::InvalidBrightness(16)));
+        matrix.set_display(true).unwrap();
+        matrix.set_blink(Blink::Slow).unwrap();
+        matrix.set_display(false).unwrap();
+
+        let commands: Vec<_> = i2c.writes().into_iter().map(|(_, bytes)| bytes[0]).collect();
+        assert_eq!(commands, [0xE3, 0x81, 0x87, 0x86]);
+    }
+
+    #[test]
+    fn bus_errors_are_reported() {
+        let i2c = MockI2c::new();
+        i2c.set_fail(true);
+        let mut matrix = Ht16k33::new(i2c, DEFAULT_ADDRESS);
+        assert!(matches!(matrix.init(), Err(Error::I2c(_))));
+    }
+}
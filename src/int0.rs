@@ -0,0 +1,419 @@
+//! Interrupt-Driven Button on INT0
+//!
+//! The kit button sits on D2, the INT0 pin of the ATmega328P. [`Int0Button`]
+//! lets the INT0 interrupt record every press and release with its
+//! [`crate::clock`] timestamp, so presses are not missed while the main loop
+//! is stuck in a long blocking pattern; the main loop picks them up later
+//! with [`Int0Button::pop`].
+//!
+//! Edges go through an [`EdgeFilter`] in the interrupt (contact bounce only
+//! ever produces the first edge of a press or release) and then into an
+//! [`EdgeQueue`], a single-producer single-consumer ring buffer that needs
+//! no critical section.
+//!
+//! [`Int0Button::power_down`] puts the MCU into power-down sleep until the
+//! button is pressed. In power-down only a low *level* on INT0 can wake the
+//! MCU, so this needs an active-low button (the kit button on a pull-up
+//! input). Timer0 stops while asleep, so `millis()` doesn't count the time
+//! spent sleeping.
+//!
+//! ## Example
+//! ```ignore
+//! let mut button = Int0Button::new(dp.EXINT, pins.d2.into_pull_up_input());
+//! unsafe { avr_device::interrupt::enable() };
+//! loop {
+//!     slow_blocking_animation();
+//!     while let Some(edge) = button.pop() {
+//!         if edge.pressed { next_animation() }
+//!     }
+//! }
+//! ```
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use crate::clock::{Duration, Instant};
+
+/// Contact bounce settles well within this time
+pub const DEBOUNCE: Duration = Duration::from_millis(10);
+
+/// A press or release of the button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub pressed: bool,
+    pub at: Instant,
+}
+
+impl Edge {
+    const NONE: Edge = Edge {
+        pressed: false,
+        at: Instant::from_millis(0),
+    };
+}
+
+/// Drops contact bounce from a stream of edges
+///
+/// An edge counts if it changes the reported state and comes at least the
+/// debounce time after the last edge that counted. A change that comes too
+/// soon (a real release right after its press looks just like bounce) is
+/// kept pending and reported by [`EdgeFilter::settled`] once the pin has
+/// stayed at the new level for the debounce time.
+#[derive(Clone, Copy, Debug)]
+pub struct EdgeFilter {
+    debounce: Duration,
+    // Last reported state and when it was reported
+    pressed: bool,
+    last: Option<Instant>,
+    // Pin level as of the latest edge, counted or not
+    level: Edge,
+}
+
+impl EdgeFilter {
+    pub const fn new(debounce: Duration) -> Self {
+        Self {
+            debounce,
+            // Starts out released
+            pressed: false,
+            last: None,
+            level: Edge::NONE,
+        }
+    }
+
+    /// `true` if `edge` is a real press or release. Call
+    /// [`settled`](Self::settled) first so a pending change is reported
+    /// before the edge that follows it.
+    pub fn accept(&mut self, edge: Edge) -> bool {
+        self.level = edge;
+        let changed = edge.pressed != self.pressed;
+        let settled = self
+            .last
+            .is_none_or(|at| edge.at.duration_since(at) >= self.debounce);
+        if !changed || !settled {
+            return false;
+        }
+        self.report(edge);
+        true
+    }
+
+    /// The pending change, once the pin has stayed at a level other than
+    /// the reported one for the debounce time by `now`. The edge has the
+    /// time the pin changed.
+    pub fn settled(&mut self, now: Instant) -> Option<Edge> {
+        let edge = self.level;
+        if edge.pressed == self.pressed || now.duration_since(edge.at) < self.debounce {
+            return None;
+        }
+        self.report(edge);
+        Some(edge)
+    }
+
+    fn report(&mut self, edge: Edge) {
+        self.pressed = edge.pressed;
+        self.last = Some(edge.at);
+    }
+}
+
+/// Lock-free ring buffer for one producer (the interrupt) and one consumer
+/// (the main loop), holding up to `N - 1` edges (`N` at most 255)
+pub struct EdgeQueue<const N: usize> {
+    slots: [UnsafeCell<Edge>; N],
+    // Next slot to write, only changed by the producer
+    head: AtomicU8,
+    // Next slot to read, only changed by the consumer
+    tail: AtomicU8,
+    dropped: AtomicU8,
+}
+
+// SAFETY: a slot is only written by the producer while it is outside the
+// readable range, and only read by the consumer while it is inside it
+unsafe impl<const N: usize> Sync for EdgeQueue<N> {}
+
+impl<const N: usize> EdgeQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { UnsafeCell::new(Edge::NONE) }; N],
+            head: AtomicU8::new(0),
+            tail: AtomicU8::new(0),
+            dropped: AtomicU8::new(0),
+        }
+    }
+
+    /// Add an edge, or count it as dropped if the queue is full.
+    ///
+    /// # Safety
+    /// Only one context (e.g. one interrupt handler) may ever push.
+    pub unsafe fn push(&self, edge: Edge) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = Self::next(head);
+        if next == self.tail.load(Ordering::Acquire) {
+            let dropped = self.dropped.load(Ordering::Relaxed);
+            self.dropped
+                .store(dropped.wrapping_add(1), Ordering::Relaxed);
+            return false;
+        }
+        *self.slots[head as usize].get() = edge;
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Take the oldest edge.
+    ///
+    /// # Safety
+    /// Only one context (e.g. the main loop) may ever pop.
+    pub unsafe fn pop(&self) -> Option<Edge> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+        let edge = *self.slots[tail as usize].get();
+        self.tail.store(Self::next(tail), Ordering::Release);
+        Some(edge)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.head.load(Ordering::Acquire) == self.tail.load(Ordering::Acquire)
+    }
+
+    /// Edges lost because the queue was full (wraps at 256).
+    pub fn dropped(&self) -> u8 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn next(index: u8) -> u8 {
+        if index as usize + 1 == N {
+            0
+        } else {
+            index + 1
+        }
+    }
+}
+
+impl<const N: usize> Default for EdgeQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "avr")]
+pub use self::exint::Int0Button;
+
+#[cfg(target_arch = "avr")]
+mod exint {
+    use core::cell::Cell;
+
+    use arduino_hal::hal::port::PD2;
+    use arduino_hal::pac::{CPU, EXINT, PORTD};
+    use arduino_hal::port::mode::{Input, PullUp};
+    use arduino_hal::port::Pin;
+    use avr_device::interrupt::{self, Mutex};
+
+    use super::{Edge, EdgeFilter, EdgeQueue, DEBOUNCE};
+    use crate::clock::Instant;
+
+    // Sense control (ISC0) values
+    const ISC_LOW_LEVEL: u8 = 0b00;
+    const ISC_ANY_CHANGE: u8 = 0b01;
+
+    static EDGES: EdgeQueue<16> = EdgeQueue::new();
+    static FILTER: Mutex<Cell<EdgeFilter>> = Mutex::new(Cell::new(EdgeFilter::new(DEBOUNCE)));
+
+    /// Active-low button on D2, recorded by the INT0 interrupt
+    ///
+    /// Global interrupts must be enabled, and the clock running for
+    /// meaningful timestamps.
+    pub struct Int0Button {
+        exint: EXINT,
+        _pin: Pin<Input<PullUp>, PD2>,
+    }
+
+    impl Int0Button {
+        pub fn new(exint: EXINT, pin: Pin<Input<PullUp>, PD2>) -> Self {
+            exint.eicra().write(|w| w.isc0().set(ISC_ANY_CHANGE));
+            exint.eifr().write(|w| w.intf0().set_bit());
+            exint.eimsk().write(|w| w.int0().set_bit());
+            Self { exint, _pin: pin }
+        }
+
+        /// Oldest press or release not picked up yet.
+        pub fn pop(&mut self) -> Option<Edge> {
+            // SAFETY: `Int0Button` is the only consumer, the ISR the only producer
+            if let Some(edge) = unsafe { EDGES.pop() } {
+                return Some(edge);
+            }
+            // A change that came too soon after the last one, reported once
+            // the pin has settled; no edge may follow to let the ISR do it
+            interrupt::free(|cs| {
+                let filter = FILTER.borrow(cs);
+                let mut state = filter.get();
+                let edge = state.settled(Instant::now());
+                filter.set(state);
+                edge
+            })
+        }
+
+        /// Drop all recorded edges and return the time of the first press
+        /// among them.
+        pub fn take_press(&mut self) -> Option<Instant> {
+            let mut first = None;
+            while let Some(edge) = self.pop() {
+                if edge.pressed && first.is_none() {
+                    first = Some(edge.at);
+                }
+            }
+            first
+        }
+
+        /// Edges lost because they weren't picked up in time.
+        pub fn dropped(&self) -> u8 {
+            EDGES.dropped()
+        }
+
+        /// Sleep in power-down mode until the button is pressed.
+        ///
+        /// Returns right away if there are unread edges.
+        pub fn power_down(&mut self, cpu: &CPU) {
+            interrupt::disable();
+            if !EDGES.is_empty() {
+                // SAFETY: interrupts were enabled before
+                unsafe { interrupt::enable() };
+                return;
+            }
+            // Only a low level wakes from power-down; the ISR switches back
+            self.exint.eicra().write(|w| w.isc0().set(ISC_LOW_LEVEL));
+            cpu.smcr().write(|w| w.sm().pdown().se().set_bit());
+            // SAFETY: the instruction after `sei` always runs before any
+            // interrupt, so a press can't slip in between enable and sleep
+            unsafe { interrupt::enable() };
+            avr_device::asm::sleep();
+            cpu.smcr().write(|w| w.se().clear_bit());
+        }
+    }
+
+    #[avr_device::interrupt(atmega328p)]
+    fn INT0() {
+        interrupt::free(|cs| {
+            // SAFETY: read-only access to the input and sense control registers
+            let pressed = unsafe { &*PORTD::ptr() }.pind().read().pd2().bit_is_clear();
+            let exint = unsafe { &*EXINT::ptr() };
+            if exint.eicra().read().isc0().bits() == ISC_LOW_LEVEL {
+                // Woken up: back to edges so a held button doesn't retrigger
+                exint.eicra().write(|w| w.isc0().set(ISC_ANY_CHANGE));
+            }
+
+            let edge = Edge {
+                pressed,
+                at: Instant::now(),
+            };
+            let filter = FILTER.borrow(cs);
+            let mut state = filter.get();
+            // SAFETY: this handler is the only producer (`pop` only takes
+            // pending edges inside a critical section, when the queue is empty)
+            if let Some(pending) = state.settled(edge.at) {
+                unsafe { EDGES.push(pending) };
+            }
+            if state.accept(edge) {
+                unsafe { EDGES.push(edge) };
+            }
+            filter.set(state);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(pressed: bool, ms: u32) -> Edge {
+        Edge {
+            pressed,
+            at: Instant::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn filter_drops_bounce() {
+        let mut filter = EdgeFilter::new(DEBOUNCE);
+        let edges = [
+            edge(false, 0), // spurious release at start
+            edge(true, 100),
+            edge(false, 101), // bounce
+            edge(true, 102),
+            edge(false, 300),
+            edge(true, 303), // bounce
+            edge(false, 304),
+            edge(true, 500),
+        ];
+        let accepted: Vec<_> = edges
+            .into_iter()
+            .filter(|&edge| filter.accept(edge))
+            .map(|edge| (edge.pressed, edge.at.as_millis()))
+            .collect();
+        assert_eq!(accepted, [(true, 100), (false, 300), (true, 500)]);
+    }
+
+    #[test]
+    fn filter_reports_early_release_once_settled() {
+        let mut filter = EdgeFilter::new(DEBOUNCE);
+        assert!(filter.accept(edge(true, 100)));
+        // A real release, but too soon to tell from bounce
+        assert!(!filter.accept(edge(false, 105)));
+        assert_eq!(filter.settled(Instant::from_millis(110)), None);
+        assert_eq!(
+            filter.settled(Instant::from_millis(115)),
+            Some(edge(false, 105))
+        );
+        assert_eq!(filter.settled(Instant::from_millis(200)), None);
+        assert!(filter.accept(edge(true, 500)));
+
+        // Without a poll in between, the next edge brings it out
+        let mut filter = EdgeFilter::new(DEBOUNCE);
+        assert!(filter.accept(edge(true, 100)));
+        assert!(!filter.accept(edge(false, 105)));
+        assert_eq!(
+            filter.settled(Instant::from_millis(500)),
+            Some(edge(false, 105))
+        );
+        assert!(filter.accept(edge(true, 500)));
+    }
+
+    #[test]
+    fn filter_ignores_bounce_back_to_the_reported_state() {
+        let mut filter = EdgeFilter::new(DEBOUNCE);
+        assert!(filter.accept(edge(true, 100)));
+        assert!(!filter.accept(edge(false, 101)));
+        assert!(!filter.accept(edge(true, 102)));
+        assert_eq!(filter.settled(Instant::from_millis(300)), None);
+        assert!(filter.accept(edge(false, 300)));
+    }
+
+    #[test]
+    fn queue_is_fifo() {
+        let queue: EdgeQueue<4> = EdgeQueue::new();
+        unsafe {
+            assert_eq!(queue.pop(), None);
+            assert!(queue.push(edge(true, 1)));
+            assert!(queue.push(edge(false, 2)));
+            assert_eq!(queue.pop(), Some(edge(true, 1)));
+            // Wraps around the end of the buffer
+            assert!(queue.push(edge(true, 3)));
+            assert!(queue.push(edge(false, 4)));
+            assert_eq!(queue.pop(), Some(edge(false, 2)));
+            assert_eq!(queue.pop(), Some(edge(true, 3)));
+            assert_eq!(queue.pop(), Some(edge(false, 4)));
+            assert_eq!(queue.pop(), None);
+        }
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn full_queue_drops_new_edges() {
+        let queue: EdgeQueue<4> = EdgeQueue::new();
+        unsafe {
+            for ms in 0..5 {
+                queue.push(edge(ms % 2 == 0, ms));
+            }
+            assert_eq!(queue.dropped(), 2);
+            assert_eq!(queue.pop(), Some(edge(true, 0)));
+        }
+    }
+}
@@ -0,0 +1,467 @@
+//! Analog Joystick
+//!
+//! Reads the two joystick axes and maps them to one of eight directions.
+//! Raw values range from 0 to 1023 with the stick resting around 512; the
+//! exact rest position differs from module to module, so [`Joystick`]
+//! measures it with [`Joystick::calibrate`] and reports positions relative to
+//! it, normalized to `-NORMALIZED_MAX..=NORMALIZED_MAX` on each axis.
+//!
+//! Directions use screen coordinates: a low X reading is left and a low Y
+//! reading is up. Modules that report up as a high value can flip the Y axis
+//! with [`Joystick::set_invert_y`] (and the X axis with
+//! [`Joystick::set_invert_x`]).
+//!
+//! For continuous input, [`Joystick::polar`] gives the stick position as an
+//! angle and a distance from the center, e.g. to pick a hue on a color wheel.
+//!
+//! ## Example
+//! ```ignore
+//! let mut joystick = Joystick::new(x_channel, y_channel)
+//!     .with_button(Button::new(pins.d2.into_pull_up_input(), Active::Low));
+//! joystick.calibrate(&mut adc, 16);
+//! loop {
+//!     let (x, y) = joystick.position(&mut adc);
+//!     if let Some(direction) = joystick.direction(&mut adc) { /* ... */ }
+//!     if let Ok(Some(Event::Click)) = joystick.button().update(Instant::now()) { /* ... */ }
+//! }
+//! ```
+
+use crate::analog::AnalogRead;
+
+/// Raw reading with the stick at rest
+pub const CENTER_VALUE: u16 = 512;
+
+/// Distance from the center, in raw counts, that the examples used to
+/// require on each axis before it counted as pushed
+pub const THRESHOLD: u16 = 300;
+
+/// Largest raw reading of the 10-bit ADC
+pub const ADC_MAX: u16 = 1023;
+
+/// Normalized value of a fully pushed axis
+pub const NORMALIZED_MAX: i16 = 1000;
+
+/// Default distance from the center before the stick has a direction
+/// (the same as [`THRESHOLD`] raw counts, but measured from the center in
+/// any direction instead of along each axis)
+pub const DIRECTION_THRESHOLD: i16 = 586;
+
+// tan(22.5°) * 1000: below this slope a direction is straight, not diagonal
+const TAN_22_5: i32 = 414;
+
+/// One of the eight stick directions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction8 {
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+impl Direction8 {
+    /// Unit step in screen coordinates (`y` grows downwards).
+    pub const fn delta(self) -> (i8, i8) {
+        match self {
+            Direction8::Up => (0, -1),
+            Direction8::UpRight => (1, -1),
+            Direction8::Right => (1, 0),
+            Direction8::DownRight => (1, 1),
+            Direction8::Down => (0, 1),
+            Direction8::DownLeft => (-1, 1),
+            Direction8::Left => (-1, 0),
+            Direction8::UpLeft => (-1, -1),
+        }
+    }
+
+    /// Direction of a normalized position, split into 45° sectors; `None`
+    /// closer than `threshold` to the center.
+    pub fn from_position(x: i16, y: i16, threshold: i16) -> Option<Self> {
+        if magnitude(x, y) < threshold.max(1) as u16 {
+            return None;
+        }
+        let (ax, ay) = ((x as i32).abs(), (y as i32).abs());
+        // An axis only counts when the stick is within 67.5° of it
+        let dx = if ax * 1000 < ay * TAN_22_5 { 0 } else { x.signum() as i8 };
+        let dy = if ay * 1000 < ax * TAN_22_5 { 0 } else { y.signum() as i8 };
+        Self::from_delta(dx, dy)
+    }
+
+    fn from_delta(dx: i8, dy: i8) -> Option<Self> {
+        match (dx, dy) {
+            (0, -1) => Some(Direction8::Up),
+            (1, -1) => Some(Direction8::UpRight),
+            (1, 0) => Some(Direction8::Right),
+            (1, 1) => Some(Direction8::DownRight),
+            (0, 1) => Some(Direction8::Down),
+            (-1, 1) => Some(Direction8::DownLeft),
+            (-1, 0) => Some(Direction8::Left),
+            (-1, -1) => Some(Direction8::UpLeft),
+            _ => None,
+        }
+    }
+}
+
+/// Scale a raw reading to `-NORMALIZED_MAX..=NORMALIZED_MAX` around `center`.
+///
+/// Each side of the center is scaled on its own, so an off-center rest
+/// position still reaches the full range in both directions.
+pub fn normalize(raw: u16, center: u16) -> i16 {
+    let raw = raw.min(ADC_MAX) as i32;
+    let center = center.min(ADC_MAX) as i32;
+    let span = if raw >= center { ADC_MAX as i32 - center } else { center };
+    if span == 0 {
+        return 0;
+    }
+    ((raw - center) * NORMALIZED_MAX as i32 / span) as i16
+}
+
+/// Distance of a normalized position from the center, at most `NORMALIZED_MAX`.
+pub fn magnitude(x: i16, y: i16) -> u16 {
+    let squared = (x as i32 * x as i32 + y as i32 * y as i32) as u32;
+    squared.isqrt().min(NORMALIZED_MAX as u32) as u16
+}
+
+/// Angle of a normalized position in degrees, 0-359.
+///
+/// 0° is right and the angle grows counterclockwise, so up is 90°; the
+/// center reads as 0°. Uses an integer atan2 approximation that stays within
+/// half a degree, cheap enough for the AVR.
+pub fn angle(x: i16, y: i16) -> u16 {
+    // Screen coordinates: up is a negative y
+    let (x, y) = (x as i32, -(y as i32));
+    let (ax, ay) = (x.abs(), y.abs());
+    if ax == 0 && ay == 0 {
+        return 0;
+    }
+    // atan(z) ≈ 45z + 15.64z(1 - z) degrees for 0 <= z <= 1, in hundredths
+    // of a degree with z scaled by 1024
+    let z = ax.min(ay) * 1024 / ax.max(ay);
+    let octant = (4500 * z + 1564 * z * (1024 - z) / 1024) / 1024;
+    let quadrant = if ay > ax { 9000 - octant } else { octant };
+    let hundredths = match (x >= 0, y >= 0) {
+        (true, true) => quadrant,
+        (false, true) => 18000 - quadrant,
+        (false, false) => 18000 + quadrant,
+        (true, false) => 36000 - quadrant,
+    };
+    ((hundredths + 50) / 100 % 360) as u16
+}
+
+/// Region around the center that reads as zero, in normalized units
+///
+/// The radial deadzone zeroes the whole position inside a circle; the axial
+/// deadzone zeroes each axis on its own, which makes it easy to move along
+/// exactly one axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Deadzone {
+    pub radial: i16,
+    pub axial: i16,
+}
+
+impl Deadzone {
+    pub const NONE: Deadzone = Deadzone { radial: 0, axial: 0 };
+
+    pub fn apply(&self, x: i16, y: i16) -> (i16, i16) {
+        if magnitude(x, y) < self.radial.max(0) as u16 {
+            return (0, 0);
+        }
+        let axial = |value: i16| if value.abs() < self.axial { 0 } else { value };
+        (axial(x), axial(y))
+    }
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Deadzone { radial: 80, axial: 0 }
+    }
+}
+
+/// Two-axis analog joystick, optionally with its push button `B`
+pub struct Joystick<X, Y, B = ()> {
+    x: X,
+    y: Y,
+    button: B,
+    center: (u16, u16),
+    invert_x: bool,
+    invert_y: bool,
+    deadzone: Deadzone,
+    threshold: i16,
+}
+
+impl<X, Y> Joystick<X, Y> {
+    pub fn new(x: X, y: Y) -> Self {
+        Self {
+            x,
+            y,
+            button: (),
+            center: (CENTER_VALUE, CENTER_VALUE),
+            invert_x: false,
+            invert_y: false,
+            deadzone: Deadzone::default(),
+            threshold: DIRECTION_THRESHOLD,
+        }
+    }
+
+    /// Attach the stick's push button, e.g. a [`crate::button::Button`].
+    pub fn with_button<B>(self, button: B) -> Joystick<X, Y, B> {
+        Joystick {
+            x: self.x,
+            y: self.y,
+            button,
+            center: self.center,
+            invert_x: self.invert_x,
+            invert_y: self.invert_y,
+            deadzone: self.deadzone,
+            threshold: self.threshold,
+        }
+    }
+}
+
+impl<X, Y, B> Joystick<X, Y, B> {
+    /// Treat a high X reading as left instead of right.
+    pub fn set_invert_x(&mut self, invert: bool) {
+        self.invert_x = invert;
+    }
+
+    /// Treat a high Y reading as up instead of down.
+    pub fn set_invert_y(&mut self, invert: bool) {
+        self.invert_y = invert;
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+    }
+
+    /// Normalized distance from the center needed for a direction.
+    pub fn set_direction_threshold(&mut self, threshold: i16) {
+        self.threshold = threshold;
+    }
+
+    /// Raw rest position, as measured by [`calibrate`](Self::calibrate).
+    pub fn center(&self) -> (u16, u16) {
+        self.center
+    }
+
+    pub fn set_center(&mut self, center: (u16, u16)) {
+        self.center = center;
+    }
+
+    pub fn button(&mut self) -> &mut B {
+        &mut self.button
+    }
+
+    /// Measure the rest position by averaging `samples` readings; call it
+    /// at startup while nobody touches the stick.
+    pub fn calibrate<ADC>(&mut self, adc: &mut ADC, samples: u8)
+    where
+        X: AnalogRead<ADC>,
+        Y: AnalogRead<ADC>,
+    {
+        let samples = samples.max(1) as u32;
+        let (mut x_sum, mut y_sum) = (0u32, 0u32);
+        for _ in 0..samples {
+            let (x, y) = self.read(adc);
+            x_sum += x as u32;
+            y_sum += y as u32;
+        }
+        self.center = ((x_sum / samples) as u16, (y_sum / samples) as u16);
+    }
+
+    /// Read both raw axes, without calibration or inversion.
+    pub fn read<ADC>(&mut self, adc: &mut ADC) -> (u16, u16)
+    where
+        X: AnalogRead<ADC>,
+        Y: AnalogRead<ADC>,
+    {
+        (self.x.analog_read(adc), self.y.analog_read(adc))
+    }
+
+    /// Calibrated, inverted and deadzoned position, each axis within
+    /// `-NORMALIZED_MAX..=NORMALIZED_MAX`.
+    pub fn position<ADC>(&mut self, adc: &mut ADC) -> (i16, i16)
+    where
+        X: AnalogRead<ADC>,
+        Y: AnalogRead<ADC>,
+    {
+        let (x_raw, y_raw) = self.read(adc);
+        let mut x = normalize(x_raw, self.center.0);
+        let mut y = normalize(y_raw, self.center.1);
+        if self.invert_x {
+            x = -x;
+        }
+        if self.invert_y {
+            y = -y;
+        }
+        self.deadzone.apply(x, y)
+    }
+
+    /// How far the stick is pushed, from 0 to `NORMALIZED_MAX`.
+    pub fn magnitude<ADC>(&mut self, adc: &mut ADC) -> u16
+    where
+        X: AnalogRead<ADC>,
+        Y: AnalogRead<ADC>,
+    {
+        let (x, y) = self.position(adc);
+        magnitude(x, y)
+    }
+
+    /// Read the stick as `(angle, magnitude)`, see [`angle`] and
+    /// [`magnitude`](fn@magnitude).
+    pub fn polar<ADC>(&mut self, adc: &mut ADC) -> (u16, u16)
+    where
+        X: AnalogRead<ADC>,
+        Y: AnalogRead<ADC>,
+    {
+        let (x, y) = self.position(adc);
+        (angle(x, y), magnitude(x, y))
+    }
+
+    /// Read the stick and map it to a direction, `None` while centered.
+    pub fn direction<ADC>(&mut self, adc: &mut ADC) -> Option<Direction8>
+    where
+        X: AnalogRead<ADC>,
+        Y: AnalogRead<ADC>,
+    {
+        let (x, y) = self.position(adc);
+        Direction8::from_position(x, y, self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockAnalog;
+
+    fn direction(x_raw: u16, y_raw: u16) -> Option<Direction8> {
+        Joystick::new(MockAnalog::new(x_raw), MockAnalog::new(y_raw)).direction(&mut ())
+    }
+
+    #[test]
+    fn centered_inside_threshold() {
+        assert_eq!(direction(512, 512), None);
+        assert_eq!(direction(300, 700), None);
+    }
+
+    #[test]
+    fn threshold_is_a_distance_from_the_center() {
+        // The examples used to check each axis against THRESHOLD on its
+        // own; now two axes just short of it add up to a diagonal
+        assert_eq!(direction(213, 811), Some(Direction8::DownLeft));
+        assert_eq!(direction(213, 512), None);
+        assert_eq!(direction(512, 811), None);
+    }
+
+    #[test]
+    fn maps_all_eight_directions() {
+        assert_eq!(direction(512, 0), Some(Direction8::Up));
+        assert_eq!(direction(1023, 0), Some(Direction8::UpRight));
+        assert_eq!(direction(1023, 512), Some(Direction8::Right));
+        assert_eq!(direction(1023, 1023), Some(Direction8::DownRight));
+        assert_eq!(direction(512, 1023), Some(Direction8::Down));
+        assert_eq!(direction(0, 1023), Some(Direction8::DownLeft));
+        assert_eq!(direction(0, 512), Some(Direction8::Left));
+        assert_eq!(direction(0, 0), Some(Direction8::UpLeft));
+    }
+
+    #[test]
+    fn inverted_y_swaps_up_and_down() {
+        let x = MockAnalog::new(512);
+        let y = MockAnalog::new(1000);
+        let mut joystick = Joystick::new(x, y.clone());
+        assert_eq!(joystick.direction(&mut ()), Some(Direction8::Down));
+
+        joystick.set_invert_y(true);
+        assert_eq!(joystick.direction(&mut ()), Some(Direction8::Up));
+
+        y.set(20);
+        assert_eq!(joystick.direction(&mut ()), Some(Direction8::Down));
+    }
+
+    #[test]
+    fn normalizes_each_side_of_the_center() {
+        assert_eq!(normalize(530, 530), 0);
+        assert_eq!(normalize(0, 530), -NORMALIZED_MAX);
+        assert_eq!(normalize(1023, 530), NORMALIZED_MAX);
+        assert_eq!(normalize(265, 530), -500);
+        assert_eq!(normalize(2000, 530), NORMALIZED_MAX);
+    }
+
+    #[test]
+    fn calibration_moves_the_center() {
+        let x = MockAnalog::new(540);
+        let y = MockAnalog::new(490);
+        let mut joystick = Joystick::new(x.clone(), y);
+        joystick.set_deadzone(Deadzone::NONE);
+        assert_ne!(joystick.position(&mut ()), (0, 0));
+
+        joystick.calibrate(&mut (), 8);
+        assert_eq!(joystick.center(), (540, 490));
+        assert_eq!(joystick.position(&mut ()), (0, 0));
+
+        x.set(1023);
+        joystick.set_invert_x(true);
+        assert_eq!(joystick.position(&mut ()), (-NORMALIZED_MAX, 0));
+        assert_eq!(joystick.direction(&mut ()), Some(Direction8::Left));
+        assert_eq!(joystick.magnitude(&mut ()), NORMALIZED_MAX as u16);
+    }
+
+    #[test]
+    fn deadzones() {
+        let radial = Deadzone { radial: 100, axial: 0 };
+        assert_eq!(radial.apply(60, 70), (0, 0));
+        assert_eq!(radial.apply(90, 50), (90, 50));
+
+        let axial = Deadzone { radial: 0, axial: 100 };
+        assert_eq!(axial.apply(60, 70), (0, 0));
+        assert_eq!(axial.apply(800, 70), (800, 0));
+    }
+
+    #[test]
+    fn magnitudes() {
+        assert_eq!(magnitude(0, 0), 0);
+        assert_eq!(magnitude(300, -400), 500);
+        assert_eq!(magnitude(-1000, 1000), NORMALIZED_MAX as u16);
+    }
+
+    #[test]
+    fn sectors_of_45_degrees() {
+        let t = DIRECTION_THRESHOLD;
+        assert_eq!(Direction8::from_position(0, 0, t), None);
+        assert_eq!(Direction8::from_position(500, 0, t), None);
+        assert_eq!(Direction8::from_position(1000, 0, t), Some(Direction8::Right));
+        // 20° below the X axis is still right, 25° is diagonal
+        assert_eq!(Direction8::from_position(940, 342, t), Some(Direction8::Right));
+        assert_eq!(Direction8::from_position(906, 423, t), Some(Direction8::DownRight));
+        assert_eq!(Direction8::from_position(-700, -700, t), Some(Direction8::UpLeft));
+        assert_eq!(Direction8::from_position(-342, -940, t), Some(Direction8::Up));
+        assert_eq!(Direction8::from_position(423, -906, t), Some(Direction8::UpRight));
+    }
+
+    #[test]
+    fn angles() {
+        assert_eq!(angle(0, 0), 0);
+        assert_eq!(angle(1000, 0), 0);
+        assert_eq!(angle(0, -1000), 90);
+        assert_eq!(angle(-1000, 0), 180);
+        assert_eq!(angle(0, 1000), 270);
+        assert_eq!(angle(700, -700), 45);
+        assert_eq!(angle(-700, 700), 225);
+        assert_eq!(angle(1000, 1), 0);
+        assert_eq!(angle(1000, 10), 359);
+    }
+
+    #[test]
+    fn angle_is_within_a_degree() {
+        for degrees in 0..360 {
+            let radians = (degrees as f64).to_radians();
+            let x = (radians.cos() * 1000.0).round() as i16;
+            let y = -(radians.sin() * 1000.0).round() as i16;
+            let error = (angle(x, y) as i32 - degrees).rem_euclid(360);
+            assert!(error == 0 || error == 359 || error == 1, "{degrees}°: {}", angle(x, y));
+        }
+    }
+}
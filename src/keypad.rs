@@ -0,0 +1,195 @@
+//! Keypad on the HT16K33
+//!
+//! Keypads wired to the key scan inputs of an HT16K33 backpack (K1-K13
+//! against KS0-KS2) need no extra pins: the keys are read over the same I2C
+//! bus as the LEDs. [`Keypad`] polls the key data every
+//! [`SCAN_INTERVAL`], ignores changes that don't last [`DEFAULT_DEBOUNCE`]
+//! and reports each key going down or up as a [`KeyEvent`].
+//!
+//! The keypad doesn't own the driver, so the same [`Ht16k33`] keeps drawing
+//! on the matrix in between.
+//!
+//! ## Example
+//! ```ignore
+//! let mut keypad = Keypad::new();
+//! loop {
+//!     match keypad.update(&mut ht16k33, Instant::now()) {
+//!         Ok(Some(KeyEvent::Pressed(key))) => digit(key.column),
+//!         _ => {}
+//!     }
+//! }
+//! ```
+
+use embedded_hal::i2c::I2c;
+
+use crate::clock::{Deadline, Duration, Instant};
+use crate::ht16k33::{Error, Ht16k33, Key, Keys, KEY_ROWS};
+
+/// Time between two reads of the key data
+pub const SCAN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How long the keys have to stay the same before a change counts
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(20);
+
+/// A key went down or up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    Pressed(Key),
+    Released(Key),
+}
+
+/// Debounced key events from the HT16K33 key scan
+pub struct Keypad {
+    debounce: Duration,
+    next_scan: Option<Deadline>,
+    raw: Keys,
+    raw_since: Instant,
+    // Debounced keys, and the part of them reported so far
+    pressed: Keys,
+    reported: Keys,
+}
+
+impl Keypad {
+    /// A keypad with [`DEFAULT_DEBOUNCE`] and no key down.
+    pub fn new() -> Self {
+        Self {
+            debounce: DEFAULT_DEBOUNCE,
+            next_scan: None,
+            raw: Keys::default(),
+            raw_since: Instant::from_millis(0),
+            pressed: Keys::default(),
+            reported: Keys::default(),
+        }
+    }
+
+    pub fn set_debounce(&mut self, debounce: Duration) {
+        self.debounce = debounce;
+    }
+
+    /// Debounced keys.
+    pub fn pressed(&self) -> Keys {
+        self.pressed
+    }
+
+    /// Read the keys when a scan is due and return the next event, if any.
+    ///
+    /// Returns at most one event per call; keys changing together come out
+    /// on the following calls, before the keys are read again.
+    pub fn update<I2C, E>(
+        &mut self,
+        display: &mut Ht16k33<I2C>,
+        now: Instant,
+    ) -> Result<Option<KeyEvent>, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+    {
+        if let Some(event) = self.next_event() {
+            return Ok(Some(event));
+        }
+        if self.next_scan.is_none_or(|scan| scan.expired_at(now)) {
+            self.next_scan = Some(Deadline::after_from(now, SCAN_INTERVAL));
+            let keys = display.read_keys()?;
+            self.scan(keys, now);
+        }
+        Ok(self.next_event())
+    }
+
+    fn scan(&mut self, keys: Keys, now: Instant) {
+        if keys != self.raw {
+            self.raw = keys;
+            self.raw_since = now;
+        }
+        if now.duration_since(self.raw_since) >= self.debounce {
+            self.pressed = self.raw;
+        }
+    }
+
+    // Report one of the keys that changed, lowest first
+    fn next_event(&mut self) -> Option<KeyEvent> {
+        let key = self.pressed.changes(&self.reported).first()?;
+        let row = key.row as usize;
+        debug_assert!(row < KEY_ROWS);
+        self.reported.0[row] ^= 1 << key.column;
+        Some(if self.pressed.is_pressed(key) {
+            KeyEvent::Pressed(key)
+        } else {
+            KeyEvent::Released(key)
+        })
+    }
+}
+
+impl Default for Keypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ht16k33::DEFAULT_ADDRESS;
+    use crate::mock::MockI2c;
+
+    // Key data RAM with the given keys down on KS0
+    fn ks0(bits: u16) -> [u8; 6] {
+        let [low, high] = bits.to_le_bytes();
+        [low, high, 0, 0, 0, 0]
+    }
+
+    #[test]
+    fn events_after_debounce() {
+        let i2c = MockI2c::new();
+        let mut display = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        let mut keypad = Keypad::new();
+        let mut update = |keypad: &mut Keypad, ms| {
+            keypad
+                .update(&mut display, Instant::from_millis(ms))
+                .unwrap()
+        };
+
+        // A blip that's gone on the next scan doesn't count
+        for bits in [0b1, 0, 0] {
+            i2c.queue_read(&ks0(bits));
+        }
+        assert_eq!(update(&mut keypad, 0), None);
+        assert_eq!(update(&mut keypad, 5), None);
+        assert_eq!(update(&mut keypad, 10), None);
+        assert_eq!(update(&mut keypad, 20), None);
+
+        // Two keys held for 20 ms, reported one by one
+        for bits in [0b101, 0b101, 0b101] {
+            i2c.queue_read(&ks0(bits));
+        }
+        assert_eq!(update(&mut keypad, 30), None);
+        assert_eq!(update(&mut keypad, 40), None);
+        assert_eq!(
+            update(&mut keypad, 50),
+            Some(KeyEvent::Pressed(Key::new(0, 0)))
+        );
+        assert_eq!(
+            update(&mut keypad, 51),
+            Some(KeyEvent::Pressed(Key::new(0, 2)))
+        );
+        assert_eq!(update(&mut keypad, 52), None);
+        assert!(keypad.pressed().is_pressed(Key::new(0, 2)));
+
+        for bits in [0b001, 0b001, 0b001] {
+            i2c.queue_read(&ks0(bits));
+        }
+        assert_eq!(update(&mut keypad, 60), None);
+        assert_eq!(update(&mut keypad, 70), None);
+        assert_eq!(
+            update(&mut keypad, 80),
+            Some(KeyEvent::Released(Key::new(0, 2)))
+        );
+    }
+
+    #[test]
+    fn bus_errors_are_reported() {
+        let i2c = MockI2c::new();
+        i2c.set_fail(true);
+        let mut display = Ht16k33::new(i2c, DEFAULT_ADDRESS);
+        let result = Keypad::new().update(&mut display, Instant::from_millis(0));
+        assert!(matches!(result, Err(Error::I2c(_))));
+    }
+}
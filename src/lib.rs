@@ -0,0 +1,48 @@
+//! Shared drivers for the sensor kit examples.
+//!
+//! Everything in here is written against the `embedded-hal` traits so the
+//! examples can share one implementation instead of copying register
+//! definitions around. Only code behind `cfg(target_arch = "avr")` touches
+//! `arduino-hal` directly; the rest also builds on the host, where the unit
+//! tests run against the mocks in `mock.rs` (`cargo test-host`).
+
+#![cfg_attr(not(test), no_std)]
+// `lpm` for reading program memory, see `progmem`
+#![cfg_attr(target_arch = "avr", feature(asm_experimental_arch))]
+
+pub mod analog;
+pub mod animation;
+pub mod animations;
+pub mod bitmaps;
+pub mod button;
+pub mod clock;
+pub mod cursor;
+pub mod font;
+pub mod gray;
+pub mod ht16k33;
+pub mod int0;
+pub mod joystick;
+pub mod keypad;
+pub mod marquee;
+pub mod matrix;
+pub mod matrix_chain;
+pub mod melody;
+pub mod paint;
+pub mod progmem;
+pub mod pwm;
+pub mod rgb;
+pub mod rtttl;
+pub mod scheduler;
+pub mod settings;
+pub mod snake;
+pub mod tone;
+pub mod traffic_light;
+
+#[cfg(test)]
+mod mock;
+
+// Runs the asset parser tests in `build.rs` along with the library's
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "../build.rs"]
+mod build_script;
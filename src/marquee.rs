@@ -0,0 +1,296 @@
+//! Scrolling Text
+//!
+//! [`Marquee`] scrolls a line of text in the [`crate::font`] across the
+//! matrix (or any other `embedded-graphics` [`DrawTarget`]). The text enters
+//! at one edge, scrolls one column per step until it has left the other
+//! edge, and then starts over - or stops, with looping turned off.
+//!
+//! The text lives in a fixed buffer of `N` bytes. [`Marquee::set_text`]
+//! replaces it, and the marquee is also a [`ufmt::uWrite`], so readings can
+//! be formatted straight into it with `uwrite!` (`ufmt` keeps `core::fmt`
+//! out of the firmware image).
+//!
+//! Like the other drivers the marquee never blocks: [`Marquee::update`]
+//! advances the scroll by however many steps are due and
+//! [`Marquee::draw`] renders the current position.
+//!
+//! ## Example
+//! ```ignore
+//! let mut marquee: Marquee<16> = Marquee::new(matrix::SIZE);
+//! uwrite!(marquee, "{}.{}C", tenths / 10, tenths % 10).ok();
+//! loop {
+//!     if marquee.update(Instant::now()) {
+//!         let mut frame = Matrix8x8::new();
+//!         marquee.draw(&mut frame).unwrap();
+//!         frame.flush(&mut ht16k33).unwrap();
+//!     }
+//! }
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+
+use crate::clock::{Duration, Instant};
+use crate::font;
+
+/// Default time per scroll step
+pub const DEFAULT_SPEED: Duration = Duration::from_millis(80);
+
+/// Which way the text moves
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScrollDirection {
+    /// Enters at the right edge, like reading
+    #[default]
+    Left,
+    /// Enters at the left edge
+    Right,
+}
+
+/// A line of scrolling text, up to `N` bytes long
+pub struct Marquee<const N: usize> {
+    text: [u8; N],
+    len: usize,
+    text_width: u32,
+    width: u32,
+    speed: Duration,
+    direction: ScrollDirection,
+    looping: bool,
+    // Columns scrolled since the text started entering
+    offset: u32,
+    last_step: Option<Instant>,
+}
+
+impl<const N: usize> Marquee<N> {
+    /// An empty marquee for a display `width` pixels wide.
+    pub fn new(width: u32) -> Self {
+        Self {
+            text: [0; N],
+            len: 0,
+            text_width: 0,
+            width,
+            speed: DEFAULT_SPEED,
+            direction: ScrollDirection::Left,
+            looping: true,
+            offset: 0,
+            last_step: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        // Only whole `&str`s are ever copied in
+        core::str::from_utf8(&self.text[..self.len]).unwrap_or("")
+    }
+
+    /// Replace the text and scroll it in from the start. Text that doesn't
+    /// fit in `N` bytes is cut off.
+    pub fn set_text(&mut self, text: &str) {
+        self.clear();
+        self.push_str(text);
+    }
+
+    /// Remove the text and restart; [`ufmt::uWrite`] appends from here.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.text_width = 0;
+        self.restart();
+    }
+
+    /// Time per one-column scroll step.
+    pub fn set_speed(&mut self, speed: Duration) {
+        self.speed = speed;
+    }
+
+    pub fn direction(&self) -> ScrollDirection {
+        self.direction
+    }
+
+    pub fn set_direction(&mut self, direction: ScrollDirection) {
+        self.direction = direction;
+    }
+
+    /// Start over once the text has scrolled out (the default), or stop.
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    /// Scroll the text in from the start again.
+    pub fn restart(&mut self) {
+        self.offset = 0;
+        self.last_step = None;
+    }
+
+    /// `true` once a non-looping marquee has scrolled its text out.
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.offset >= self.cycle()
+    }
+
+    /// Advance by the steps due at `now`. Returns `true` if the text moved,
+    /// i.e. the display needs to be redrawn.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let Some(last_step) = self.last_step else {
+            // The first call shows the start position
+            self.last_step = Some(now);
+            return true;
+        };
+        let speed = self.speed.as_millis().max(1);
+        let steps = now.duration_since(last_step).as_millis() / speed;
+        if steps == 0 || self.is_finished() {
+            return false;
+        }
+        self.last_step = Some(last_step + Duration::from_millis(steps * speed));
+        let offset = self.offset + steps;
+        self.offset = if self.looping {
+            offset % self.cycle()
+        } else {
+            offset.min(self.cycle())
+        };
+        true
+    }
+
+    /// Draw the text at its current position. Only lit pixels are drawn, so
+    /// clear the target first.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let mut x = match self.direction {
+            ScrollDirection::Left => self.width as i32 - self.offset as i32,
+            ScrollDirection::Right => self.offset as i32 - self.text_width as i32,
+        };
+        for c in self.text().chars() {
+            let glyph = font::glyph(c);
+            let width = glyph.width() as i32;
+            if x + width > 0 && x < self.width as i32 {
+                target.draw_iter((0..glyph.width()).flat_map(|column| {
+                    let bits = glyph.column(column);
+                    (0..font::HEIGHT as i32)
+                        .filter(move |&row| bits & 1 << row != 0)
+                        .map(move |row| Pixel(Point::new(x + column as i32, row), BinaryColor::On))
+                }))?;
+            }
+            x += width + font::SPACING as i32;
+        }
+        Ok(())
+    }
+
+    fn push_str(&mut self, text: &str) {
+        for c in text.chars() {
+            let end = self.len + c.len_utf8();
+            if end > N {
+                break;
+            }
+            c.encode_utf8(&mut self.text[self.len..end]);
+            if self.len > 0 {
+                self.text_width += font::SPACING as u32;
+            }
+            self.text_width += font::glyph(c).width() as u32;
+            self.len = end;
+        }
+    }
+
+    // Columns from entering at one edge to having left the other
+    fn cycle(&self) -> u32 {
+        (self.width + self.text_width).max(1)
+    }
+}
+
+impl<const N: usize> ufmt::uWrite for Marquee<N> {
+    type Error = Infallible;
+
+    fn write_str(&mut self, text: &str) -> Result<(), Infallible> {
+        self.push_str(text);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matrix::{Matrix8x8, SIZE};
+
+    fn frame<const N: usize>(marquee: &Marquee<N>) -> [u8; 8] {
+        let mut frame = Matrix8x8::new();
+        marquee.draw(&mut frame).unwrap();
+        *frame.rows()
+    }
+
+    fn at(ms: u32) -> Instant {
+        Instant::from_millis(ms)
+    }
+
+    #[test]
+    fn scrolls_in_from_the_right() {
+        let mut marquee: Marquee<8> = Marquee::new(SIZE);
+        marquee.set_text("I");
+        assert!(marquee.update(at(0)));
+        assert_eq!(frame(&marquee), [0; 8]);
+
+        // 'I' is 3 columns wide: 0x41, 0x7F, 0x41
+        assert!(!marquee.update(at(79)));
+        assert!(marquee.update(at(80)));
+        assert_eq!(frame(&marquee)[0], 0b1000_0000);
+        assert!(marquee.update(at(240)));
+        assert_eq!(frame(&marquee)[0], 0b1110_0000);
+        assert_eq!(frame(&marquee)[1], 0b0100_0000);
+    }
+
+    #[test]
+    fn loops_after_leaving_the_other_edge() {
+        let mut marquee: Marquee<8> = Marquee::new(SIZE);
+        marquee.set_text("I");
+        marquee.set_speed(Duration::from_millis(10));
+        marquee.update(at(0));
+        // 8 columns to the left edge, 3 more to be gone
+        marquee.update(at(100));
+        assert_eq!(frame(&marquee), [1, 0, 0, 0, 0, 0, 1, 0]);
+        marquee.update(at(110));
+        assert_eq!(frame(&marquee), [0; 8]);
+        marquee.update(at(120));
+        assert_eq!(frame(&marquee)[0], 0b1000_0000);
+    }
+
+    #[test]
+    fn stops_without_looping() {
+        let mut marquee: Marquee<8> = Marquee::new(SIZE);
+        marquee.set_text("I");
+        marquee.set_looping(false);
+        marquee.update(at(0));
+        assert!(marquee.update(at(10_000)));
+        assert!(marquee.is_finished());
+        assert!(!marquee.update(at(20_000)));
+        assert_eq!(frame(&marquee), [0; 8]);
+
+        marquee.restart();
+        assert!(!marquee.is_finished());
+    }
+
+    #[test]
+    fn scrolls_right() {
+        let mut marquee: Marquee<8> = Marquee::new(SIZE);
+        marquee.set_text("I");
+        marquee.set_direction(ScrollDirection::Right);
+        marquee.update(at(0));
+        marquee.update(at(80));
+        assert_eq!(frame(&marquee)[0], 0b0000_0001);
+    }
+
+    #[test]
+    fn formatted_text_is_cut_to_the_buffer() {
+        let mut marquee: Marquee<4> = Marquee::new(SIZE);
+        let tenths = 235;
+        ufmt::uwrite!(marquee, "{}.{}C", tenths / 10, tenths % 10).unwrap();
+        assert_eq!(marquee.text(), "23.5");
+        marquee.set_text("OK");
+        assert_eq!(marquee.text(), "OK");
+    }
+
+    #[test]
+    fn ufmt_appends_text() {
+        let mut marquee: Marquee<16> = Marquee::new(SIZE);
+        marquee.set_text("Best ");
+        ufmt::uwrite!(marquee, "{}", 1234u16).unwrap();
+        assert_eq!(marquee.text(), "Best 1234");
+    }
+}
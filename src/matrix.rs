@@ -0,0 +1,209 @@
+//! 8x8 Matrix Framebuffer
+//!
+//! [`Matrix8x8`] is an `embedded-graphics` [`DrawTarget`], so the matrix can
+//! be drawn on with the standard primitives, text and images instead of
+//! building row bytes by hand. Drawing only touches the in-memory frame;
+//! [`Matrix8x8::flush`] sends it to the display through the
+//! [`Ht16k33`] driver.
+//!
+//! Pixels outside the 8x8 area are dropped, so shapes may hang over the edge.
+//!
+//! ## Example
+//! ```ignore
+//! let mut frame = Matrix8x8::new();
+//! Circle::new(Point::new(1, 1), 6)
+//!     .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+//!     .draw(&mut frame)?;
+//! frame.flush(&mut ht16k33)?;
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_hal::i2c::I2c;
+
+use crate::ht16k33::{self, Ht16k33, ROWS};
+
+/// Width and height of the matrix in pixels
+pub const SIZE: u32 = 8;
+
+/// One frame for the 8x8 LED matrix
+///
+/// Row `y` holds the pixels of that row, bit `x` being column `x`, the same
+/// layout as [`Ht16k33::set_rows`] and the [`crate::bitmaps`] tables.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Matrix8x8 {
+    rows: [u8; ROWS],
+}
+
+impl Matrix8x8 {
+    /// An empty (all off) frame.
+    pub const fn new() -> Self {
+        Self { rows: [0; ROWS] }
+    }
+
+    /// A frame showing `rows`, e.g. one of the [`crate::bitmaps`].
+    pub const fn from_rows(rows: [u8; ROWS]) -> Self {
+        Self { rows }
+    }
+
+    pub fn rows(&self) -> &[u8; ROWS] {
+        &self.rows
+    }
+
+    pub fn set_rows(&mut self, rows: &[u8; ROWS]) {
+        self.rows = *rows;
+    }
+
+    /// The pixel at `(x, y)`; off outside the matrix.
+    pub fn pixel(&self, x: i32, y: i32) -> bool {
+        Self::contains(x, y) && self.rows[y as usize] & 1 << x != 0
+    }
+
+    /// Set a pixel; coordinates outside the matrix are ignored.
+    pub fn set_pixel(&mut self, x: i32, y: i32, on: bool) {
+        if !Self::contains(x, y) {
+            return;
+        }
+        if on {
+            self.rows[y as usize] |= 1 << x;
+        } else {
+            self.rows[y as usize] &= !(1 << x);
+        }
+    }
+
+    /// Send the frame to the display.
+    pub fn flush<I2C, E>(&self, display: &mut Ht16k33<I2C>) -> Result<(), ht16k33::Error<E>>
+    where
+        I2C: I2c<Error = E>,
+    {
+        display.write_rows(&self.rows)
+    }
+
+    fn contains(x: i32, y: i32) -> bool {
+        (0..SIZE as i32).contains(&x) && (0..SIZE as i32).contains(&y)
+    }
+}
+
+impl From<[u8; ROWS]> for Matrix8x8 {
+    fn from(rows: [u8; ROWS]) -> Self {
+        Self::from_rows(rows)
+    }
+}
+
+impl OriginDimensions for Matrix8x8 {
+    fn size(&self) -> Size {
+        Size::new(SIZE, SIZE)
+    }
+}
+
+impl DrawTarget for Matrix8x8 {
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.set_pixel(point.x, point.y, color.is_on());
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        self.rows = [if color.is_on() { 0xFF } else { 0 }; ROWS];
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmaps;
+    use crate::ht16k33::DEFAULT_ADDRESS;
+    use crate::mock::MockI2c;
+    use embedded_graphics::mono_font::ascii::FONT_4X6;
+    use embedded_graphics::mono_font::MonoTextStyle;
+    use embedded_graphics::primitives::{Line, PrimitiveStyle, Rectangle};
+    use embedded_graphics::text::{Baseline, Text};
+
+    #[test]
+    fn primitives() {
+        let mut frame = Matrix8x8::new();
+        let stroke = PrimitiveStyle::with_stroke(BinaryColor::On, 1);
+        Line::new(Point::new(0, 0), Point::new(7, 7))
+            .into_styled(stroke)
+            .draw(&mut frame)
+            .unwrap();
+        assert_eq!(frame.rows(), &[1, 2, 4, 8, 16, 32, 64, 128]);
+
+        Rectangle::new(Point::new(0, 0), Size::new(8, 8))
+            .into_styled(stroke)
+            .draw(&mut frame)
+            .unwrap();
+        assert_eq!(frame.rows()[0], 0xFF);
+        assert_eq!(frame.rows()[3], 0b1000_1001);
+    }
+
+    #[test]
+    fn pixels_outside_are_dropped() {
+        let mut frame = Matrix8x8::new();
+        Line::new(Point::new(-4, 2), Point::new(11, 2))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut frame)
+            .unwrap();
+        assert_eq!(frame.rows(), &[0, 0, 0xFF, 0, 0, 0, 0, 0]);
+        assert!(!frame.pixel(8, 2));
+        frame.set_pixel(2, 2, false);
+        assert!(!frame.pixel(2, 2));
+        assert!(frame.pixel(3, 2));
+    }
+
+    #[test]
+    fn text() {
+        let mut frame = Matrix8x8::new();
+        let style = MonoTextStyle::new(&FONT_4X6, BinaryColor::On);
+        Text::with_baseline("I", Point::zero(), style, Baseline::Top)
+            .draw(&mut frame)
+            .unwrap();
+        assert!(frame.rows().iter().any(|&row| row != 0));
+        assert!(frame.rows().iter().all(|&row| row < 1 << 4));
+    }
+
+    #[test]
+    fn clear_and_flush() {
+        let i2c = MockI2c::new();
+        let mut display = Ht16k33::new(i2c.clone(), DEFAULT_ADDRESS);
+        let mut frame = Matrix8x8::from(bitmaps::HEART);
+        frame.flush(&mut display).unwrap();
+        assert_eq!(display.buffer()[0], bitmaps::HEART[0]);
+
+        assert_eq!(i2c.writes().len(), 1);
+
+        // Rows 1-3 of the heart are already on: only rows 0 and 4-7 are
+        // sent, as two runs
+        frame.clear(BinaryColor::On).unwrap();
+        frame.flush(&mut display).unwrap();
+        assert_eq!(
+            i2c.writes()[1..],
+            [
+                (DEFAULT_ADDRESS, vec![0x00, 0xFF]),
+                (DEFAULT_ADDRESS, vec![0x08, 0xFF, 0, 0xFF, 0, 0xFF, 0, 0xFF]),
+            ]
+        );
+
+        // Every row changes: one run from RAM address 0, without the unused
+        // byte after the last row
+        frame.clear(BinaryColor::Off).unwrap();
+        frame.flush(&mut display).unwrap();
+        assert_eq!(
+            i2c.writes()[3..],
+            [(
+                DEFAULT_ADDRESS,
+                vec![0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+            )]
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Chained LED Matrices
+//!
+//! [`MatrixChain`] tiles up to eight 8x8 matrix modules on the same I2C bus
+//! into one wider `embedded-graphics` [`DrawTarget`], module 0 on the left.
+//! Four modules make a 32x8 canvas, wide enough for a few characters of
+//! [`crate::marquee::Marquee`] text at once.
+//!
+//! Each module needs its own address, set with the solder jumpers on the
+//! backpack; by default module `n` is expected at
+//! [`ht16k33::DEFAULT_ADDRESS`] + `n` (0x70-0x77). Every module has its own
+//! brightness and [`Orientation`].
+//!
+//! Drawing only changes the framebuffer. [`MatrixChain::flush`] sends just
+//! the modules whose picture differs from what they show, so a scroll that
+//! only touches one module costs one I2C write instead of four, and of those
+//! only the changed bytes, like [`Ht16k33::write_display`].
+//!
+//! ## Example
+//! ```ignore
+//! let mut chain: MatrixChain<_, 4> = MatrixChain::new(i2c);
+//! chain.init()?;
+//! chain.clear(BinaryColor::Off)?;
+//! marquee.draw(&mut chain)?;
+//! chain.flush()?;
+//! ```
+
+use core::convert::Infallible;
+
+use embedded_graphics::pixelcolor::BinaryColor;
+use embedded_graphics::prelude::*;
+use embedded_hal::i2c::I2c;
+
+use crate::ht16k33::{self, Error, Ht16k33, Orientation, MAX_BRIGHTNESS, ROWS};
+use crate::matrix::{Matrix8x8, SIZE};
+
+/// Most modules on one bus (the HT16K33 has three address jumpers)
+pub const MAX_MODULES: usize = 8;
+
+#[derive(Clone, Copy)]
+struct Module {
+    address: u8,
+    orientation: Orientation,
+    brightness: u8,
+    // What the module shows since the last write, `None` if it needs
+    // to be written whatever the framebuffer holds
+    shown: Option<[u8; ROWS]>,
+    // Its display RAM, see `Ht16k33::shown`
+    ram: Option<[u8; ROWS * 2]>,
+}
+
+/// `N` matrix modules side by side as one `8N`x8 display
+pub struct MatrixChain<I2C, const N: usize> {
+    i2c: I2C,
+    modules: [Module; N],
+    tiles: [Matrix8x8; N],
+}
+
+impl<I2C, E, const N: usize> MatrixChain<I2C, N>
+where
+    I2C: I2c<Error = E>,
+{
+    const FITS_ON_BUS: () = assert!(N > 0 && N <= MAX_MODULES, "1 to 8 modules");
+
+    /// A chain with module `n` at address 0x70 + `n`. Nothing is sent until
+    /// [`init`](Self::init).
+    pub fn new(i2c: I2C) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_ON_BUS;
+        let mut modules = [Module {
+            address: ht16k33::DEFAULT_ADDRESS,
+            orientation: Orientation::new(),
+            brightness: MAX_BRIGHTNESS,
+            shown: None,
+            ram: None,
+        }; N];
+        for (n, module) in modules.iter_mut().enumerate() {
+            module.address += n as u8;
+        }
+        Self {
+            i2c,
+            modules,
+            tiles: [Matrix8x8::new(); N],
+        }
+    }
+
+    /// Use other addresses, left to right.
+    pub fn with_addresses(mut self, addresses: [u8; N]) -> Self {
+        for (module, address) in self.modules.iter_mut().zip(addresses) {
+            module.address = address;
+        }
+        self
+    }
+
+    /// The same orientation for every module.
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        for module in self.modules.iter_mut() {
+            module.orientation = orientation;
+        }
+        self
+    }
+
+    /// Change the orientation of one module; it is redrawn with the next
+    /// [`flush`](Self::flush).
+    pub fn set_orientation(&mut self, module: usize, orientation: Orientation) {
+        if let Some(module) = self.modules.get_mut(module) {
+            module.orientation = orientation;
+            module.shown = None;
+        }
+    }
+
+    /// Set up every module, blank and at its brightness.
+    pub fn init(&mut self) -> Result<(), Error<E>> {
+        for n in 0..N {
+            let brightness = self.modules[n].brightness;
+            let mut display = self.display(n);
+            display.init()?;
+            display.set_brightness(brightness)?;
+            self.modules[n].ram = display.shown();
+            self.modules[n].shown = Some([0; ROWS]);
+        }
+        Ok(())
+    }
+
+    /// Width of the whole chain in pixels.
+    pub fn width(&self) -> u32 {
+        N as u32 * SIZE
+    }
+
+    /// Brightness of one module, 0 to [`MAX_BRIGHTNESS`].
+    pub fn brightness(&self, module: usize) -> Option<u8> {
+        self.modules.get(module).map(|module| module.brightness)
+    }
+
+    /// Set the brightness of one module right away.
+    pub fn set_module_brightness(&mut self, module: usize, level: u8) -> Result<(), Error<E>> {
+        if module >= N {
+            return Err(Error::OutOfBounds);
+        }
+        self.display(module).set_brightness(level)?;
+        self.modules[module].brightness = level;
+        Ok(())
+    }
+
+    /// Set the brightness of every module.
+    pub fn set_brightness(&mut self, level: u8) -> Result<(), Error<E>> {
+        for module in 0..N {
+            self.set_module_brightness(module, level)?;
+        }
+        Ok(())
+    }
+
+    /// The picture of one module.
+    pub fn tile(&self, module: usize) -> Option<&Matrix8x8> {
+        self.tiles.get(module)
+    }
+
+    /// Replace the picture of one module, e.g. with an animation frame.
+    pub fn set_tile(&mut self, module: usize, frame: &Matrix8x8) {
+        if let Some(tile) = self.tiles.get_mut(module) {
+            *tile = *frame;
+        }
+    }
+
+    /// The pixel at `(x, y)` of the whole chain; off outside of it.
+    pub fn pixel(&self, x: i32, y: i32) -> bool {
+        match self.locate(x) {
+            Some((module, x)) => self.tiles[module].pixel(x, y),
+            None => false,
+        }
+    }
+
+    /// Set a pixel; coordinates outside the chain are ignored.
+    pub fn set_pixel(&mut self, x: i32, y: i32, on: bool) {
+        if let Some((module, x)) = self.locate(x) {
+            self.tiles[module].set_pixel(x, y, on);
+        }
+    }
+
+    /// `true` if any module shows something else than the framebuffer.
+    pub fn is_dirty(&self) -> bool {
+        (0..N).any(|module| self.is_module_dirty(module))
+    }
+
+    /// Send the modules whose picture changed since their last write.
+    pub fn flush(&mut self) -> Result<(), Error<E>> {
+        for module in 0..N {
+            if self.is_module_dirty(module) {
+                self.write_module(module)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send every module, e.g. after a module was reconnected.
+    pub fn flush_all(&mut self) -> Result<(), Error<E>> {
+        for module in 0..N {
+            self.modules[module].ram = None;
+            self.write_module(module)?;
+        }
+        Ok(())
+    }
+
+    /// Give back the I2C bus.
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+
+    fn is_module_dirty(&self, module: usize) -> bool {
+        self.modules[module].shown != Some(*self.tiles[module].rows())
+    }
+
+    fn write_module(&mut self, module: usize) -> Result<(), Error<E>> {
+        let rows = *self.tiles[module].rows();
+        let mut display = self.display(module);
+        let result = display.write_rows(&rows);
+        // Unknown again after a failed write
+        self.modules[module].ram = display.shown();
+        result?;
+        self.modules[module].shown = Some(rows);
+        Ok(())
+    }
+
+    // A driver for one module, borrowing the shared bus
+    fn display(&mut self, module: usize) -> Ht16k33<&mut I2C> {
+        let Module {
+            address,
+            orientation,
+            ram,
+            ..
+        } = self.modules[module];
+        Ht16k33::new(&mut self.i2c, address)
+            .with_orientation(orientation)
+            .with_shown(ram)
+    }
+
+    // Module and its column for column `x` of the chain
+    fn locate(&self, x: i32) -> Option<(usize, i32)> {
+        if !(0..self.width() as i32).contains(&x) {
+            return None;
+        }
+        Some((x as usize / SIZE as usize, x % SIZE as i32))
+    }
+}
+
+impl<I2C, E, const N: usize> OriginDimensions for MatrixChain<I2C, N>
+where
+    I2C: I2c<Error = E>,
+{
+    fn size(&self) -> Size {
+        Size::new(self.width(), SIZE)
+    }
+}
+
+impl<I2C, E, const N: usize> DrawTarget for MatrixChain<I2C, N>
+where
+    I2C: I2c<Error = E>,
+{
+    type Color = BinaryColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            self.set_pixel(point.x, point.y, color.is_on());
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        for tile in self.tiles.iter_mut() {
+            tile.clear(color)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ht16k33::Rotation;
+    use crate::mock::MockI2c;
+    use embedded_graphics::primitives::{Line, PrimitiveStyle};
+
+    fn chain(i2c: &MockI2c) -> MatrixChain<MockI2c, 4> {
+        let mut chain = MatrixChain::new(i2c.clone());
+        chain.init().unwrap();
+        chain
+    }
+
+    // Addresses of the display RAM writes after `init`
+    fn ram_writes(i2c: &MockI2c) -> Vec<u8> {
+        i2c.writes()
+            .into_iter()
+            .skip(4 * 5)
+            .filter(|(_, bytes)| bytes.len() > 1)
+            .map(|(address, _)| address)
+            .collect()
+    }
+
+    #[test]
+    fn init_sets_up_every_module() {
+        let i2c = MockI2c::new();
+        let chain = chain(&i2c);
+        assert_eq!(chain.width(), 32);
+        assert_eq!(chain.size(), Size::new(32, 8));
+        let addresses: Vec<_> = i2c.writes().iter().map(|(address, _)| *address).collect();
+        assert_eq!(addresses.len(), 4 * 5);
+        assert_eq!(addresses[0], 0x70);
+        assert_eq!(addresses[19], 0x73);
+        assert!(!chain.is_dirty());
+    }
+
+    #[test]
+    fn drawing_spans_modules_and_flushes_only_changes() {
+        let i2c = MockI2c::new();
+        let mut chain = chain(&i2c);
+        Line::new(Point::new(6, 0), Point::new(9, 0))
+            .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 1))
+            .draw(&mut chain)
+            .unwrap();
+        assert_eq!(chain.tile(0).unwrap().rows()[0], 0b1100_0000);
+        assert_eq!(chain.tile(1).unwrap().rows()[0], 0b0000_0011);
+        assert!(chain.pixel(9, 0));
+        assert!(!chain.pixel(40, 0));
+
+        chain.flush().unwrap();
+        assert_eq!(ram_writes(&i2c), [0x70, 0x71]);
+        assert!(!chain.is_dirty());
+
+        // Redrawing the same picture sends nothing
+        chain.clear(BinaryColor::Off).unwrap();
+        chain.set_pixel(6, 0, true);
+        chain.set_pixel(7, 0, true);
+        chain.set_pixel(8, 0, true);
+        chain.set_pixel(9, 0, true);
+        chain.flush().unwrap();
+        assert_eq!(ram_writes(&i2c), [0x70, 0x71]);
+
+        chain.set_tile(3, &Matrix8x8::from_rows([0xFF; ROWS]));
+        chain.flush().unwrap();
+        assert_eq!(ram_writes(&i2c), [0x70, 0x71, 0x73]);
+        chain.flush_all().unwrap();
+        assert_eq!(ram_writes(&i2c).len(), 7);
+    }
+
+    #[test]
+    fn modules_have_their_own_settings() {
+        let i2c = MockI2c::new();
+        let mut chain: MatrixChain<_, 2> =
+            MatrixChain::new(i2c.clone()).with_addresses([0x74, 0x72]);
+        chain.set_orientation(1, Orientation::new().with_rotation(Rotation::Half));
+        chain.init().unwrap();
+
+        chain.set_module_brightness(1, 3).unwrap();
+        assert_eq!(chain.brightness(1), Some(3));
+        assert_eq!(chain.brightness(0), Some(MAX_BRIGHTNESS));
+        assert_eq!(i2c.writes().last().unwrap(), &(0x72, vec![0xE3]));
+        assert_eq!(chain.set_module_brightness(2, 3), Err(Error::OutOfBounds));
+
+        // Module 1 is upside down: only its last row is sent
+        chain.set_pixel(8, 0, true);
+        chain.flush().unwrap();
+        assert_eq!(i2c.writes().last().unwrap(), &(0x72, vec![0x0E, 0b1000_0000]));
+
+        // Turning it back redraws the same picture the other way up
+        chain.set_orientation(1, Orientation::new());
+        assert!(chain.is_dirty());
+        let sent = i2c.writes().len();
+        chain.flush().unwrap();
+        assert_eq!(
+            i2c.writes()[sent..],
+            [(0x72, vec![0x00, 0b0000_0001]), (0x72, vec![0x0E, 0])]
+        );
+        assert!(!chain.is_dirty());
+    }
+}
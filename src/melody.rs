@@ -0,0 +1,42 @@
+//! Melodies for the Buzzer
+//!
+//! The melodies are generated at build time from the RTTTL (`.rtttl`) and ABC
+//! (`.abc`) files in `assets/melodies/`; each file becomes a `&[Note]`
+//! constant named after it (`mario.rtttl` -> [`MARIO`]). Add a file there to
+//! add a song. Play them with [`crate::tone::Player`]:
+//!
+//! ```ignore
+//! let mut player = Player::new(melody::MARIO.iter().copied());
+//! ```
+
+use crate::tone::Note;
+
+include!(concat!(env!("OUT_DIR"), "/melodies.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rtttl_assets() {
+        assert_eq!(MARIO[0], Note::new(659, 150));
+        assert_eq!(MARIO[2], Note::rest(150));
+        assert_eq!(IMPERIAL_MARCH[3], Note::new(311, 375));
+    }
+
+    #[test]
+    fn abc_assets() {
+        // L:1/4 at Q:1/4=120, starting C C G G
+        assert_eq!(TWINKLE[0], Note::new(262, 500));
+        assert_eq!(TWINKLE[2], Note::new(392, 500));
+        // `G2` in the second bar
+        assert_eq!(TWINKLE[6], Note::new(392, 1000));
+        assert!(TWINKLE.last().unwrap().is_rest());
+    }
+
+    #[test]
+    fn all_is_sorted_by_file_name() {
+        let names: Vec<_> = ALL.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["happy_birthday", "imperial_march", "mario", "twinkle"]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Mock Peripherals for Host-Side Tests
+//!
+//! Stand-ins for the `embedded-hal` traits (and the ADC) so driver logic can
+//! be tested with `cargo test-host` without a board attached. Every mock is
+//! cheap to clone and clones share state, so a test can hand one copy to the
+//! code under test and keep another to drive inputs and inspect outputs.
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use embedded_hal::digital::{self, InputPin, OutputPin, StatefulOutputPin};
+use embedded_hal::i2c::{self, I2c, NoAcknowledgeSource, Operation};
+use embedded_hal::pwm::{self, SetDutyCycle};
+
+use crate::analog::AnalogRead;
+use crate::settings::Storage;
+
+#[derive(Default)]
+struct I2cState {
+    writes: Vec<(u8, Vec<u8>)>,
+    reads: VecDeque<Vec<u8>>,
+    fail: bool,
+}
+
+/// I2C bus that records every write and answers reads from a queue
+#[derive(Clone, Default)]
+pub struct MockI2c {
+    state: Rc<RefCell<I2cState>>,
+}
+
+impl MockI2c {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All writes so far as `(address, bytes)` pairs.
+    pub fn writes(&self) -> Vec<(u8, Vec<u8>)> {
+        self.state.borrow().writes.clone()
+    }
+
+    /// Queue the bytes the next read returns.
+    pub fn queue_read(&self, bytes: &[u8]) {
+        self.state.borrow_mut().reads.push_back(bytes.to_vec());
+    }
+
+    /// Make every following transaction fail with a NACK.
+    pub fn set_fail(&self, fail: bool) {
+        self.state.borrow_mut().fail = fail;
+    }
+}
+
+impl i2c::ErrorType for MockI2c {
+    type Error = i2c::ErrorKind;
+}
+
+impl I2c for MockI2c {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let mut state = self.state.borrow_mut();
+        if state.fail {
+            return Err(i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => state.writes.push((address, bytes.to_vec())),
+                Operation::Read(buf) => {
+                    let bytes = state.reads.pop_front().expect("unexpected I2C read");
+                    buf.copy_from_slice(&bytes);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Digital pin usable both as an input and an output
+#[derive(Clone, Default)]
+pub struct MockPin {
+    high: Rc<Cell<bool>>,
+}
+
+impl MockPin {
+    pub fn new(high: bool) -> Self {
+        let pin = Self::default();
+        pin.high.set(high);
+        pin
+    }
+
+    /// Drive the pin from the outside, e.g. to press a button.
+    pub fn set(&self, high: bool) {
+        self.high.set(high);
+    }
+
+    /// Current pin level.
+    pub fn level(&self) -> bool {
+        self.high.get()
+    }
+}
+
+impl digital::ErrorType for MockPin {
+    type Error = core::convert::Infallible;
+}
+
+impl InputPin for MockPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.level())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.level())
+    }
+}
+
+impl OutputPin for MockPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.high.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.high.set(true);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for MockPin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.level())
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.level())
+    }
+}
+
+/// Analog input returning whatever value the test set last
+#[derive(Clone, Default)]
+pub struct MockAnalog {
+    value: Rc<Cell<u16>>,
+}
+
+impl MockAnalog {
+    pub fn new(value: u16) -> Self {
+        let analog = Self::default();
+        analog.set(value);
+        analog
+    }
+
+    pub fn set(&self, value: u16) {
+        self.value.set(value);
+    }
+}
+
+impl AnalogRead<()> for MockAnalog {
+    fn analog_read(&mut self, _adc: &mut ()) -> u16 {
+        self.value.get()
+    }
+}
+
+/// PWM output with a maximum duty of 255 that remembers the last duty
+#[derive(Clone, Default)]
+pub struct MockPwm {
+    duty: Rc<Cell<u16>>,
+}
+
+impl MockPwm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn duty(&self) -> u16 {
+        self.duty.get()
+    }
+}
+
+impl pwm::ErrorType for MockPwm {
+    type Error = core::convert::Infallible;
+}
+
+impl SetDutyCycle for MockPwm {
+    fn max_duty_cycle(&self) -> u16 {
+        255
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.duty.set(duty);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct EepromState {
+    bytes: Vec<u8>,
+    write_counts: Vec<u32>,
+}
+
+/// EEPROM that starts erased (all `0xFF`) and counts writes per byte
+#[derive(Clone, Default)]
+pub struct MockEeprom {
+    state: Rc<RefCell<EepromState>>,
+}
+
+impl MockEeprom {
+    pub fn new(capacity: usize) -> Self {
+        let eeprom = Self::default();
+        *eeprom.state.borrow_mut() = EepromState {
+            bytes: vec![0xFF; capacity],
+            write_counts: vec![0; capacity],
+        };
+        eeprom
+    }
+
+    /// How often the byte at `offset` was written.
+    pub fn write_count(&self, offset: u16) -> u32 {
+        self.state.borrow().write_counts[offset as usize]
+    }
+
+    /// Change a byte behind the driver's back.
+    pub fn poke(&self, offset: u16, value: u8) {
+        self.state.borrow_mut().bytes[offset as usize] = value;
+    }
+
+    /// Flip the bits of a byte, like an interrupted write.
+    pub fn corrupt(&self, offset: u16) {
+        self.state.borrow_mut().bytes[offset as usize] ^= 0xFF;
+    }
+}
+
+impl Storage for MockEeprom {
+    fn read(&mut self, offset: u16, buf: &mut [u8]) {
+        let state = self.state.borrow();
+        let start = offset as usize;
+        buf.copy_from_slice(&state.bytes[start..start + buf.len()]);
+    }
+
+    fn write(&mut self, offset: u16, data: &[u8]) {
+        let mut state = self.state.borrow_mut();
+        let start = offset as usize;
+        state.bytes[start..start + data.len()].copy_from_slice(data);
+        for count in &mut state.write_counts[start..start + data.len()] {
+            *count += 1;
+        }
+    }
+}
@@ -0,0 +1,435 @@
+//! Paint Tool
+//!
+//! The pieces of a small paint program for the 8x8 matrix:
+//!
+//! - [`Paint`] holds the canvas and paints on it with the selected [`Tool`]
+//!   while the pen is down. Every stroke can be taken back with
+//!   [`Paint::undo`]; the last `N` canvases are kept in an [`UndoRing`].
+//! - [`CanvasStore`] keeps up to [`CANVAS_SLOTS`] drawings in the EEPROM,
+//!   right after the [`crate::settings`] region.
+//! - [`write_ascii`] and [`write_hex`] print a drawing, e.g. over serial.
+//!   The ASCII art is the format of `assets/bitmaps/`, so a drawing can be
+//!   pasted there to become one of the [`crate::bitmaps`].
+//!
+//! ## Example
+//! ```ignore
+//! let mut paint: Paint<8> = Paint::new();
+//! paint.pen_down();
+//! paint.paint(3, 4);
+//! paint.pen_up();
+//! store.save(0, paint.canvas());
+//! write_ascii(&mut serial, paint.canvas())?;
+//! ```
+
+use ufmt::uWrite;
+
+use crate::ht16k33::ROWS;
+use crate::matrix::Matrix8x8;
+use crate::settings::{self, crc16, Storage};
+
+/// First EEPROM byte of the canvases, right after the settings
+pub const CANVAS_OFFSET: u16 = settings::REGION_LEN;
+
+/// Number of canvases the EEPROM keeps
+pub const CANVAS_SLOTS: u8 = 8;
+
+/// EEPROM bytes used by the canvases, starting at [`CANVAS_OFFSET`]
+pub const CANVAS_REGION_LEN: u16 = CANVAS_SLOTS as u16 * RECORD_LEN as u16;
+
+// Magic byte, the rows and a CRC-16 of both
+const RECORD_LEN: usize = 1 + ROWS + 2;
+const MAGIC: u8 = 0x5A;
+
+/// What painting does to a pixel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    /// Turn pixels on
+    #[default]
+    Pen,
+    /// Turn pixels off
+    Eraser,
+    /// Flip pixels, once per visit
+    Toggle,
+}
+
+impl Tool {
+    /// The next tool, in the order above.
+    pub fn next(self) -> Self {
+        match self {
+            Tool::Pen => Tool::Eraser,
+            Tool::Eraser => Tool::Toggle,
+            Tool::Toggle => Tool::Pen,
+        }
+    }
+}
+
+/// The last `N` values pushed; older ones are dropped
+pub struct UndoRing<T, const N: usize> {
+    items: [T; N],
+    // Index the next push goes to
+    next: usize,
+    len: usize,
+}
+
+impl<T: Copy + Default, const N: usize> UndoRing<T, N> {
+    pub fn new() -> Self {
+        Self {
+            items: [T::default(); N],
+            next: 0,
+            len: 0,
+        }
+    }
+
+    /// Add a value, dropping the oldest one when full.
+    pub fn push(&mut self, item: T) {
+        if N == 0 {
+            return;
+        }
+        self.items[self.next] = item;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// Take the newest value.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.next = (self.next + N - 1) % N;
+        self.len -= 1;
+        Some(self.items[self.next])
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<T: Copy + Default, const N: usize> Default for UndoRing<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A canvas with tools and an undo history of `N` steps
+pub struct Paint<const N: usize> {
+    canvas: Matrix8x8,
+    tool: Tool,
+    history: UndoRing<Matrix8x8, N>,
+    drawing: bool,
+    // Last pixel painted in this stroke
+    last: Option<(u8, u8)>,
+}
+
+impl<const N: usize> Paint<N> {
+    /// An empty canvas with the [`Tool::Pen`], pen up.
+    pub fn new() -> Self {
+        Self {
+            canvas: Matrix8x8::new(),
+            tool: Tool::default(),
+            history: UndoRing::new(),
+            drawing: false,
+            last: None,
+        }
+    }
+
+    pub fn canvas(&self) -> &Matrix8x8 {
+        &self.canvas
+    }
+
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+    }
+
+    /// `true` between [`pen_down`](Self::pen_down) and [`pen_up`](Self::pen_up).
+    pub fn is_drawing(&self) -> bool {
+        self.drawing
+    }
+
+    /// Start a stroke; one [`undo`](Self::undo) takes back all of it.
+    pub fn pen_down(&mut self) {
+        if !self.drawing {
+            self.history.push(self.canvas);
+            self.drawing = true;
+            self.last = None;
+        }
+    }
+
+    pub fn pen_up(&mut self) {
+        self.drawing = false;
+    }
+
+    /// Use the tool on a pixel while the pen is down. Staying on the same
+    /// pixel only counts once. Returns `true` if the canvas changed.
+    pub fn paint(&mut self, x: u8, y: u8) -> bool {
+        if !self.drawing || self.last == Some((x, y)) {
+            return false;
+        }
+        self.last = Some((x, y));
+        let (x, y) = (x as i32, y as i32);
+        let on = match self.tool {
+            Tool::Pen => true,
+            Tool::Eraser => false,
+            Tool::Toggle => !self.canvas.pixel(x, y),
+        };
+        let before = self.canvas;
+        self.canvas.set_pixel(x, y, on);
+        self.canvas != before
+    }
+
+    /// Replace the canvas (undoable), e.g. with a loaded drawing.
+    pub fn set_canvas(&mut self, canvas: Matrix8x8) {
+        self.history.push(self.canvas);
+        self.canvas = canvas;
+        self.last = None;
+    }
+
+    /// Clear the canvas (undoable).
+    pub fn clear(&mut self) {
+        self.set_canvas(Matrix8x8::new());
+    }
+
+    /// Go back to the canvas before the last stroke, clear or load. Returns
+    /// `false` when there is nothing left to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.history.pop() {
+            Some(canvas) => {
+                self.canvas = canvas;
+                self.last = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Steps that can be undone.
+    pub fn undo_steps(&self) -> usize {
+        self.history.len()
+    }
+}
+
+impl<const N: usize> Default for Paint<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Saves and loads canvases in the EEPROM
+pub struct CanvasStore<S> {
+    storage: S,
+}
+
+impl<S: Storage> CanvasStore<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// Save a canvas in `slot` (0 to [`CANVAS_SLOTS`] - 1), unless it's
+    /// already stored there. Returns `false` for a slot that doesn't exist.
+    pub fn save(&mut self, slot: u8, canvas: &Matrix8x8) -> bool {
+        if slot >= CANVAS_SLOTS {
+            return false;
+        }
+        if self.load(slot) == Some(*canvas) {
+            return true;
+        }
+        let mut record = [0u8; RECORD_LEN];
+        record[0] = MAGIC;
+        record[1..1 + ROWS].copy_from_slice(canvas.rows());
+        let crc = crc16(&record[..RECORD_LEN - 2]);
+        record[RECORD_LEN - 2..].copy_from_slice(&crc.to_le_bytes());
+        self.storage.write(Self::offset(slot), &record);
+        true
+    }
+
+    /// The canvas in `slot`, `None` if nothing valid is stored there.
+    pub fn load(&mut self, slot: u8) -> Option<Matrix8x8> {
+        if slot >= CANVAS_SLOTS {
+            return None;
+        }
+        let mut record = [0u8; RECORD_LEN];
+        self.storage.read(Self::offset(slot), &mut record);
+        let crc = u16::from_le_bytes([record[RECORD_LEN - 2], record[RECORD_LEN - 1]]);
+        if record[0] != MAGIC || crc16(&record[..RECORD_LEN - 2]) != crc {
+            return None;
+        }
+        let mut rows = [0u8; ROWS];
+        rows.copy_from_slice(&record[1..1 + ROWS]);
+        Some(Matrix8x8::from_rows(rows))
+    }
+
+    /// Give the storage back.
+    pub fn release(self) -> S {
+        self.storage
+    }
+
+    fn offset(slot: u8) -> u16 {
+        CANVAS_OFFSET + slot as u16 * RECORD_LEN as u16
+    }
+}
+
+/// Print a canvas as 8 lines of `#` (on) and `.` (off).
+pub fn write_ascii<W: uWrite + ?Sized>(out: &mut W, canvas: &Matrix8x8) -> Result<(), W::Error> {
+    for y in 0..ROWS as i32 {
+        for x in 0..ROWS as i32 {
+            out.write_char(if canvas.pixel(x, y) { '#' } else { '.' })?;
+        }
+        out.write_str("\r\n")?;
+    }
+    Ok(())
+}
+
+/// Print the rows of a canvas as a Rust array, e.g. `[0x00, 0x66, ...]`.
+pub fn write_hex<W: uWrite + ?Sized>(out: &mut W, canvas: &Matrix8x8) -> Result<(), W::Error> {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+    out.write_char('[')?;
+    for (y, &row) in canvas.rows().iter().enumerate() {
+        if y > 0 {
+            out.write_str(", ")?;
+        }
+        out.write_str("0x")?;
+        out.write_char(DIGITS[(row >> 4) as usize] as char)?;
+        out.write_char(DIGITS[(row & 0x0F) as usize] as char)?;
+    }
+    out.write_str("]\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmaps;
+    use crate::mock::MockEeprom;
+
+    #[test]
+    fn tools() {
+        let mut paint: Paint<4> = Paint::new();
+        assert!(!paint.paint(0, 0));
+        paint.pen_down();
+        assert!(paint.paint(0, 0));
+        assert!(paint.paint(1, 0));
+        paint.pen_up();
+
+        paint.set_tool(Tool::Eraser);
+        paint.pen_down();
+        assert!(paint.paint(0, 0));
+        assert!(!paint.paint(5, 5));
+        paint.pen_up();
+        assert_eq!(paint.canvas().rows()[0], 0b10);
+
+        // Resting on a pixel toggles it once
+        paint.set_tool(Tool::Toggle);
+        paint.pen_down();
+        assert!(paint.paint(1, 0));
+        assert!(!paint.paint(1, 0));
+        assert!(paint.paint(2, 0));
+        assert!(paint.paint(1, 0));
+        assert_eq!(paint.canvas().rows()[0], 0b110);
+        assert_eq!(Tool::Toggle.next(), Tool::Pen);
+    }
+
+    #[test]
+    fn undo_takes_back_whole_strokes() {
+        let mut paint: Paint<2> = Paint::new();
+        for x in 0..3 {
+            paint.pen_down();
+            paint.paint(x, 0);
+            paint.paint(x, 1);
+            paint.pen_up();
+        }
+        assert_eq!(paint.undo_steps(), 2);
+        assert!(paint.undo());
+        assert_eq!(paint.canvas().rows()[..2], [0b11, 0b11]);
+        assert!(paint.undo());
+        assert_eq!(paint.canvas().rows()[..2], [0b1, 0b1]);
+        // The first stroke fell out of the history
+        assert!(!paint.undo());
+
+        paint.clear();
+        assert_eq!(paint.canvas(), &Matrix8x8::new());
+        assert!(paint.undo());
+        assert_eq!(paint.canvas().rows()[0], 0b1);
+    }
+
+    #[test]
+    fn undo_ring_drops_the_oldest() {
+        let mut ring: UndoRing<u8, 3> = UndoRing::new();
+        for item in 1..=5 {
+            ring.push(item);
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.pop(), Some(5));
+        ring.push(6);
+        assert_eq!(ring.pop(), Some(6));
+        assert_eq!(ring.pop(), Some(4));
+        assert_eq!(ring.pop(), Some(3));
+        assert_eq!(ring.pop(), None);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn canvases_survive_a_restart() {
+        let eeprom = MockEeprom::new(1024);
+        let heart = Matrix8x8::from_rows(bitmaps::HEART);
+        let mut store = CanvasStore::new(eeprom.clone());
+        assert_eq!(store.load(0), None);
+        assert!(store.save(7, &heart));
+        assert!(!store.save(CANVAS_SLOTS, &heart));
+        // Nothing written into the settings region
+        assert!((0..CANVAS_OFFSET).all(|offset| eeprom.write_count(offset) == 0));
+
+        let mut store = CanvasStore::new(eeprom.clone());
+        assert_eq!(store.load(7), Some(heart));
+        assert_eq!(store.load(6), None);
+
+        // Saving the same drawing again doesn't wear the EEPROM
+        store.save(7, &heart);
+        assert_eq!(eeprom.write_count(CANVAS_OFFSET + 7 * RECORD_LEN as u16), 1);
+
+        eeprom.corrupt(CANVAS_OFFSET + 7 * RECORD_LEN as u16 + 3);
+        assert_eq!(store.load(7), None);
+    }
+
+    // `ufmt` only writes to a `String` with its `std` feature
+    #[derive(Default)]
+    struct Text(String);
+
+    impl uWrite for Text {
+        type Error = core::convert::Infallible;
+
+        fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
+            self.0.push_str(s);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn exports() {
+        let canvas = Matrix8x8::from_rows([0x81, 0, 0, 0, 0, 0, 0, 0x0F]);
+        let mut ascii = Text::default();
+        write_ascii(&mut ascii, &canvas).unwrap();
+        assert!(ascii.0.ends_with("####....\r\n"));
+        let lines: Vec<_> = ascii.0.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "#......#");
+        assert_eq!(lines[7], "####....");
+
+        let mut hex = Text::default();
+        write_hex(&mut hex, &canvas).unwrap();
+        assert_eq!(
+            hex.0,
+            "[0x81, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0F]\r\n"
+        );
+    }
+}
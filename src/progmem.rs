@@ -0,0 +1,68 @@
+//! Program Memory Tables
+//!
+//! The ATmega328P has 32 KB of flash but only 2 KB of RAM, and a plain
+//! `static` is copied into RAM at startup. Large constant tables (like the
+//! [`crate::font`] glyphs) are kept in flash instead:
+//!
+//! ```ignore
+//! #[cfg_attr(target_arch = "avr", link_section = ".progmem.data")]
+//! static TABLE: [u8; 4] = [1, 2, 3, 4];
+//!
+//! let second = progmem::read_byte(&TABLE[1]);
+//! ```
+//!
+//! Flash is a separate address space on the AVR, so such a table must never
+//! be read directly - only through [`read_byte`], [`read`] and
+//! [`read_value`], which use the `lpm` instruction. Taking references into
+//! it is fine. On the host the attribute does nothing and these are plain
+//! reads, so tables stay testable.
+
+use core::mem::{size_of, MaybeUninit};
+
+/// Read one byte of a table in program memory.
+#[inline]
+pub fn read_byte(byte: &u8) -> u8 {
+    read_ptr(byte)
+}
+
+/// Copy a whole array out of program memory.
+pub fn read<const N: usize>(bytes: &[u8; N]) -> [u8; N] {
+    read_value(bytes)
+}
+
+/// Copy any plain value (without padding, like `#[repr(C)]` structs of
+/// bytes) out of program memory.
+pub fn read_value<T: Copy>(value: &T) -> T {
+    let mut copy = MaybeUninit::<T>::uninit();
+    let src = value as *const T as *const u8;
+    let dst = copy.as_mut_ptr() as *mut u8;
+    for i in 0..size_of::<T>() {
+        // SAFETY: both pointers stay within a `T`
+        unsafe { *dst.add(i) = read_ptr(src.add(i)) };
+    }
+    // SAFETY: every byte has been copied from a valid `T`
+    unsafe { copy.assume_init() }
+}
+
+#[inline]
+fn read_ptr(byte: *const u8) -> u8 {
+    #[cfg(target_arch = "avr")]
+    {
+        let value: u8;
+        // SAFETY: `byte` points into flash, where `lpm` reads from
+        unsafe {
+            core::arch::asm!(
+                "lpm {}, Z",
+                out(reg) value,
+                in("Z") byte,
+                options(pure, readonly, nostack, preserves_flags),
+            );
+        }
+        value
+    }
+    #[cfg(not(target_arch = "avr"))]
+    {
+        // SAFETY: callers only pass pointers into live values
+        unsafe { *byte }
+    }
+}
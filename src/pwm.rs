@@ -0,0 +1,236 @@
+//! Software PWM
+//!
+//! 8-bit PWM on any output pin, for LEDs wired to pins without a hardware
+//! PWM channel (like the RGB LED on D11/D12/D13). Timer2 interrupts at
+//! [`TICK_HZ`] and switches every channel on for the first `duty` of each
+//! 256 ticks, giving a flicker-free ~98 Hz PWM frequency.
+//!
+//! Channels implement the `embedded-hal` [`SetDutyCycle`] trait with a
+//! maximum duty of 255, so they work with [`crate::rgb::RgbLed`] or anything
+//! else written against hardware PWM.
+//!
+//! ## Example
+//! ```ignore
+//! let mut pwm = SoftPwm::new(dp.TC2);
+//! let mut led = pwm.channel(pins.d13.into_output()).unwrap();
+//! unsafe { avr_device::interrupt::enable() };
+//! led.set_duty_cycle_percent(25).unwrap();
+//! ```
+//!
+//! Where no timer is free, [`OnOff`] wraps a plain output pin as a PWM
+//! channel that is either fully on or off.
+//!
+//! [`SetDutyCycle`]: embedded_hal::pwm::SetDutyCycle
+
+use embedded_hal::digital::{self, OutputPin};
+use embedded_hal::pwm::{self, SetDutyCycle};
+
+use crate::tone::CPU_FREQUENCY_HZ;
+
+/// Most pins one [`SoftPwm`] can drive
+pub const MAX_CHANNELS: usize = 4;
+
+/// Timer2 interrupt rate
+pub const TICK_HZ: u32 = 25_000;
+
+/// PWM frequency: one period is 256 ticks
+pub const PWM_HZ: u32 = TICK_HZ / 256;
+
+/// Timer2 compare value, counting at CPU / 8 in CTC mode
+pub const TIMER_TOP: u8 = (CPU_FREQUENCY_HZ / 8 / TICK_HZ - 1) as u8;
+
+/// Level of a channel with `duty` at tick `counter` of the period.
+///
+/// 0 is always off and 255 always on; in between the pin is high for the
+/// first `duty` ticks.
+pub fn level(duty: u8, counter: u8) -> bool {
+    duty == u8::MAX || counter < duty
+}
+
+/// An output pin as a PWM channel without dimming: duties from half of
+/// [`u8::MAX`] up switch it high, anything below low.
+pub struct OnOff<P> {
+    pin: P,
+}
+
+impl<P: OutputPin> OnOff<P> {
+    pub fn new(pin: P) -> Self {
+        Self { pin }
+    }
+
+    /// Give the pin back.
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+impl<P: OutputPin> pwm::ErrorType for OnOff<P> {
+    type Error = OnOffError<P::Error>;
+}
+
+impl<P: OutputPin> SetDutyCycle for OnOff<P> {
+    fn max_duty_cycle(&self) -> u16 {
+        u8::MAX as u16
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        if duty > u8::MAX as u16 / 2 {
+            self.pin.set_high().map_err(OnOffError)
+        } else {
+            self.pin.set_low().map_err(OnOffError)
+        }
+    }
+}
+
+/// Pin error seen through [`OnOff`]
+#[derive(Debug)]
+pub struct OnOffError<E>(pub E);
+
+impl<E: digital::Error> pwm::Error for OnOffError<E> {
+    fn kind(&self) -> pwm::ErrorKind {
+        pwm::ErrorKind::Other
+    }
+}
+
+#[cfg(target_arch = "avr")]
+pub use self::timer::{PwmChannel, SoftPwm};
+
+#[cfg(target_arch = "avr")]
+mod timer {
+    use core::cell::{Cell, RefCell};
+    use core::convert::Infallible;
+
+    use arduino_hal::pac::TC2;
+    use arduino_hal::port::mode::Output;
+    use arduino_hal::port::{Pin, PinOps};
+    use avr_device::interrupt::{self, Mutex};
+    use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+
+    use super::{level, MAX_CHANNELS, TIMER_TOP};
+
+    static PINS: Mutex<RefCell<[Option<Pin<Output>>; MAX_CHANNELS]>> =
+        Mutex::new(RefCell::new([const { None }; MAX_CHANNELS]));
+    static DUTIES: Mutex<Cell<[u8; MAX_CHANNELS]>> = Mutex::new(Cell::new([0; MAX_CHANNELS]));
+    static COUNTER: Mutex<Cell<u8>> = Mutex::new(Cell::new(0));
+
+    /// Software PWM on Timer2
+    ///
+    /// Global interrupts must be enabled for the outputs to run.
+    pub struct SoftPwm {
+        _tc2: TC2,
+        channels: u8,
+    }
+
+    impl SoftPwm {
+        /// Take over Timer2 and start the PWM interrupt.
+        pub fn new(tc2: TC2) -> Self {
+            // CTC mode (WGM2 = 0b010), CPU / 8
+            tc2.tccr2a().write(|w| w.wgm2().set(0b10));
+            tc2.ocr2a().write(|w| w.set(TIMER_TOP));
+            tc2.tcnt2().write(|w| w.set(0));
+            tc2.timsk2().write(|w| w.ocie2a().set_bit());
+            tc2.tccr2b().write(|w| w.cs2().prescale_8());
+            Self {
+                _tc2: tc2,
+                channels: 0,
+            }
+        }
+
+        /// Drive `pin` with a new channel, starting at 0% duty; `None` once
+        /// all [`MAX_CHANNELS`] are taken.
+        pub fn channel<PIN: PinOps>(&mut self, pin: Pin<Output, PIN>) -> Option<PwmChannel> {
+            let index = self.channels as usize;
+            if index >= MAX_CHANNELS {
+                return None;
+            }
+            let mut pin = pin.downgrade();
+            pin.set_low();
+            interrupt::free(|cs| PINS.borrow(cs).borrow_mut()[index] = Some(pin));
+            self.channels += 1;
+            Some(PwmChannel { index: index as u8 })
+        }
+    }
+
+    /// One pin driven by [`SoftPwm`]
+    pub struct PwmChannel {
+        index: u8,
+    }
+
+    impl ErrorType for PwmChannel {
+        type Error = Infallible;
+    }
+
+    impl SetDutyCycle for PwmChannel {
+        fn max_duty_cycle(&self) -> u16 {
+            u8::MAX as u16
+        }
+
+        fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+            interrupt::free(|cs| {
+                let cell = DUTIES.borrow(cs);
+                let mut duties = cell.get();
+                duties[self.index as usize] = duty.min(u8::MAX as u16) as u8;
+                cell.set(duties);
+            });
+            Ok(())
+        }
+    }
+
+    #[avr_device::interrupt(atmega328p)]
+    fn TIMER2_COMPA() {
+        interrupt::free(|cs| {
+            let counter = COUNTER.borrow(cs);
+            let tick = counter.get().wrapping_add(1);
+            counter.set(tick);
+
+            let duties = DUTIES.borrow(cs).get();
+            let mut pins = PINS.borrow(cs).borrow_mut();
+            for (pin, duty) in pins.iter_mut().zip(duties) {
+                if let Some(pin) = pin {
+                    if level(duty, tick) {
+                        pin.set_high();
+                    } else {
+                        pin.set_low();
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockPin;
+
+    fn on_ticks(duty: u8) -> usize {
+        (0..=255u8).filter(|&counter| level(duty, counter)).count()
+    }
+
+    #[test]
+    fn duty_sets_on_time() {
+        assert_eq!(on_ticks(0), 0);
+        assert_eq!(on_ticks(1), 1);
+        assert_eq!(on_ticks(128), 128);
+        assert_eq!(on_ticks(255), 256);
+    }
+
+    #[test]
+    fn on_off_switches_at_half_duty() {
+        let pin = MockPin::new(true);
+        let mut channel = OnOff::new(pin.clone());
+        channel.set_duty_cycle(127).unwrap();
+        assert!(!pin.level());
+        channel.set_duty_cycle(128).unwrap();
+        assert!(pin.level());
+        channel.set_duty_cycle_fully_off().unwrap();
+        assert!(!pin.level());
+    }
+
+    #[test]
+    fn timer_rate() {
+        assert_eq!(TIMER_TOP, 79);
+        assert_eq!(CPU_FREQUENCY_HZ / 8 / (TIMER_TOP as u32 + 1), TICK_HZ);
+        assert_eq!(PWM_HZ, 97);
+    }
+}
@@ -0,0 +1,477 @@
+//! RGB LED
+//!
+//! Drives a three-channel LED through any `embedded-hal` [`SetDutyCycle`]
+//! outputs, e.g. the [`crate::pwm::SoftPwm`] channels, or plain pins wrapped
+//! in [`crate::pwm::OnOff`]. Colors are given in perceived brightness and
+//! gamma-corrected before they reach the LED, so a fade looks even instead of
+//! jumping at the dark end.
+//!
+//! The kit has been shipped with both module variants: common cathode (V to
+//! GND, a high pin lights the LED) and common anode (V to 5V, a low pin
+//! lights it). Pick the matching [`Polarity`] once and every [`Color`] comes
+//! out the same on either. The pins are passed in as red, green and blue, so
+//! however the module is wired only the constructor call changes.
+//!
+//! [`RgbLed::fade_to`] starts a crossfade that [`RgbLed::update`] advances
+//! from the main loop (or a scheduler task), so fades never block.
+//!
+//! ## Example
+//! ```ignore
+//! let mut led = RgbLed::new(red, green, blue).with_polarity(Polarity::CommonAnode);
+//! led.set_color(Color::BLACK).unwrap();
+//! led.fade_to(Color::new(255, 80, 0), Duration::from_millis(500), Instant::now());
+//! loop {
+//!     led.update(Instant::now()).unwrap();
+//! }
+//! ```
+//!
+//! [`SetDutyCycle`]: embedded_hal::pwm::SetDutyCycle
+
+use embedded_hal::pwm::SetDutyCycle;
+
+use crate::clock::{Duration, Instant};
+
+/// A color in perceived brightness, 0-255 per channel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    /// All channels off
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    pub const RED: Color = Color::new(255, 0, 0);
+    pub const GREEN: Color = Color::new(0, 255, 0);
+    pub const BLUE: Color = Color::new(0, 0, 255);
+    /// Red + green
+    pub const YELLOW: Color = Color::new(255, 255, 0);
+    /// Green + blue
+    pub const CYAN: Color = Color::new(0, 255, 255);
+    /// Red + blue
+    pub const MAGENTA: Color = Color::new(255, 0, 255);
+    /// All channels on
+    pub const WHITE: Color = Color::new(255, 255, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Mix `self` and `other`, `t` going from 0 (`self`) to 255 (`other`).
+    pub fn lerp(self, other: Color, t: u8) -> Color {
+        let mix = |a: u8, b: u8| {
+            let (a, b, t) = (a as i32, b as i32, t as i32);
+            (a + (b - a) * t / 255) as u8
+        };
+        Color::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+        )
+    }
+
+    /// Color wheel: red -> green -> blue -> red as `position` goes 0-255.
+    pub fn wheel(position: u8) -> Color {
+        let (segment, offset) = (position / 85, (position % 85) * 3);
+        match segment {
+            0 => Color::new(255 - offset, offset, 0),
+            1 => Color::new(0, 255 - offset, offset),
+            2 => Color::new(offset, 0, 255 - offset),
+            // 255 is back at red
+            _ => Color::new(255, 0, 0),
+        }
+    }
+
+    /// The same color as hue, saturation and value.
+    pub fn to_hsv(self) -> Hsv {
+        let (r, g, b) = (self.r as i32, self.g as i32, self.b as i32);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+        if delta == 0 {
+            return Hsv::new(0, 0, max as u8);
+        }
+        let sector =
+            |base: i32, a: i32, b: i32| base + (60 * (a - b) + delta / 2).div_euclid(delta);
+        let hue = if max == r {
+            sector(0, g, b)
+        } else if max == g {
+            sector(120, b, r)
+        } else {
+            sector(240, r, g)
+        };
+        let saturation = (delta * 255 + max / 2) / max;
+        Hsv::new(hue.rem_euclid(360) as u16, saturation as u8, max as u8)
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(hsv: Hsv) -> Self {
+        hsv.to_rgb()
+    }
+}
+
+/// A color as hue (degrees, 0-359), saturation and value (0-255 each)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Hsv {
+    pub hue: u16,
+    pub saturation: u8,
+    pub value: u8,
+}
+
+impl Hsv {
+    /// Hues of 360 and above wrap around.
+    pub const fn new(hue: u16, saturation: u8, value: u8) -> Self {
+        Self {
+            hue: hue % 360,
+            saturation,
+            value,
+        }
+    }
+
+    /// The same color as red, green and blue.
+    pub fn to_rgb(self) -> Color {
+        let (s, v) = (self.saturation as u32, self.value as u32);
+        let hue = self.hue as u32 % 360;
+        // Position within the 60 degree sector, 0-255
+        let f = ((hue % 60) * 255 + 30) / 60;
+        let scale = |x: u32| ((v * (255 - x) + 127) / 255) as u8;
+        let (v, p) = (v as u8, scale(s));
+        let q = scale(s * f / 255);
+        let t = scale(s * (255 - f) / 255);
+        match hue / 60 {
+            0 => Color::new(v, t, p),
+            1 => Color::new(q, v, p),
+            2 => Color::new(p, v, t),
+            3 => Color::new(p, q, v),
+            4 => Color::new(t, p, v),
+            _ => Color::new(v, p, q),
+        }
+    }
+}
+
+/// What the distance from the center drives in [`Hsv::from_polar`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RadiusMode {
+    /// White in the center, full colors at the edge
+    Saturation,
+    /// Off in the center, full brightness at the edge
+    #[default]
+    Value,
+}
+
+impl Hsv {
+    /// A point on a color wheel: `angle` in degrees is the hue and `radius`
+    /// out of `max_radius` sets the saturation or value, the other one at
+    /// full.
+    ///
+    /// With [`Joystick::polar`](crate::joystick::Joystick::polar) and
+    /// [`NORMALIZED_MAX`](crate::joystick::NORMALIZED_MAX) the stick becomes
+    /// a color picker.
+    pub fn from_polar(angle: u16, radius: u16, max_radius: u16, mode: RadiusMode) -> Self {
+        let level = radius.min(max_radius) as u32 * 255 / max_radius.max(1) as u32;
+        match mode {
+            RadiusMode::Saturation => Hsv::new(angle, level as u8, 255),
+            RadiusMode::Value => Hsv::new(angle, 255, level as u8),
+        }
+    }
+}
+
+impl From<Color> for Hsv {
+    fn from(color: Color) -> Self {
+        color.to_hsv()
+    }
+}
+
+/// How the module's common pin is wired
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Polarity {
+    /// Common pin to GND, a high output lights the LED
+    #[default]
+    CommonCathode,
+    /// Common pin to 5V, a low output lights the LED
+    CommonAnode,
+}
+
+/// Perceived brightness to PWM duty (gamma 2, cheap enough for the AVR).
+pub fn gamma(value: u8) -> u8 {
+    (value as u16 * value as u16).div_ceil(255) as u8
+}
+
+/// A crossfade between two colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fade {
+    from: Color,
+    to: Color,
+    start: Instant,
+    duration: Duration,
+}
+
+impl Fade {
+    pub fn new(from: Color, to: Color, start: Instant, duration: Duration) -> Self {
+        Self {
+            from,
+            to,
+            start,
+            duration,
+        }
+    }
+
+    pub fn target(&self) -> Color {
+        self.to
+    }
+
+    /// Color at `now`, or `None` once the fade has finished.
+    pub fn color_at(&self, now: Instant) -> Option<Color> {
+        let elapsed = now.duration_since(self.start).as_millis();
+        let total = self.duration.as_millis();
+        if elapsed >= total {
+            return None;
+        }
+        let t = (elapsed as u64 * 255 / total as u64) as u8;
+        Some(self.from.lerp(self.to, t))
+    }
+}
+
+/// RGB LED on three PWM outputs
+pub struct RgbLed<R, G, B> {
+    red: R,
+    green: G,
+    blue: B,
+    polarity: Polarity,
+    color: Color,
+    fade: Option<Fade>,
+}
+
+impl<R, G, B, E> RgbLed<R, G, B>
+where
+    R: SetDutyCycle<Error = E>,
+    G: SetDutyCycle<Error = E>,
+    B: SetDutyCycle<Error = E>,
+{
+    /// Take over the three outputs of a common cathode LED.
+    ///
+    /// The outputs are left alone until the first color is set, so start
+    /// with `set_color(Color::BLACK)` to switch the LED off.
+    pub fn new(red: R, green: G, blue: B) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            polarity: Polarity::CommonCathode,
+            color: Color::BLACK,
+            fade: None,
+        }
+    }
+
+    /// Use the given wiring instead of common cathode.
+    pub fn with_polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    pub fn polarity(&self) -> Polarity {
+        self.polarity
+    }
+
+    /// The color shown right now.
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    /// `true` while a crossfade is running.
+    pub fn is_fading(&self) -> bool {
+        self.fade.is_some()
+    }
+
+    /// Show a color right away, cancelling any fade.
+    pub fn set_rgb(&mut self, r: u8, g: u8, b: u8) -> Result<(), E> {
+        self.set_color(Color::new(r, g, b))
+    }
+
+    /// Show a color right away, cancelling any fade.
+    pub fn set_color(&mut self, color: Color) -> Result<(), E> {
+        self.fade = None;
+        self.show(color)
+    }
+
+    /// Start a crossfade from the current color; drive it with
+    /// [`update`](Self::update).
+    pub fn fade_to(&mut self, color: Color, duration: Duration, now: Instant) {
+        self.fade = Some(Fade::new(self.color, color, now, duration));
+    }
+
+    /// Advance the running fade. Returns `true` while it is still running.
+    pub fn update(&mut self, now: Instant) -> Result<bool, E> {
+        let Some(fade) = self.fade else {
+            return Ok(false);
+        };
+        match fade.color_at(now) {
+            Some(color) => {
+                self.show(color)?;
+                Ok(true)
+            }
+            None => {
+                self.fade = None;
+                self.show(fade.target())?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Give the outputs back.
+    pub fn release(self) -> (R, G, B) {
+        (self.red, self.green, self.blue)
+    }
+
+    fn show(&mut self, color: Color) -> Result<(), E> {
+        let duty = |value: u8| {
+            let duty = gamma(value) as u16;
+            match self.polarity {
+                Polarity::CommonCathode => duty,
+                Polarity::CommonAnode => 255 - duty,
+            }
+        };
+        let (r, g, b) = (duty(color.r), duty(color.g), duty(color.b));
+        self.red.set_duty_cycle_fraction(r, 255)?;
+        self.green.set_duty_cycle_fraction(g, 255)?;
+        self.blue.set_duty_cycle_fraction(b, 255)?;
+        self.color = color;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockPin, MockPwm};
+    use crate::pwm::OnOff;
+
+    fn led() -> (RgbLed<MockPwm, MockPwm, MockPwm>, [MockPwm; 3]) {
+        let pwm = [MockPwm::new(), MockPwm::new(), MockPwm::new()];
+        let led = RgbLed::new(pwm[0].clone(), pwm[1].clone(), pwm[2].clone());
+        (led, pwm)
+    }
+
+    fn duties(pwm: &[MockPwm; 3]) -> [u16; 3] {
+        [pwm[0].duty(), pwm[1].duty(), pwm[2].duty()]
+    }
+
+    #[test]
+    fn gamma_curve() {
+        assert_eq!(gamma(0), 0);
+        assert_eq!(gamma(1), 1);
+        assert_eq!(gamma(128), 65);
+        assert_eq!(gamma(255), 255);
+    }
+
+    #[test]
+    fn set_rgb_applies_gamma() {
+        let (mut led, pwm) = led();
+        led.set_rgb(255, 128, 0).unwrap();
+        assert_eq!(duties(&pwm), [255, 65, 0]);
+        assert_eq!(led.color(), Color::new(255, 128, 0));
+    }
+
+    #[test]
+    fn crossfade() {
+        let (mut led, pwm) = led();
+        let start = Instant::from_millis(1000);
+        led.set_rgb(255, 0, 0).unwrap();
+        led.fade_to(Color::new(0, 0, 255), Duration::from_millis(100), start);
+
+        assert!(led.update(start).unwrap());
+        assert_eq!(led.color(), Color::new(255, 0, 0));
+        assert!(led.update(Instant::from_millis(1050)).unwrap());
+        assert_eq!(led.color(), Color::new(128, 0, 127));
+        assert!(!led.update(Instant::from_millis(1100)).unwrap());
+        assert_eq!(led.color(), Color::new(0, 0, 255));
+        assert_eq!(duties(&pwm), [0, 0, 255]);
+        assert!(!led.is_fading());
+    }
+
+    #[test]
+    fn set_color_cancels_fade() {
+        let (mut led, _) = led();
+        led.fade_to(
+            Color::new(255, 255, 255),
+            Duration::from_secs(1),
+            Instant::from_millis(0),
+        );
+        led.set_rgb(0, 10, 0).unwrap();
+        assert!(!led.update(Instant::from_millis(500)).unwrap());
+        assert_eq!(led.color(), Color::new(0, 10, 0));
+    }
+
+    #[test]
+    fn common_anode_inverts_the_outputs() {
+        let (led, pwm) = led();
+        let mut led = led.with_polarity(Polarity::CommonAnode);
+        led.set_rgb(255, 128, 0).unwrap();
+        assert_eq!(duties(&pwm), [0, 190, 255]);
+        led.set_color(Color::BLACK).unwrap();
+        assert_eq!(duties(&pwm), [255, 255, 255]);
+    }
+
+    #[test]
+    fn on_off_pins_show_the_named_colors() {
+        let pins = [
+            MockPin::new(false),
+            MockPin::new(false),
+            MockPin::new(false),
+        ];
+        let mut led = RgbLed::new(
+            OnOff::new(pins[0].clone()),
+            OnOff::new(pins[1].clone()),
+            OnOff::new(pins[2].clone()),
+        )
+        .with_polarity(Polarity::CommonAnode);
+        let levels = |pins: &[MockPin; 3]| [pins[0].level(), pins[1].level(), pins[2].level()];
+
+        led.set_color(Color::YELLOW).unwrap();
+        assert_eq!(levels(&pins), [false, false, true]);
+        led.set_color(Color::BLACK).unwrap();
+        assert_eq!(levels(&pins), [true, true, true]);
+    }
+
+    #[test]
+    fn hsv_round_trip() {
+        let named = [
+            (Color::BLACK, Hsv::new(0, 0, 0)),
+            (Color::RED, Hsv::new(0, 255, 255)),
+            (Color::YELLOW, Hsv::new(60, 255, 255)),
+            (Color::GREEN, Hsv::new(120, 255, 255)),
+            (Color::CYAN, Hsv::new(180, 255, 255)),
+            (Color::BLUE, Hsv::new(240, 255, 255)),
+            (Color::MAGENTA, Hsv::new(300, 255, 255)),
+            (Color::WHITE, Hsv::new(0, 0, 255)),
+        ];
+        for (color, hsv) in named {
+            assert_eq!(color.to_hsv(), hsv);
+            assert_eq!(Color::from(hsv), color);
+        }
+        assert_eq!(Hsv::new(30, 255, 255).to_rgb(), Color::new(255, 128, 0));
+        assert_eq!(Hsv::new(0, 128, 200).to_rgb(), Color::new(200, 100, 100));
+        assert_eq!(Color::new(200, 100, 100).to_hsv(), Hsv::new(0, 128, 200));
+        assert_eq!(Hsv::new(400, 255, 255), Hsv::new(40, 255, 255));
+    }
+
+    #[test]
+    fn polar_color_picker() {
+        let pick = |angle, radius, mode| Color::from(Hsv::from_polar(angle, radius, 1000, mode));
+        assert_eq!(pick(0, 1000, RadiusMode::Value), Color::RED);
+        assert_eq!(pick(120, 1000, RadiusMode::Saturation), Color::GREEN);
+        assert_eq!(pick(240, 0, RadiusMode::Value), Color::BLACK);
+        assert_eq!(pick(240, 0, RadiusMode::Saturation), Color::WHITE);
+        assert_eq!(pick(60, 500, RadiusMode::Value), Color::new(127, 127, 0));
+        assert_eq!(pick(60, 2000, RadiusMode::Value), Color::YELLOW);
+    }
+
+    #[test]
+    fn wheel_goes_around() {
+        assert_eq!(Color::wheel(0), Color::new(255, 0, 0));
+        assert_eq!(Color::wheel(85), Color::new(0, 255, 0));
+        assert_eq!(Color::wheel(170), Color::new(0, 0, 255));
+        assert_eq!(Color::wheel(255), Color::new(255, 0, 0));
+    }
+}
@@ -0,0 +1,326 @@
+//! RTTTL Ringtone Parser
+//!
+//! Parses songs in the Nokia Ring Tone Text Transfer Language, e.g.
+//! `mario:d=16,o=5,b=100:e,e,p,e,p,c,e,p,g,8p.,g4,4p`.
+//!
+//! A song has three sections separated by `:`:
+//! - the name
+//! - defaults: `d` (note duration), `o` (octave) and `b` (tempo in beats per minute)
+//! - comma-separated notes: `[duration]note[#][.][octave][.]`, where `note`
+//!   is one of `c d e f g a b h` or `p` for a pause and `.` makes it 1.5x longer
+//!
+//! [`Song::parse`] checks the whole song up front, so iterating over
+//! [`Song::notes`] cannot fail and can feed [`crate::tone::Player`] directly.
+
+use crate::tone::Note;
+
+/// Duration used when the song doesn't set `d`
+pub const DEFAULT_DURATION: u8 = 4;
+
+/// Octave used when the song doesn't set `o`
+pub const DEFAULT_OCTAVE: u8 = 6;
+
+/// Tempo used when the song doesn't set `b`
+pub const DEFAULT_BPM: u16 = 63;
+
+// Frequencies of octave 8 (C8-B8), lower octaves are derived by halving
+const OCTAVE_8_HZ: [u16; 12] = [
+    4186, 4435, 4699, 4978, 5274, 5588, 5920, 6272, 6645, 7040, 7459, 7902,
+];
+
+/// Parse errors, with the byte offset of the offending item in the song text
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The song doesn't have name, defaults and notes sections
+    MissingSection,
+    /// Unknown key or out-of-range value in the defaults section
+    InvalidDefault { position: usize },
+    /// A note that can't be parsed
+    InvalidNote { position: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Defaults {
+    duration: u8,
+    octave: u8,
+    bpm: u16,
+}
+
+/// A parsed and validated song
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Song<'a> {
+    name: &'a str,
+    defaults: Defaults,
+    notes: &'a str,
+}
+
+impl<'a> Song<'a> {
+    /// Parse and validate a whole song.
+    pub fn parse(text: &'a str) -> Result<Self, Error> {
+        let mut sections = text.splitn(3, ':');
+        let (Some(name), Some(defaults), Some(notes)) =
+            (sections.next(), sections.next(), sections.next())
+        else {
+            return Err(Error::MissingSection);
+        };
+
+        let defaults = parse_defaults(text, defaults)?;
+        for token in notes.split(',') {
+            if parse_note(token, defaults).is_none() {
+                return Err(Error::InvalidNote {
+                    position: offset(text, token),
+                });
+            }
+        }
+
+        Ok(Self {
+            name: name.trim(),
+            defaults,
+            notes,
+        })
+    }
+
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Tempo in beats (quarter notes) per minute.
+    pub fn bpm(&self) -> u16 {
+        self.defaults.bpm
+    }
+
+    /// Iterate over the notes of the song.
+    pub fn notes(&self) -> Notes<'a> {
+        Notes {
+            tokens: self.notes.split(','),
+            defaults: self.defaults,
+        }
+    }
+}
+
+/// Iterator over the notes of a [`Song`]
+#[derive(Clone, Debug)]
+pub struct Notes<'a> {
+    tokens: core::str::Split<'a, char>,
+    defaults: Defaults,
+}
+
+impl Iterator for Notes<'_> {
+    type Item = Note;
+
+    fn next(&mut self) -> Option<Note> {
+        // Every token was validated by `Song::parse`
+        self.tokens
+            .by_ref()
+            .find_map(|token| parse_note(token, self.defaults))
+    }
+}
+
+/// Frequency of a note, `semitone` 0 being C, in octaves 1 to 8.
+pub fn frequency(semitone: u8, octave: u8) -> u16 {
+    let shift = 8 - octave as u32;
+    let hz = OCTAVE_8_HZ[semitone as usize] as u32;
+    // Round instead of truncating while halving
+    ((hz + (1 << shift >> 1)) >> shift) as u16
+}
+
+/// Length in ms of a `1/duration` note at `bpm`, optionally dotted.
+pub fn duration_ms(duration: u8, dotted: bool, bpm: u16) -> u16 {
+    // One beat is a quarter note
+    let whole_ms = 240_000 / bpm as u32;
+    let mut ms = whole_ms / duration as u32;
+    if dotted {
+        ms += ms / 2;
+    }
+    ms.min(u16::MAX as u32) as u16
+}
+
+fn offset(text: &str, part: &str) -> usize {
+    part.as_ptr() as usize - text.as_ptr() as usize
+}
+
+fn valid_duration(duration: u32) -> bool {
+    matches!(duration, 1 | 2 | 4 | 8 | 16 | 32)
+}
+
+fn valid_octave(octave: u32) -> bool {
+    (1..=8).contains(&octave)
+}
+
+fn parse_defaults(text: &str, section: &str) -> Result<Defaults, Error> {
+    let mut defaults = Defaults {
+        duration: DEFAULT_DURATION,
+        octave: DEFAULT_OCTAVE,
+        bpm: DEFAULT_BPM,
+    };
+
+    for item in section.split(',') {
+        let entry = item.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        let error = Error::InvalidDefault {
+            position: offset(text, item),
+        };
+        let (key, value) = entry.split_once('=').ok_or(error)?;
+        let value: u32 = value.trim().parse().map_err(|_| error)?;
+        match key.trim() {
+            "d" if valid_duration(value) => defaults.duration = value as u8,
+            "o" if valid_octave(value) => defaults.octave = value as u8,
+            "b" if (1..=900).contains(&value) => defaults.bpm = value as u16,
+            _ => return Err(error),
+        }
+    }
+    Ok(defaults)
+}
+
+fn parse_note(token: &str, defaults: Defaults) -> Option<Note> {
+    let mut bytes = token.trim().bytes().map(|b| b.to_ascii_lowercase()).peekable();
+
+    // Optional duration
+    let mut duration = 0;
+    let mut digits = 0;
+    while let Some(digit) = bytes.next_if(u8::is_ascii_digit) {
+        duration = duration * 10 + (digit - b'0') as u32;
+        digits += 1;
+        if duration > 32 {
+            return None;
+        }
+    }
+    let duration = match digits {
+        0 => defaults.duration,
+        _ if valid_duration(duration) => duration as u8,
+        _ => return None,
+    };
+
+    // Note letter and optional sharp
+    let semitone = match bytes.next()? {
+        b'p' => None,
+        b'c' => Some(0),
+        b'd' => Some(2),
+        b'e' => Some(4),
+        b'f' => Some(5),
+        b'g' => Some(7),
+        b'a' => Some(9),
+        b'b' | b'h' => Some(11),
+        _ => return None,
+    };
+    let sharp = bytes.next_if_eq(&b'#').is_some();
+    if sharp && semitone.is_none() {
+        return None;
+    }
+
+    // Dot before or after the octave
+    let mut dotted = bytes.next_if_eq(&b'.').is_some();
+    let octave = match bytes.next_if(u8::is_ascii_digit) {
+        Some(digit) if valid_octave((digit - b'0') as u32) => digit - b'0',
+        Some(_) => return None,
+        None => defaults.octave,
+    };
+    if !dotted {
+        dotted = bytes.next_if_eq(&b'.').is_some();
+    }
+    if bytes.next().is_some() {
+        return None;
+    }
+
+    let duration_ms = duration_ms(duration, dotted, defaults.bpm);
+    let Some(semitone) = semitone else {
+        return Some(Note::rest(duration_ms));
+    };
+
+    // B# is the C of the next octave
+    let (semitone, octave) = match semitone + sharp as u8 {
+        12 if octave < 8 => (0, octave + 1),
+        12 => return None,
+        semitone => (semitone, octave),
+    };
+    Some(Note::new(frequency(semitone, octave), duration_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_header() {
+        let song = Song::parse("mario:d=16,o=5,b=100:e,e,p").unwrap();
+        assert_eq!(song.name(), "mario");
+        assert_eq!(song.bpm(), 100);
+        assert_eq!(song.notes().count(), 3);
+    }
+
+    #[test]
+    fn uses_spec_defaults() {
+        let song = Song::parse("beep::c").unwrap();
+        let notes: Vec<_> = song.notes().collect();
+        // Quarter note at 63 bpm, C6
+        assert_eq!(notes, [Note::new(1047, 952)]);
+    }
+
+    #[test]
+    fn note_frequencies() {
+        assert_eq!(frequency(9, 4), 440);
+        assert_eq!(frequency(0, 4), 262);
+        assert_eq!(frequency(4, 5), 659);
+        assert_eq!(frequency(3, 4), 311);
+        assert_eq!(frequency(11, 8), 7902);
+    }
+
+    #[test]
+    fn durations_and_dots() {
+        let song = Song::parse("t:d=4,o=5,b=120:8c,8c.,8c.5,1p,32a#4").unwrap();
+        let notes: Vec<_> = song.notes().collect();
+        assert_eq!(
+            notes,
+            [
+                Note::new(523, 250),
+                Note::new(523, 375),
+                Note::new(523, 375),
+                Note::rest(2000),
+                Note::new(466, 62),
+            ]
+        );
+    }
+
+    #[test]
+    fn b_sharp_wraps_to_next_octave() {
+        let song = Song::parse("t:o=4,b=120:b#").unwrap();
+        assert_eq!(song.notes().next().unwrap().freq_hz, 523);
+    }
+
+    #[test]
+    fn tolerates_spaces_and_case() {
+        let song = Song::parse("Tune : d=8, o=5, b=140 : E6, 4P, C#").unwrap();
+        assert_eq!(song.name(), "Tune");
+        let notes: Vec<_> = song.notes().collect();
+        assert_eq!(notes[0], Note::new(1319, 214));
+        assert!(notes[1].is_rest());
+        assert_eq!(notes[2].freq_hz, 554);
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        assert_eq!(Song::parse("no sections"), Err(Error::MissingSection));
+        assert_eq!(
+            Song::parse("t:d=4,x=5:c"),
+            Err(Error::InvalidDefault { position: 6 })
+        );
+        assert_eq!(
+            Song::parse("t:d=3:c"),
+            Err(Error::InvalidDefault { position: 2 })
+        );
+        assert_eq!(
+            Song::parse("t:d=4:c,d,x,e"),
+            Err(Error::InvalidNote { position: 10 })
+        );
+        assert_eq!(
+            Song::parse("t:d=4:c,p#"),
+            Err(Error::InvalidNote { position: 8 })
+        );
+        assert_eq!(
+            Song::parse("t:d=4:c9"),
+            Err(Error::InvalidNote { position: 6 })
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Cooperative Scheduler
+//!
+//! Runs several periodic tasks from one main loop without a heap. Each task
+//! is a plain function that gets the shared application state and the
+//! current time; it must do a little work and return instead of calling
+//! `delay_ms`, so the other tasks get their turn.
+//!
+//! Timing comes from [`crate::clock`]. Tasks keep a steady rate: a task with
+//! a 100 ms period runs at 100, 200, 300 ms, ... even if one run was late.
+//! A task that falls more than a whole period behind skips the missed runs
+//! instead of running several times in a row.
+//!
+//! ## Example
+//! ```ignore
+//! struct App { led: Pin<Output>, button: Pin<Input<PullUp>> }
+//!
+//! fn blink(app: &mut App, _now: Instant) {
+//!     app.led.toggle();
+//! }
+//!
+//! let mut scheduler: Scheduler<App, 4> = Scheduler::new();
+//! scheduler.add(Instant::now(), Duration::from_millis(500), blink).unwrap();
+//! scheduler.run(&mut app);
+//! ```
+
+use crate::clock::{Deadline, Duration, Instant};
+
+/// A task body, called with the shared state and the current time
+pub type TaskFn<C> = fn(&mut C, Instant);
+
+/// Handle of a task added to a [`Scheduler`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskId(u8);
+
+/// Scheduler errors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// All task slots are taken
+    Full,
+    /// The id doesn't belong to a task of this scheduler
+    InvalidTask,
+}
+
+struct Task<C> {
+    run: TaskFn<C>,
+    period: Duration,
+    next: Deadline,
+    enabled: bool,
+}
+
+/// Runs up to `N` periodic tasks sharing the state `C`
+pub struct Scheduler<C, const N: usize> {
+    tasks: [Option<Task<C>>; N],
+}
+
+impl<C, const N: usize> Scheduler<C, N> {
+    pub const fn new() -> Self {
+        Self {
+            tasks: [const { None }; N],
+        }
+    }
+
+    /// Add a task that first runs at `now` and then every `period`.
+    pub fn add(&mut self, now: Instant, period: Duration, run: TaskFn<C>) -> Result<TaskId, Error> {
+        let (index, slot) = self
+            .tasks
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.is_none())
+            .ok_or(Error::Full)?;
+        *slot = Some(Task {
+            run,
+            period,
+            next: Deadline::at(now),
+            enabled: true,
+        });
+        Ok(TaskId(index as u8))
+    }
+
+    /// Remove a task, freeing its slot.
+    pub fn remove(&mut self, id: TaskId) -> Result<(), Error> {
+        self.task_mut(id)?;
+        self.tasks[id.0 as usize] = None;
+        Ok(())
+    }
+
+    /// Change how often a task runs, starting after its next run.
+    pub fn set_period(&mut self, id: TaskId, period: Duration) -> Result<(), Error> {
+        self.task_mut(id)?.period = period;
+        Ok(())
+    }
+
+    /// Stop running a task until it is resumed.
+    pub fn pause(&mut self, id: TaskId) -> Result<(), Error> {
+        self.task_mut(id)?.enabled = false;
+        Ok(())
+    }
+
+    /// Run a paused task again, starting at `now`.
+    pub fn resume(&mut self, id: TaskId, now: Instant) -> Result<(), Error> {
+        let task = self.task_mut(id)?;
+        task.enabled = true;
+        task.next = Deadline::at(now);
+        Ok(())
+    }
+
+    /// Run every task that is due at `now`, each at most once, in the order
+    /// they were added. Returns the number of tasks that ran.
+    pub fn poll(&mut self, now: Instant, ctx: &mut C) -> usize {
+        let mut ran = 0;
+        for task in self.tasks.iter_mut().flatten() {
+            if !task.enabled || !task.next.expired_at(now) {
+                continue;
+            }
+            (task.run)(ctx, now);
+            ran += 1;
+
+            // Keep the rate steady, but don't try to catch up on missed runs
+            task.next = Deadline::at(task.next.instant() + task.period);
+            if task.next.expired_at(now) {
+                task.next = Deadline::after_from(now, task.period);
+            }
+        }
+        ran
+    }
+
+    /// When the next task is due, or `None` if no task is enabled.
+    pub fn next_due(&self, now: Instant) -> Option<Instant> {
+        self.tasks
+            .iter()
+            .flatten()
+            .filter(|task| task.enabled)
+            .map(|task| task.next.instant())
+            .min_by_key(|due| due.duration_since(now))
+    }
+
+    fn task_mut(&mut self, id: TaskId) -> Result<&mut Task<C>, Error> {
+        self.tasks
+            .get_mut(id.0 as usize)
+            .and_then(Option::as_mut)
+            .ok_or(Error::InvalidTask)
+    }
+}
+
+impl<C, const N: usize> Default for Scheduler<C, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "avr")]
+impl<C, const N: usize> Scheduler<C, N> {
+    /// Run the tasks forever on the [`crate::clock`] time.
+    ///
+    /// The clock must be running (see [`crate::clock::init`]).
+    pub fn run(&mut self, ctx: &mut C) -> ! {
+        loop {
+            self.poll(Instant::now(), ctx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Log {
+        runs: Vec<(char, u32)>,
+    }
+
+    fn task_a(log: &mut Log, now: Instant) {
+        log.runs.push(('a', now.as_millis()));
+    }
+
+    fn task_b(log: &mut Log, now: Instant) {
+        log.runs.push(('b', now.as_millis()));
+    }
+
+    fn ms(ms: u32) -> Instant {
+        Instant::from_millis(ms)
+    }
+
+    fn run_until(scheduler: &mut Scheduler<Log, 4>, log: &mut Log, end: u32) {
+        for now in 0..=end {
+            scheduler.poll(ms(now), log);
+        }
+    }
+
+    #[test]
+    fn runs_each_task_at_its_own_rate() {
+        let mut scheduler = Scheduler::new();
+        let mut log = Log::default();
+        scheduler.add(ms(0), Duration::from_millis(100), task_a).unwrap();
+        scheduler.add(ms(0), Duration::from_millis(250), task_b).unwrap();
+
+        run_until(&mut scheduler, &mut log, 500);
+        assert_eq!(
+            log.runs,
+            [
+                ('a', 0),
+                ('b', 0),
+                ('a', 100),
+                ('a', 200),
+                ('b', 250),
+                ('a', 300),
+                ('a', 400),
+                ('a', 500),
+                ('b', 500),
+            ]
+        );
+    }
+
+    #[test]
+    fn late_runs_keep_the_rate() {
+        let mut scheduler: Scheduler<Log, 1> = Scheduler::new();
+        let mut log = Log::default();
+        scheduler.add(ms(0), Duration::from_millis(100), task_a).unwrap();
+
+        for now in [0, 130, 199, 200, 450, 500, 600] {
+            scheduler.poll(ms(now), &mut log);
+        }
+        // 130 is late but 200 stays on time; after missing 300 and 400 the
+        // task restarts from 450 instead of running three times
+        assert_eq!(
+            log.runs,
+            [('a', 0), ('a', 130), ('a', 200), ('a', 450), ('a', 600)]
+        );
+    }
+
+    #[test]
+    fn pause_resume_and_remove() {
+        let mut scheduler: Scheduler<Log, 1> = Scheduler::new();
+        let mut log = Log::default();
+        let a = scheduler.add(ms(0), Duration::from_millis(10), task_a).unwrap();
+
+        scheduler.pause(a).unwrap();
+        assert_eq!(scheduler.poll(ms(0), &mut log), 0);
+        assert_eq!(scheduler.next_due(ms(0)), None);
+
+        scheduler.resume(a, ms(5)).unwrap();
+        assert_eq!(scheduler.next_due(ms(0)), Some(ms(5)));
+        assert_eq!(scheduler.poll(ms(5), &mut log), 1);
+
+        scheduler.set_period(a, Duration::from_millis(50)).unwrap();
+        scheduler.poll(ms(15), &mut log);
+        assert_eq!(scheduler.next_due(ms(15)), Some(ms(65)));
+
+        scheduler.remove(a).unwrap();
+        assert_eq!(scheduler.pause(a), Err(Error::InvalidTask));
+        assert_eq!(log.runs, [('a', 5), ('a', 15)]);
+    }
+
+    #[test]
+    fn slots_are_limited() {
+        let mut scheduler: Scheduler<Log, 2> = Scheduler::new();
+        let period = Duration::from_millis(1);
+        let a = scheduler.add(ms(0), period, task_a).unwrap();
+        scheduler.add(ms(0), period, task_b).unwrap();
+        assert_eq!(scheduler.add(ms(0), period, task_a), Err(Error::Full));
+
+        scheduler.remove(a).unwrap();
+        assert_eq!(scheduler.add(ms(0), period, task_b), Ok(a));
+    }
+}
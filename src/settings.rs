@@ -0,0 +1,355 @@
+//! Persistent Settings
+//!
+//! Keeps the joystick calibration, the matrix brightness and the selected
+//! patterns in the ATmega328P EEPROM so they survive a power cycle.
+//!
+//! The settings are stored as a 16-byte record:
+//!
+//! | bytes | content                                         |
+//! |-------|-------------------------------------------------|
+//! | 0     | magic byte `0xA5`                               |
+//! | 1     | layout [`VERSION`]                              |
+//! | 2-3   | sequence number, incremented on every save      |
+//! | 4-13  | the [`Settings`] fields                         |
+//! | 14-15 | CRC-16/CCITT of bytes 0-13                      |
+//!
+//! EEPROM cells survive about 100,000 writes, so [`SettingsStore`] spreads
+//! the records over [`SLOTS`] slots, writing each save into the slot after
+//! the newest one. Loading picks the valid record with the highest sequence
+//! number; a record torn by a power loss fails its CRC and the previous one
+//! is used instead. Without any valid record (or after a layout change) the
+//! defaults apply.
+//!
+//! ## Example
+//! ```ignore
+//! let mut store = SettingsStore::new(arduino_hal::Eeprom::new(dp.EEPROM));
+//! let mut settings = store.load().unwrap_or_default();
+//! settings.brightness = 8;
+//! store.save(&settings);
+//! ```
+
+use crate::ht16k33::MAX_BRIGHTNESS;
+use crate::joystick::{Joystick, CENTER_VALUE, DIRECTION_THRESHOLD};
+
+/// Layout version of the stored record; bump it when [`Settings`] changes
+pub const VERSION: u8 = 1;
+
+/// Size of one stored record in bytes
+pub const RECORD_LEN: usize = 16;
+
+/// Number of records the writes are spread over
+pub const SLOTS: u16 = 16;
+
+/// EEPROM bytes used by the settings, starting at offset 0
+pub const REGION_LEN: u16 = SLOTS * RECORD_LEN as u16;
+
+const MAGIC: u8 = 0xA5;
+const PAYLOAD_LEN: usize = 10;
+
+/// Byte-addressed non-volatile memory
+pub trait Storage {
+    fn read(&mut self, offset: u16, buf: &mut [u8]);
+
+    fn write(&mut self, offset: u16, data: &[u8]);
+}
+
+/// Lets several stores share one EEPROM, e.g. the settings and
+/// [`crate::paint::CanvasStore`].
+impl<S: Storage> Storage for &mut S {
+    fn read(&mut self, offset: u16, buf: &mut [u8]) {
+        (**self).read(offset, buf);
+    }
+
+    fn write(&mut self, offset: u16, data: &[u8]) {
+        (**self).write(offset, data);
+    }
+}
+
+#[cfg(target_arch = "avr")]
+impl Storage for arduino_hal::Eeprom {
+    fn read(&mut self, offset: u16, buf: &mut [u8]) {
+        // Offsets are fixed by the callers and always in range
+        let _ = arduino_hal::Eeprom::read(self, offset, buf);
+    }
+
+    fn write(&mut self, offset: u16, data: &[u8]) {
+        let _ = arduino_hal::Eeprom::write(self, offset, data);
+    }
+}
+
+/// User settings that survive a power cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Raw joystick rest position from [`Joystick::calibrate`]
+    pub joystick_center: (u16, u16),
+    /// Normalized distance the stick needs for a direction
+    pub direction_threshold: i16,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    /// LED matrix brightness, 0 to [`MAX_BRIGHTNESS`]
+    pub brightness: u8,
+    /// Index of the selected melody
+    pub melody: u8,
+    /// Index of the selected traffic light pattern
+    pub light_pattern: u8,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            joystick_center: (CENTER_VALUE, CENTER_VALUE),
+            direction_threshold: DIRECTION_THRESHOLD,
+            invert_x: false,
+            invert_y: false,
+            brightness: MAX_BRIGHTNESS,
+            melody: 0,
+            light_pattern: 0,
+        }
+    }
+}
+
+impl Settings {
+    /// Set up a joystick with the stored calibration.
+    pub fn configure_joystick<X, Y, B>(&self, joystick: &mut Joystick<X, Y, B>) {
+        joystick.set_center(self.joystick_center);
+        joystick.set_direction_threshold(self.direction_threshold);
+        joystick.set_invert_x(self.invert_x);
+        joystick.set_invert_y(self.invert_y);
+    }
+
+    fn to_bytes(self) -> [u8; PAYLOAD_LEN] {
+        let [x0, x1] = self.joystick_center.0.to_le_bytes();
+        let [y0, y1] = self.joystick_center.1.to_le_bytes();
+        let [t0, t1] = self.direction_threshold.to_le_bytes();
+        let flags = self.invert_x as u8 | (self.invert_y as u8) << 1;
+        [
+            x0,
+            x1,
+            y0,
+            y1,
+            t0,
+            t1,
+            flags,
+            self.brightness,
+            self.melody,
+            self.light_pattern,
+        ]
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let word = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        Self {
+            joystick_center: (word(0), word(2)),
+            direction_threshold: word(4) as i16,
+            invert_x: bytes[6] & 0b01 != 0,
+            invert_y: bytes[6] & 0b10 != 0,
+            brightness: bytes[7].min(MAX_BRIGHTNESS),
+            melody: bytes[8],
+            light_pattern: bytes[9],
+        }
+    }
+}
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF).
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |mut crc: u16, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Loads and saves [`Settings`] in a wear-leveled EEPROM region
+pub struct SettingsStore<S> {
+    storage: S,
+    // Slot and sequence number of the newest valid record
+    newest: Option<(u16, u16)>,
+}
+
+impl<S: Storage> SettingsStore<S> {
+    pub fn new(storage: S) -> Self {
+        Self {
+            storage,
+            newest: None,
+        }
+    }
+
+    /// The newest valid settings, or `None` if nothing valid is stored.
+    pub fn load(&mut self) -> Option<Settings> {
+        let mut newest: Option<(u16, u16, Settings)> = None;
+        for slot in 0..SLOTS {
+            let Some((sequence, settings)) = self.read_slot(slot) else {
+                continue;
+            };
+            let is_newer = match newest {
+                // Sequence numbers wrap, compare them by distance
+                Some((_, best, _)) => sequence.wrapping_sub(best) as i16 > 0,
+                None => true,
+            };
+            if is_newer {
+                newest = Some((slot, sequence, settings));
+            }
+        }
+        self.newest = newest.map(|(slot, sequence, _)| (slot, sequence));
+        newest.map(|(_, _, settings)| settings)
+    }
+
+    /// Store `settings` in the next slot, unless they are already stored.
+    ///
+    /// Call [`load`](Self::load) first so the store knows the newest slot.
+    pub fn save(&mut self, settings: &Settings) {
+        if let Some((slot, _)) = self.newest {
+            if self.read_slot(slot).map(|(_, stored)| stored) == Some(*settings) {
+                return;
+            }
+        }
+        let (slot, sequence) = match self.newest {
+            Some((slot, sequence)) => ((slot + 1) % SLOTS, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+
+        let mut record = [0u8; RECORD_LEN];
+        record[0] = MAGIC;
+        record[1] = VERSION;
+        record[2..4].copy_from_slice(&sequence.to_le_bytes());
+        record[4..4 + PAYLOAD_LEN].copy_from_slice(&settings.to_bytes());
+        let crc = crc16(&record[..RECORD_LEN - 2]);
+        record[RECORD_LEN - 2..].copy_from_slice(&crc.to_le_bytes());
+
+        self.storage.write(slot * RECORD_LEN as u16, &record);
+        self.newest = Some((slot, sequence));
+    }
+
+    /// Give the storage back.
+    pub fn release(self) -> S {
+        self.storage
+    }
+
+    fn read_slot(&mut self, slot: u16) -> Option<(u16, Settings)> {
+        let mut record = [0u8; RECORD_LEN];
+        self.storage.read(slot * RECORD_LEN as u16, &mut record);
+        let crc = u16::from_le_bytes([record[RECORD_LEN - 2], record[RECORD_LEN - 1]]);
+        if record[0] != MAGIC || record[1] != VERSION || crc16(&record[..RECORD_LEN - 2]) != crc {
+            return None;
+        }
+        let sequence = u16::from_le_bytes([record[2], record[3]]);
+        Some((sequence, Settings::from_bytes(&record[4..4 + PAYLOAD_LEN])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockEeprom;
+
+    fn custom() -> Settings {
+        Settings {
+            joystick_center: (530, 498),
+            direction_threshold: 500,
+            invert_x: true,
+            invert_y: false,
+            brightness: 7,
+            melody: 2,
+            light_pattern: 1,
+        }
+    }
+
+    #[test]
+    fn crc_check_value() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn blank_eeprom_has_no_settings() {
+        let mut store = SettingsStore::new(MockEeprom::new(1024));
+        assert_eq!(store.load(), None);
+        assert_eq!(store.load().unwrap_or_default(), Settings::default());
+    }
+
+    #[test]
+    fn saved_settings_survive_a_restart() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = SettingsStore::new(eeprom.clone());
+        store.load();
+        store.save(&custom());
+
+        let mut store = SettingsStore::new(eeprom);
+        assert_eq!(store.load(), Some(custom()));
+    }
+
+    #[test]
+    fn saves_rotate_through_the_slots() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = SettingsStore::new(eeprom.clone());
+        store.load();
+        let mut settings = custom();
+        for melody in 0..40 {
+            settings.melody = melody;
+            store.save(&settings);
+        }
+        // 40 saves over 16 slots: no byte written more than 3 times
+        assert!((0..REGION_LEN).all(|offset| eeprom.write_count(offset) <= 3));
+        assert_eq!(eeprom.write_count(REGION_LEN), 0);
+
+        let mut store = SettingsStore::new(eeprom);
+        assert_eq!(store.load().unwrap().melody, 39);
+    }
+
+    #[test]
+    fn unchanged_settings_are_not_rewritten() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = SettingsStore::new(eeprom.clone());
+        store.load();
+        store.save(&custom());
+        store.save(&custom());
+        assert_eq!(eeprom.write_count(RECORD_LEN as u16), 0);
+    }
+
+    #[test]
+    fn torn_record_falls_back_to_the_previous_one() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = SettingsStore::new(eeprom.clone());
+        store.load();
+        store.save(&Settings::default());
+        store.save(&custom());
+
+        // Power lost halfway through writing the second record
+        eeprom.corrupt(RECORD_LEN as u16 + 9);
+        let mut store = SettingsStore::new(eeprom.clone());
+        assert_eq!(store.load(), Some(Settings::default()));
+
+        // The next save goes after the valid record, over the torn one
+        store.save(&custom());
+        assert_eq!(SettingsStore::new(eeprom).load(), Some(custom()));
+    }
+
+    #[test]
+    fn other_versions_are_ignored() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = SettingsStore::new(eeprom.clone());
+        store.load();
+        store.save(&custom());
+        eeprom.poke(1, VERSION + 1);
+        assert_eq!(SettingsStore::new(eeprom).load(), None);
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = SettingsStore::new(eeprom.clone());
+        store.newest = Some((SLOTS - 1, u16::MAX));
+        store.save(&custom());
+        // Slot 0 now holds sequence 0, which is newer than 65535
+        let mut older = custom();
+        older.melody = 9;
+        store.newest = Some((SLOTS - 2, u16::MAX - 1));
+        store.save(&older);
+        assert_eq!(SettingsStore::new(eeprom).load(), Some(custom()));
+    }
+}
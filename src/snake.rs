@@ -0,0 +1,548 @@
+//! Snake Game
+//!
+//! The rules of Snake on the 8x8 matrix, kept apart from the hardware so
+//! they can be tested on the host:
+//!
+//! - [`Game`] moves the snake one cell per step, turns it where the stick
+//!   points, grows it on food and ends the game when it runs into itself
+//!   (or the edge, with [`Walls::Solid`]). Every food scores a point and
+//!   makes the next steps a little faster.
+//! - [`Snake`] keeps the cells of the body in a ring buffer, head first.
+//! - [`Rng`] places the food on a free cell.
+//! - [`HighScoreStore`] keeps the best score in the EEPROM, right after the
+//!   [`crate::paint`] canvases.
+//!
+//! The sound effects are short [`Note`] tables for a [`crate::tone::Player`].
+//!
+//! ## Example
+//! ```ignore
+//! let mut game = Game::new(seed);
+//! loop {
+//!     if let Some(direction) = joystick.direction(&mut adc) {
+//!         game.turn(direction);
+//!     }
+//!     if let Some(step) = game.update(Instant::now()) {
+//!         game.frame().flush(&mut ht16k33)?;
+//!     }
+//! }
+//! ```
+
+use crate::clock::{Duration, Instant};
+use crate::joystick::Direction8;
+use crate::matrix::{Matrix8x8, SIZE};
+use crate::paint::{CANVAS_OFFSET, CANVAS_REGION_LEN};
+use crate::settings::{crc16, Storage};
+use crate::tone::Note;
+
+/// Width and height of the board
+pub const BOARD_SIZE: u8 = SIZE as u8;
+
+/// Number of cells on the board, and the longest the snake gets
+pub const CELLS: usize = BOARD_SIZE as usize * BOARD_SIZE as usize;
+
+/// Length of the snake at the start
+pub const START_LENGTH: u8 = 3;
+
+/// Time per step at the start
+pub const START_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Time per step once the snake is at full speed
+pub const MIN_INTERVAL: Duration = Duration::from_millis(120);
+
+// Each food makes the steps this much shorter
+const SPEEDUP_MS: u32 = 10;
+
+/// EEPROM byte of the high score, right after the canvases
+pub const HIGH_SCORE_OFFSET: u16 = CANVAS_OFFSET + CANVAS_REGION_LEN;
+
+// Magic byte, the score and a CRC-16 of both
+const RECORD_LEN: usize = 1 + 2 + 2;
+const MAGIC: u8 = 0x5B;
+
+/// Eating food
+pub const EAT_SOUND: &[Note] = &[Note::new(1319, 40), Note::new(1760, 60)];
+
+/// Running into a wall or the snake itself
+pub const CRASH_SOUND: &[Note] = &[
+    Note::new(392, 120),
+    Note::new(294, 120),
+    Note::new(196, 300),
+];
+
+/// A new game starts
+pub const START_SOUND: &[Note] = &[Note::new(523, 80), Note::new(659, 80), Note::new(784, 120)];
+
+/// The game ended with a new high score (or a full board)
+pub const HIGH_SCORE_SOUND: &[Note] = &[
+    Note::new(784, 100),
+    Note::new(1047, 100),
+    Note::rest(50),
+    Note::new(1047, 100),
+    Note::new(1319, 250),
+];
+
+/// A small xorshift random number generator
+#[derive(Clone, Copy, Debug)]
+pub struct Rng {
+    state: u16,
+}
+
+impl Rng {
+    /// A generator started from `seed`, e.g. the time of a button press.
+    pub fn new(seed: u16) -> Self {
+        // Zero would stay zero forever
+        Self { state: seed.max(1) }
+    }
+
+    /// The next number; repeats only after all 65535 non-zero values.
+    pub fn next_u16(&mut self) -> u16 {
+        let mut x = self.state;
+        x ^= x << 7;
+        x ^= x >> 9;
+        x ^= x << 8;
+        self.state = x;
+        x
+    }
+
+    /// A number from `0` to `n - 1` (`0` for `n == 0`).
+    pub fn below(&mut self, n: u16) -> u16 {
+        if n == 0 {
+            return 0;
+        }
+        self.next_u16() % n
+    }
+}
+
+/// What happens at the edge of the board
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Walls {
+    /// Running into the edge ends the game
+    #[default]
+    Solid,
+    /// Leave on one side, come back on the other
+    Wrap,
+}
+
+/// Where a game stands
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Playing,
+    /// The snake ran into something
+    Over,
+    /// The snake fills the whole board
+    Won,
+}
+
+/// What a step did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    Moved,
+    /// Ate the food and grew by one
+    Ate,
+    /// Ran into something; the game is over
+    Crashed,
+    /// Ate the last food there was room for
+    Won,
+}
+
+/// The cells of the snake, head first
+pub struct Snake {
+    cells: [(u8, u8); CELLS],
+    // Index of the head; the body follows at lower indices
+    head: usize,
+    len: usize,
+}
+
+impl Snake {
+    fn new() -> Self {
+        Self {
+            cells: [(0, 0); CELLS],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The cell of the head.
+    pub fn head(&self) -> (u8, u8) {
+        self.cells[self.head]
+    }
+
+    /// The cells from the head to the tail.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        (0..self.len).map(|n| self.cells[(self.head + CELLS - n) % CELLS])
+    }
+
+    pub fn contains(&self, cell: (u8, u8)) -> bool {
+        self.iter().any(|c| c == cell)
+    }
+
+    fn push_head(&mut self, cell: (u8, u8)) {
+        self.head = (self.head + 1) % CELLS;
+        self.cells[self.head] = cell;
+        self.len = (self.len + 1).min(CELLS);
+    }
+
+    fn pop_tail(&mut self) {
+        self.len = self.len.saturating_sub(1);
+    }
+}
+
+/// A game of Snake on the 8x8 board
+pub struct Game {
+    snake: Snake,
+    heading: Direction8,
+    // Turn to take on the next step
+    turn: Option<Direction8>,
+    food: Option<(u8, u8)>,
+    rng: Rng,
+    walls: Walls,
+    state: State,
+    score: u16,
+    last_step: Option<Instant>,
+}
+
+impl Game {
+    /// A new game: a short snake on the left heading right, and the first
+    /// food placed by an [`Rng`] started from `seed`.
+    pub fn new(seed: u16) -> Self {
+        let mut game = Self {
+            snake: Snake::new(),
+            heading: Direction8::Right,
+            turn: None,
+            food: None,
+            rng: Rng::new(seed),
+            walls: Walls::default(),
+            state: State::Playing,
+            score: 0,
+            last_step: None,
+        };
+        for x in 0..START_LENGTH {
+            game.snake.push_head((x, BOARD_SIZE / 2));
+        }
+        game.place_food();
+        game
+    }
+
+    pub fn walls(&self) -> Walls {
+        self.walls
+    }
+
+    pub fn set_walls(&mut self, walls: Walls) {
+        self.walls = walls;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Food eaten so far.
+    pub fn score(&self) -> u16 {
+        self.score
+    }
+
+    pub fn snake(&self) -> &Snake {
+        &self.snake
+    }
+
+    /// The cell of the food; `None` once the board is full.
+    pub fn food(&self) -> Option<(u8, u8)> {
+        self.food
+    }
+
+    pub fn heading(&self) -> Direction8 {
+        self.heading
+    }
+
+    /// Head towards `direction` from the next step on. Diagonals and
+    /// turning back into the snake are ignored.
+    pub fn turn(&mut self, direction: Direction8) {
+        let (dx, dy) = direction.delta();
+        let (hx, hy) = self.heading.delta();
+        let straight = dx == 0 || dy == 0;
+        if straight && (dx, dy) != (-hx, -hy) {
+            self.turn = Some(direction);
+        }
+    }
+
+    /// Time between two steps at the current score.
+    pub fn interval(&self) -> Duration {
+        let faster = self.score as u32 * SPEEDUP_MS;
+        let ms = START_INTERVAL.as_millis().saturating_sub(faster);
+        Duration::from_millis(ms.max(MIN_INTERVAL.as_millis()))
+    }
+
+    /// Take a step when one is due. Returns what it did, `None` if no step
+    /// was due or the game has ended.
+    pub fn update(&mut self, now: Instant) -> Option<Step> {
+        if self.state != State::Playing {
+            return None;
+        }
+        let Some(last) = self.last_step else {
+            // The first call only starts the timing
+            self.last_step = Some(now);
+            return None;
+        };
+        if now.duration_since(last) < self.interval() {
+            return None;
+        }
+        self.last_step = Some(now);
+        Some(self.step())
+    }
+
+    /// Move the snake one cell right away.
+    pub fn step(&mut self) -> Step {
+        if self.state != State::Playing {
+            return Step::Crashed;
+        }
+        if let Some(turn) = self.turn.take() {
+            self.heading = turn;
+        }
+        let Some(next) = self.next_cell() else {
+            self.state = State::Over;
+            return Step::Crashed;
+        };
+        let eats = self.food == Some(next);
+        // Unless the snake grows, the tail moves out of the way in the same step
+        let body = self.snake.len() - usize::from(!eats);
+        if self.snake.iter().take(body).any(|cell| cell == next) {
+            self.state = State::Over;
+            return Step::Crashed;
+        }
+        if !eats {
+            self.snake.pop_tail();
+        }
+        self.snake.push_head(next);
+        if !eats {
+            return Step::Moved;
+        }
+        self.score += 1;
+        if self.snake.len() == CELLS {
+            self.food = None;
+            self.state = State::Won;
+            return Step::Won;
+        }
+        self.place_food();
+        Step::Ate
+    }
+
+    /// The snake and the food as pixels.
+    pub fn frame(&self) -> Matrix8x8 {
+        let mut frame = Matrix8x8::new();
+        for (x, y) in self.snake.iter().chain(self.food) {
+            frame.set_pixel(x as i32, y as i32, true);
+        }
+        frame
+    }
+
+    // The cell in front of the head, `None` past a solid wall
+    fn next_cell(&self) -> Option<(u8, u8)> {
+        let (x, y) = self.snake.head();
+        let (dx, dy) = self.heading.delta();
+        let (x, y) = (x as i16 + dx as i16, y as i16 + dy as i16);
+        let size = BOARD_SIZE as i16;
+        match self.walls {
+            Walls::Wrap => Some((x.rem_euclid(size) as u8, y.rem_euclid(size) as u8)),
+            Walls::Solid if (0..size).contains(&x) && (0..size).contains(&y) => {
+                Some((x as u8, y as u8))
+            }
+            Walls::Solid => None,
+        }
+    }
+
+    // Put the food on a random free cell
+    fn place_food(&mut self) {
+        let free = CELLS - self.snake.len();
+        let pick = self.rng.below(free as u16) as usize;
+        self.food = (0..BOARD_SIZE)
+            .flat_map(|y| (0..BOARD_SIZE).map(move |x| (x, y)))
+            .filter(|&cell| !self.snake.contains(cell))
+            .nth(pick);
+    }
+}
+
+/// Keeps the best score in the EEPROM
+pub struct HighScoreStore<S> {
+    storage: S,
+}
+
+impl<S: Storage> HighScoreStore<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+
+    /// The best score so far, 0 if none is stored.
+    pub fn load(&mut self) -> u16 {
+        let mut record = [0u8; RECORD_LEN];
+        self.storage.read(HIGH_SCORE_OFFSET, &mut record);
+        let crc = u16::from_le_bytes([record[RECORD_LEN - 2], record[RECORD_LEN - 1]]);
+        if record[0] != MAGIC || crc16(&record[..RECORD_LEN - 2]) != crc {
+            return 0;
+        }
+        u16::from_le_bytes([record[1], record[2]])
+    }
+
+    /// Store `score` if it beats the best one. Returns `true` for a new
+    /// high score.
+    pub fn submit(&mut self, score: u16) -> bool {
+        if score <= self.load() {
+            return false;
+        }
+        let mut record = [0u8; RECORD_LEN];
+        record[0] = MAGIC;
+        record[1..3].copy_from_slice(&score.to_le_bytes());
+        let crc = crc16(&record[..RECORD_LEN - 2]);
+        record[RECORD_LEN - 2..].copy_from_slice(&crc.to_le_bytes());
+        self.storage.write(HIGH_SCORE_OFFSET, &record);
+        true
+    }
+
+    /// Give the storage back.
+    pub fn release(self) -> S {
+        self.storage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockEeprom;
+
+    // A game with the food moved to `food`
+    fn game_with_food(food: (u8, u8)) -> Game {
+        let mut game = Game::new(1);
+        game.food = Some(food);
+        game
+    }
+
+    #[test]
+    fn rng_covers_every_value() {
+        let mut rng = Rng::new(0);
+        let first = rng.next_u16();
+        let period = 1 + (1..).take_while(|_| rng.next_u16() != first).count();
+        assert_eq!(period, u16::MAX as usize);
+        assert!((0..1000).all(|_| rng.below(7) < 7));
+        assert_eq!(rng.below(0), 0);
+    }
+
+    #[test]
+    fn moves_and_turns() {
+        let mut game = game_with_food((7, 7));
+        assert_eq!(game.snake().head(), (2, 4));
+        assert_eq!(game.step(), Step::Moved);
+        assert_eq!(
+            game.snake().iter().collect::<Vec<_>>(),
+            [(3, 4), (2, 4), (1, 4)]
+        );
+
+        // Straight back and diagonals don't count
+        game.turn(Direction8::Left);
+        game.turn(Direction8::UpLeft);
+        game.step();
+        assert_eq!(game.snake().head(), (4, 4));
+        game.turn(Direction8::Up);
+        game.step();
+        assert_eq!(game.snake().head(), (4, 3));
+        assert_eq!(game.heading(), Direction8::Up);
+        assert_eq!(game.snake().len(), START_LENGTH as usize);
+    }
+
+    #[test]
+    fn eating_grows_scores_and_speeds_up() {
+        let mut game = game_with_food((3, 4));
+        assert_eq!(game.step(), Step::Ate);
+        assert_eq!(game.score(), 1);
+        assert_eq!(game.snake().len(), 4);
+        assert_eq!(game.interval(), Duration::from_millis(290));
+        // The new food is somewhere free
+        let food = game.food().unwrap();
+        assert!(!game.snake().contains(food));
+
+        game.score = 100;
+        assert_eq!(game.interval(), MIN_INTERVAL);
+    }
+
+    #[test]
+    fn steps_follow_the_interval() {
+        let mut game = game_with_food((7, 7));
+        assert_eq!(game.update(Instant::from_millis(0)), None);
+        assert_eq!(game.update(Instant::from_millis(299)), None);
+        assert_eq!(game.update(Instant::from_millis(300)), Some(Step::Moved));
+        assert_eq!(game.update(Instant::from_millis(500)), None);
+        assert_eq!(game.update(Instant::from_millis(600)), Some(Step::Moved));
+    }
+
+    #[test]
+    fn crashes() {
+        // Into the wall after the head reaches the right edge
+        let mut game = game_with_food((0, 0));
+        let steps = (0..10).take_while(|_| game.step() == Step::Moved).count();
+        assert_eq!(steps, 5);
+        assert_eq!(game.state(), State::Over);
+        assert_eq!(game.update(Instant::from_millis(10_000)), None);
+
+        // Around through the wrapping edge instead
+        let mut game = game_with_food((0, 0));
+        game.set_walls(Walls::Wrap);
+        (0..6).for_each(|_| assert_eq!(game.step(), Step::Moved));
+        assert_eq!(game.snake().head(), (0, 4));
+
+        // Into its own body: at five long a tight square runs into the neck
+        let mut game = game_with_food((3, 4));
+        game.step();
+        game.food = Some((4, 4));
+        game.step();
+        game.food = Some((7, 7));
+        for turn in [Direction8::Down, Direction8::Left] {
+            game.turn(turn);
+            assert_eq!(game.step(), Step::Moved);
+        }
+        game.turn(Direction8::Up);
+        assert_eq!(game.step(), Step::Crashed);
+        assert_eq!(game.snake().len(), 5);
+    }
+
+    #[test]
+    fn following_the_tail_is_fine() {
+        // A snake of four in a square moves into the cell its tail leaves
+        let mut game = game_with_food((3, 4));
+        game.step();
+        game.food = Some((7, 7));
+        for turn in [Direction8::Down, Direction8::Left, Direction8::Up] {
+            game.turn(turn);
+            assert_eq!(game.step(), Step::Moved);
+        }
+    }
+
+    #[test]
+    fn frame_shows_snake_and_food() {
+        let game = game_with_food((6, 1));
+        let frame = game.frame();
+        assert_eq!(frame.rows()[4], 0b111);
+        assert_eq!(frame.rows()[1], 1 << 6);
+    }
+
+    #[test]
+    fn high_score_survives_a_restart() {
+        let eeprom = MockEeprom::new(1024);
+        let mut store = HighScoreStore::new(eeprom.clone());
+        assert_eq!(store.load(), 0);
+        assert!(store.submit(12));
+        assert!(!store.submit(12));
+        assert!(!store.submit(5));
+        // Nothing written into the settings or the canvases
+        assert!((0..HIGH_SCORE_OFFSET).all(|offset| eeprom.write_count(offset) == 0));
+
+        let mut store = HighScoreStore::new(eeprom.clone());
+        assert_eq!(store.load(), 12);
+        eeprom.corrupt(HIGH_SCORE_OFFSET + 1);
+        assert_eq!(store.load(), 0);
+    }
+}
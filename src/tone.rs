@@ -0,0 +1,370 @@
+//! Tone Generation
+//!
+//! Plays square-wave tones on the buzzer without blocking the CPU. Timer1
+//! runs in CTC mode and its compare-match interrupt toggles the buzzer pin,
+//! counting down the toggles left in the current note. [`Tone::play`] returns
+//! immediately, so the main loop keeps polling buttons while a note sounds.
+//!
+//! Rests keep the timer running at [`REST_FREQUENCY_HZ`] without touching the
+//! pin, so a rest ends exactly like a note does.
+//!
+//! ## Example
+//! ```ignore
+//! let mut tone = Tone::new(dp.TC1, pins.d8.into_output());
+//! unsafe { avr_device::interrupt::enable() };
+//!
+//! let mut player = Player::new(melody::MARIO.iter().copied());
+//! loop {
+//!     player.poll(&mut tone);
+//!     // ... other work ...
+//! }
+//! ```
+
+/// CPU clock of the Arduino Uno
+pub const CPU_FREQUENCY_HZ: u32 = 16_000_000;
+
+/// Interrupt rate used to time rests (one toggle per millisecond)
+pub const REST_FREQUENCY_HZ: u32 = 500;
+
+/// A note (or rest, when `freq_hz` is 0) of a melody
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Note {
+    pub freq_hz: u16,
+    pub duration_ms: u16,
+}
+
+impl Note {
+    pub const fn new(freq_hz: u16, duration_ms: u16) -> Self {
+        Self { freq_hz, duration_ms }
+    }
+
+    /// Silence for `duration_ms`.
+    pub const fn rest(duration_ms: u16) -> Self {
+        Self::new(0, duration_ms)
+    }
+
+    pub const fn is_rest(&self) -> bool {
+        self.freq_hz == 0
+    }
+}
+
+/// Timer1 clock divider
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prescaler {
+    Direct,
+    Div8,
+    Div64,
+    Div256,
+    Div1024,
+}
+
+impl Prescaler {
+    const ALL: [Prescaler; 5] = [
+        Prescaler::Direct,
+        Prescaler::Div8,
+        Prescaler::Div64,
+        Prescaler::Div256,
+        Prescaler::Div1024,
+    ];
+
+    pub const fn divider(self) -> u32 {
+        match self {
+            Prescaler::Direct => 1,
+            Prescaler::Div8 => 8,
+            Prescaler::Div64 => 64,
+            Prescaler::Div256 => 256,
+            Prescaler::Div1024 => 1024,
+        }
+    }
+}
+
+/// Smallest prescaler and compare value (OCR1A) that toggle the pin at
+/// twice `freq_hz`, or `None` for 0 Hz.
+///
+/// With the smallest usable prescaler the frequency error stays below 0.1%
+/// across the audible range.
+pub fn timer_settings(freq_hz: u32) -> Option<(Prescaler, u16)> {
+    if freq_hz == 0 {
+        return None;
+    }
+    Prescaler::ALL.into_iter().find_map(|prescaler| {
+        // Two compare matches per period, rounded to the nearest count
+        let step = 2 * prescaler.divider() * freq_hz;
+        let counts = (CPU_FREQUENCY_HZ + step / 2) / step;
+        match counts {
+            0 => Some((prescaler, 0)),
+            1..=0x1_0000 => Some((prescaler, (counts - 1) as u16)),
+            _ => None,
+        }
+    })
+}
+
+/// Frequency actually produced by the given timer settings.
+pub fn actual_frequency(prescaler: Prescaler, top: u16) -> u32 {
+    CPU_FREQUENCY_HZ / (2 * prescaler.divider() * (top as u32 + 1))
+}
+
+/// Number of compare-match interrupts (pin toggles) in `duration_ms` of `freq_hz`.
+pub fn toggle_count(freq_hz: u32, duration_ms: u32) -> u32 {
+    let toggles = (2 * freq_hz as u64 * duration_ms as u64) / 1000;
+    toggles.clamp(1, u32::MAX as u64) as u32
+}
+
+/// Something that plays one note at a time in the background
+pub trait ToneGenerator {
+    /// Start a note (0 Hz = rest) and return immediately.
+    fn play(&mut self, freq_hz: u16, duration_ms: u16);
+
+    /// `true` until the note started by [`play`](Self::play) has finished.
+    fn is_playing(&self) -> bool;
+
+    /// Silence the buzzer right away.
+    fn stop(&mut self);
+}
+
+/// Feeds the notes of a melody to a [`ToneGenerator`], one after the other
+pub struct Player<I> {
+    notes: I,
+}
+
+impl<I> Player<I>
+where
+    I: Iterator<Item = Note>,
+{
+    pub fn new(notes: I) -> Self {
+        Self { notes }
+    }
+
+    /// Start the next note once the current one has finished.
+    ///
+    /// Call this regularly from the main loop. Returns `false` once the
+    /// whole melody has been played.
+    pub fn poll<T: ToneGenerator>(&mut self, tone: &mut T) -> bool {
+        if tone.is_playing() {
+            return true;
+        }
+        match self.notes.next() {
+            Some(note) => {
+                tone.play(note.freq_hz, note.duration_ms);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(target_arch = "avr")]
+pub use self::timer::Tone;
+
+#[cfg(target_arch = "avr")]
+mod timer {
+    use core::cell::{Cell, RefCell};
+
+    use arduino_hal::pac::TC1;
+    use arduino_hal::port::mode::Output;
+    use arduino_hal::port::{Pin, PinOps};
+    use avr_device::interrupt::{self, Mutex};
+
+    use super::{timer_settings, toggle_count, Prescaler, ToneGenerator, REST_FREQUENCY_HZ};
+
+    #[derive(Clone, Copy)]
+    struct Playback {
+        toggles_left: u32,
+        continuous: bool,
+        audible: bool,
+    }
+
+    static PIN: Mutex<RefCell<Option<Pin<Output>>>> = Mutex::new(RefCell::new(None));
+    static PLAYBACK: Mutex<Cell<Option<Playback>>> = Mutex::new(Cell::new(None));
+
+    /// Interrupt-driven tone generator on Timer1
+    ///
+    /// Global interrupts must be enabled for anything to play.
+    pub struct Tone {
+        tc1: TC1,
+    }
+
+    impl Tone {
+        /// Take over Timer1 and drive `pin` with it (any output pin works).
+        pub fn new<PIN: PinOps>(tc1: TC1, pin: Pin<Output, PIN>) -> Self {
+            let mut pin = pin.downgrade();
+            pin.set_low();
+            interrupt::free(|cs| PIN.borrow(cs).replace(Some(pin)));
+
+            // CTC mode (WGM1 = 0b0100), clock stopped until the first note
+            tc1.tccr1a().write(|w| w.wgm1().set(0b00));
+            tc1.tccr1b().write(|w| w.wgm1().set(0b01).cs1().no_clock());
+            Self { tc1 }
+        }
+
+        /// Play `freq_hz` until [`stop`](Self::stop) is called.
+        pub fn start(&mut self, freq_hz: u16) {
+            self.begin(freq_hz as u32, None);
+        }
+
+        fn begin(&mut self, freq_hz: u32, duration_ms: Option<u16>) {
+            let audible = freq_hz != 0;
+            let rate = if audible { freq_hz } else { REST_FREQUENCY_HZ };
+            let Some((prescaler, top)) = timer_settings(rate) else {
+                return;
+            };
+            let playback = Playback {
+                toggles_left: duration_ms.map_or(0, |ms| toggle_count(rate, ms as u32)),
+                continuous: duration_ms.is_none(),
+                audible,
+            };
+
+            self.stop();
+            interrupt::free(|cs| PLAYBACK.borrow(cs).set(Some(playback)));
+            self.tc1.ocr1a().write(|w| w.set(top));
+            self.tc1.tcnt1().write(|w| w.set(0));
+            self.tc1.timsk1().write(|w| w.ocie1a().set_bit());
+            self.tc1.tccr1b().modify(|_, w| match prescaler {
+                Prescaler::Direct => w.cs1().direct(),
+                Prescaler::Div8 => w.cs1().prescale_8(),
+                Prescaler::Div64 => w.cs1().prescale_64(),
+                Prescaler::Div256 => w.cs1().prescale_256(),
+                Prescaler::Div1024 => w.cs1().prescale_1024(),
+            });
+        }
+    }
+
+    impl ToneGenerator for Tone {
+        fn play(&mut self, freq_hz: u16, duration_ms: u16) {
+            self.begin(freq_hz as u32, Some(duration_ms));
+        }
+
+        fn is_playing(&self) -> bool {
+            interrupt::free(|cs| PLAYBACK.borrow(cs).get().is_some())
+        }
+
+        fn stop(&mut self) {
+            self.tc1.tccr1b().modify(|_, w| w.cs1().no_clock());
+            interrupt::free(|cs| finish(cs, &self.tc1));
+        }
+    }
+
+    fn finish(cs: interrupt::CriticalSection, tc1: &TC1) {
+        tc1.timsk1().write(|w| w.ocie1a().clear_bit());
+        PLAYBACK.borrow(cs).set(None);
+        if let Some(pin) = PIN.borrow(cs).borrow_mut().as_mut() {
+            pin.set_low();
+        }
+    }
+
+    #[avr_device::interrupt(atmega328p)]
+    fn TIMER1_COMPA() {
+        interrupt::free(|cs| {
+            let Some(mut playback) = PLAYBACK.borrow(cs).get() else {
+                return;
+            };
+            if playback.audible {
+                if let Some(pin) = PIN.borrow(cs).borrow_mut().as_mut() {
+                    pin.toggle();
+                }
+            }
+            if !playback.continuous {
+                playback.toggles_left -= 1;
+                if playback.toggles_left == 0 {
+                    // SAFETY: Tone owns TC1; only its interrupt bits are touched here
+                    let tc1 = unsafe { &*TC1::ptr() };
+                    tc1.tccr1b().modify(|_, w| w.cs1().no_clock());
+                    finish(cs, tc1);
+                    return;
+                }
+            }
+            PLAYBACK.borrow(cs).set(Some(playback));
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MELODY: &[Note] = &[
+        Note::new(262, 250),
+        Note::new(294, 500),
+        Note::rest(150),
+        Note::new(330, 1000),
+    ];
+
+    #[derive(Default)]
+    struct MockTone {
+        played: Vec<(u16, u16)>,
+        playing: bool,
+    }
+
+    impl ToneGenerator for MockTone {
+        fn play(&mut self, freq_hz: u16, duration_ms: u16) {
+            self.played.push((freq_hz, duration_ms));
+            self.playing = true;
+        }
+
+        fn is_playing(&self) -> bool {
+            self.playing
+        }
+
+        fn stop(&mut self) {
+            self.playing = false;
+        }
+    }
+
+    #[test]
+    fn timer_settings_for_a4() {
+        let (prescaler, top) = timer_settings(440).unwrap();
+        assert_eq!(prescaler, Prescaler::Direct);
+        assert_eq!(top, 18181);
+        assert_eq!(actual_frequency(prescaler, top), 439);
+    }
+
+    #[test]
+    fn low_notes_need_a_prescaler() {
+        let (prescaler, top) = timer_settings(60).unwrap();
+        assert_eq!(prescaler, Prescaler::Div8);
+        assert_eq!(top, 16666);
+        assert_eq!(timer_settings(0), None);
+        assert_eq!(timer_settings(1).unwrap().0, Prescaler::Div256);
+    }
+
+    #[test]
+    fn frequency_error_is_small() {
+        for freq in (31..=8000).step_by(7) {
+            let (prescaler, top) = timer_settings(freq).unwrap();
+            let produced = 2 * prescaler.divider() * (top as u32 + 1) * freq;
+            assert!(produced.abs_diff(CPU_FREQUENCY_HZ) * 1000 <= CPU_FREQUENCY_HZ, "{freq} Hz");
+        }
+    }
+
+    #[test]
+    fn toggles_per_note() {
+        assert_eq!(toggle_count(440, 500), 440);
+        assert_eq!(toggle_count(REST_FREQUENCY_HZ, 150), 150);
+        assert_eq!(toggle_count(100, 1), 1);
+    }
+
+    #[test]
+    fn player_waits_for_each_note() {
+        let mut tone = MockTone::default();
+        let mut player = Player::new(MELODY.iter().copied());
+
+        assert!(player.poll(&mut tone));
+        assert!(player.poll(&mut tone));
+        assert_eq!(tone.played, [(262, 250)]);
+
+        tone.stop();
+        assert!(player.poll(&mut tone));
+        assert_eq!(tone.played.len(), 2);
+    }
+
+    #[test]
+    fn player_finishes() {
+        let mut tone = MockTone::default();
+        let mut player = Player::new(MELODY.iter().copied());
+        while player.poll(&mut tone) {
+            tone.stop();
+        }
+        assert_eq!(tone.played.len(), MELODY.len());
+        assert_eq!(tone.played[2], (0, 150));
+    }
+}
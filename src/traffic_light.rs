@@ -0,0 +1,196 @@
+//! Traffic Light Patterns
+//!
+//! The light patterns of the traffic light example as step tables, and a
+//! [`Sequencer`] that walks through the current pattern in a loop.
+
+use embedded_hal::digital::OutputPin;
+
+/// Which lights are lit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lights {
+    pub red: bool,
+    pub yellow: bool,
+    pub green: bool,
+}
+
+impl Lights {
+    pub const OFF: Lights = Lights::new(false, false, false);
+    pub const RED: Lights = Lights::new(true, false, false);
+    pub const YELLOW: Lights = Lights::new(false, true, false);
+    pub const GREEN: Lights = Lights::new(false, false, true);
+    pub const ALL: Lights = Lights::new(true, true, true);
+
+    pub const fn new(red: bool, yellow: bool, green: bool) -> Self {
+        Self { red, yellow, green }
+    }
+
+    /// Drive the three LED pins (HIGH = lit).
+    pub fn apply<R, Y, G, E>(&self, red: &mut R, yellow: &mut Y, green: &mut G) -> Result<(), E>
+    where
+        R: OutputPin<Error = E>,
+        Y: OutputPin<Error = E>,
+        G: OutputPin<Error = E>,
+    {
+        red.set_state(self.red.into())?;
+        yellow.set_state(self.yellow.into())?;
+        green.set_state(self.green.into())
+    }
+}
+
+/// One step of a pattern: lights to show and for how long
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub lights: Lights,
+    pub duration_ms: u16,
+}
+
+const fn step(lights: Lights, duration_ms: u16) -> Step {
+    Step { lights, duration_ms }
+}
+
+// Red -> Yellow -> Green -> Yellow
+const CHASE: &[Step] = &[
+    step(Lights::RED, 100),
+    step(Lights::YELLOW, 100),
+    step(Lights::GREEN, 100),
+    step(Lights::YELLOW, 100),
+];
+
+// Flash Red fast 3 times, then Green fast 3 times
+const POLICE: &[Step] = &[
+    step(Lights::RED, 40),
+    step(Lights::OFF, 40),
+    step(Lights::RED, 40),
+    step(Lights::OFF, 40),
+    step(Lights::RED, 40),
+    step(Lights::OFF, 40),
+    step(Lights::GREEN, 40),
+    step(Lights::OFF, 40),
+    step(Lights::GREEN, 40),
+    step(Lights::OFF, 40),
+    step(Lights::GREEN, 40),
+    step(Lights::OFF, 40),
+];
+
+// All on, all off
+const WARNING: &[Step] = &[step(Lights::ALL, 200), step(Lights::OFF, 200)];
+
+/// Light patterns, cycled with the button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// The "Knight Rider" bounce
+    Chase,
+    /// Police strobe
+    Police,
+    /// All lights flashing
+    Warning,
+}
+
+impl Pattern {
+    /// Every pattern, in button order
+    pub const ALL: [Pattern; 3] = [Pattern::Chase, Pattern::Police, Pattern::Warning];
+
+    /// The pattern at `index` in [`Pattern::ALL`], wrapping around.
+    pub fn from_index(index: u8) -> Pattern {
+        Self::ALL[index as usize % Self::ALL.len()]
+    }
+
+    /// Position in [`Pattern::ALL`].
+    pub fn index(self) -> u8 {
+        self as u8
+    }
+
+    pub fn steps(self) -> &'static [Step] {
+        match self {
+            Pattern::Chase => CHASE,
+            Pattern::Police => POLICE,
+            Pattern::Warning => WARNING,
+        }
+    }
+
+    /// The pattern the button switches to.
+    pub fn next(self) -> Pattern {
+        match self {
+            Pattern::Chase => Pattern::Police,
+            Pattern::Police => Pattern::Warning,
+            Pattern::Warning => Pattern::Chase,
+        }
+    }
+}
+
+/// Loops through the steps of the current pattern
+pub struct Sequencer {
+    pattern: Pattern,
+    index: usize,
+}
+
+impl Sequencer {
+    pub fn new(pattern: Pattern) -> Self {
+        Self { pattern, index: 0 }
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        self.pattern
+    }
+
+    /// Switch pattern, starting from its first step.
+    pub fn set_pattern(&mut self, pattern: Pattern) {
+        self.pattern = pattern;
+        self.index = 0;
+    }
+
+    /// Return the current step and advance, wrapping at the end of the pattern.
+    pub fn next_step(&mut self) -> Step {
+        let steps = self.pattern.steps();
+        let step = steps[self.index];
+        self.index = (self.index + 1) % steps.len();
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockPin;
+
+    #[test]
+    fn patterns_cycle() {
+        assert_eq!(Pattern::Chase.next(), Pattern::Police);
+        assert_eq!(Pattern::Police.next(), Pattern::Warning);
+        assert_eq!(Pattern::Warning.next(), Pattern::Chase);
+    }
+
+    #[test]
+    fn pattern_indices_round_trip() {
+        for pattern in Pattern::ALL {
+            assert_eq!(Pattern::from_index(pattern.index()), pattern);
+        }
+        assert_eq!(Pattern::from_index(4), Pattern::Police);
+    }
+
+    #[test]
+    fn sequencer_wraps_around() {
+        let mut sequencer = Sequencer::new(Pattern::Warning);
+        assert_eq!(sequencer.next_step().lights, Lights::ALL);
+        assert_eq!(sequencer.next_step().lights, Lights::OFF);
+        assert_eq!(sequencer.next_step().lights, Lights::ALL);
+    }
+
+    #[test]
+    fn set_pattern_restarts() {
+        let mut sequencer = Sequencer::new(Pattern::Chase);
+        sequencer.next_step();
+        sequencer.next_step();
+        sequencer.set_pattern(Pattern::Police);
+        assert_eq!(sequencer.next_step(), step(Lights::RED, 40));
+    }
+
+    #[test]
+    fn apply_drives_pins() {
+        let (mut red, mut yellow, mut green) = (MockPin::new(false), MockPin::new(false), MockPin::new(true));
+        Lights::RED.apply(&mut red, &mut yellow, &mut green).unwrap();
+        assert!(red.level());
+        assert!(!yellow.level());
+        assert!(!green.level());
+    }
+}